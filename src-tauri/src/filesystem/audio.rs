@@ -4,22 +4,20 @@ use std::path::Path;
 
 use minimp3::{Decoder, Frame};
 
+use super::mime::detect_file_type;
+
 pub fn generate_waveform(path: &Path) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, String> {
-    let extension = path
-        .extension()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_lowercase();
+    let mime_type = detect_file_type(path).mime_type;
 
     let mut samples: Vec<f32> = vec![];
 
-    if extension == "wav" {
+    if mime_type == "audio/wav" || mime_type == "audio/x-wav" {
         let mut reader = hound::WavReader::open(path).map_err(|e| e.to_string())?;
         samples = reader
             .samples::<i16>()
             .map(|s| s.map(f32::from).unwrap_or(0.0))
             .collect();
-    } else if extension == "mp3" {
+    } else if mime_type == "audio/mpeg" {
         let mp3_samples = read_mp3_samples(path.to_str().ok_or("Invalid path")?)?;
         samples = mp3_samples.iter().map(|&s| f32::from(s)).collect();
     } else {
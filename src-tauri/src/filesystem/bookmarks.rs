@@ -0,0 +1,343 @@
+use lazy_static::lazy_static;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::State;
+
+use super::utils::{file_id, FileId};
+use super::DIRECTORY;
+use crate::error::Error;
+use crate::{AppState, StateSafe};
+
+lazy_static! {
+    pub static ref BOOKMARKS_FILE_PATH: String = {
+        let mut bookmarks_path = dirs::data_dir().expect("Failed to get base data path");
+        bookmarks_path.push(format!("{}-bookmarks.json", env!("CARGO_PKG_NAME")));
+        bookmarks_path.to_string_lossy().to_string()
+    };
+    static ref BOOKMARKS: Mutex<BookmarkStore> = Mutex::new(BookmarkStore::load());
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Bookmark {
+    id: String,
+    name: String,
+    path: String,
+    mount_point: String,
+    file_id: Option<FileId>,
+    /// Set when the bookmarked entry is gone and couldn't be found anywhere else.
+    broken: bool,
+}
+
+#[derive(Default)]
+struct BookmarkStore {
+    bookmarks: Vec<Bookmark>,
+    /// First half of a rename, waiting for the event with the same tracker id.
+    renamed_from: Option<(Option<usize>, PathBuf)>,
+}
+
+impl BookmarkStore {
+    fn load() -> Self {
+        let bookmarks = fs::read(&BOOKMARKS_FILE_PATH[..])
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default();
+
+        BookmarkStore {
+            bookmarks,
+            renamed_from: None,
+        }
+    }
+
+    fn save(&self) {
+        let result = serde_json::to_vec(&self.bookmarks)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| {
+                fs::write(&BOOKMARKS_FILE_PATH[..], contents).map_err(|e| e.to_string())
+            });
+
+        if let Err(e) = result {
+            eprintln!("Failed to save bookmarks: {}", e);
+        }
+    }
+
+    /// Points bookmarks of `from`, or of something inside it, at the new location.
+    fn move_paths(&mut self, from: &Path, to: &Path) -> bool {
+        let mut changed = false;
+
+        for bookmark in &mut self.bookmarks {
+            let Ok(relative) = Path::new(&bookmark.path).strip_prefix(from) else {
+                continue;
+            };
+            bookmark.path = to.join(relative).to_string_lossy().to_string();
+            bookmark.broken = false;
+            changed = true;
+        }
+
+        changed
+    }
+
+    fn mark_removed(&mut self, path: &Path) -> bool {
+        let mut changed = false;
+
+        for bookmark in &mut self.bookmarks {
+            if !bookmark.broken && Path::new(&bookmark.path).starts_with(path) {
+                bookmark.broken = true;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    /// Fixes broken bookmarks of an entry that showed up at `path` without its old path
+    /// being reported, like when it was moved in from outside the watched directories.
+    fn reattach(&mut self, path: &Path) -> bool {
+        if !self.bookmarks.iter().any(|bookmark| bookmark.broken) {
+            return false;
+        }
+
+        let Some(id) = fs::metadata(path).ok().and_then(|m| file_id(&m)) else {
+            return false;
+        };
+
+        let mut changed = false;
+        for bookmark in &mut self.bookmarks {
+            if bookmark.broken && bookmark.file_id == Some(id) {
+                bookmark.path = path.to_string_lossy().to_string();
+                bookmark.broken = false;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+/// All bookmarks, with the ones whose entry moved without us noticing looked up again.
+/// Bookmarks already marked broken were looked for before, they are fixed by
+/// `handle_fs_event` once their entry shows up again.
+#[tauri::command]
+pub async fn get_bookmarks(state_mux: State<'_, StateSafe>) -> Result<Vec<Bookmark>, Error> {
+    // Gone or replaced, with what is at their path now.
+    let lookups: Vec<(Bookmark, Option<Option<FileId>>)> = {
+        let mut store = BOOKMARKS.lock().unwrap();
+        let mut changed = false;
+        let mut lookups = Vec::new();
+
+        for bookmark in &mut store.bookmarks {
+            let current_id = fs::metadata(&bookmark.path)
+                .ok()
+                .map(|metadata| file_id(&metadata));
+
+            match current_id {
+                Some(id) if id == bookmark.file_id || bookmark.file_id.is_none() => {
+                    changed |= bookmark.broken || bookmark.file_id != id;
+                    bookmark.file_id = id;
+                    bookmark.broken = false;
+                }
+                _ if bookmark.broken => {}
+                current_id => lookups.push((bookmark.clone(), current_id)),
+            }
+        }
+
+        if changed {
+            store.save();
+        }
+        if lookups.is_empty() {
+            return Ok(store.bookmarks.clone());
+        }
+        lookups
+    };
+
+    // Searching stats a lot of paths, so it happens without holding either lock.
+    let results: Vec<(Bookmark, Option<Option<FileId>>, Option<PathBuf>)> = lookups
+        .into_iter()
+        .map(|(bookmark, current_id)| {
+            let found = bookmark.file_id.and_then(|id| {
+                let candidates = candidate_paths(&state_mux.lock().unwrap(), &bookmark);
+                find_by_id(id, &candidates)
+            });
+            (bookmark, current_id, found)
+        })
+        .collect();
+
+    let mut store = BOOKMARKS.lock().unwrap();
+    let mut changed = false;
+
+    for (looked_up, current_id, found) in results {
+        // Skip bookmarks that were moved or removed in the meantime.
+        let Some(bookmark) = store
+            .bookmarks
+            .iter_mut()
+            .find(|bookmark| bookmark.id == looked_up.id && bookmark.path == looked_up.path)
+        else {
+            continue;
+        };
+
+        // Something else took its place or it's gone, the original wins if it was found.
+        match (found, current_id) {
+            (Some(path), _) => {
+                bookmark.path = path.to_string_lossy().to_string();
+                bookmark.broken = false;
+            }
+            (None, Some(id)) => {
+                bookmark.file_id = id;
+                bookmark.broken = false;
+            }
+            (None, None) => bookmark.broken = true,
+        }
+        changed = true;
+    }
+
+    if changed {
+        store.save();
+    }
+
+    Ok(store.bookmarks.clone())
+}
+
+#[tauri::command]
+pub async fn add_bookmark(
+    path: String,
+    name: Option<String>,
+    mount_point: String,
+) -> Result<Bookmark, Error> {
+    let metadata = fs::metadata(&path)?;
+    let mut store = BOOKMARKS.lock().unwrap();
+
+    if let Some(existing) = store
+        .bookmarks
+        .iter()
+        .find(|bookmark| bookmark.path == path)
+    {
+        return Ok(existing.clone());
+    }
+
+    let name = name
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| {
+            Path::new(&path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.clone())
+        });
+
+    let bookmark = Bookmark {
+        id: format!("{:016x}", rand::random::<u64>()),
+        name,
+        path,
+        mount_point,
+        file_id: file_id(&metadata),
+        broken: false,
+    };
+
+    store.bookmarks.push(bookmark.clone());
+    store.save();
+
+    Ok(bookmark)
+}
+
+#[tauri::command]
+pub async fn rename_bookmark(id: String, name: String) -> Result<Bookmark, Error> {
+    let mut store = BOOKMARKS.lock().unwrap();
+
+    let bookmark = store
+        .bookmarks
+        .iter_mut()
+        .find(|bookmark| bookmark.id == id)
+        .ok_or_else(|| Error::Custom(format!("No bookmark with id {}", id)))?;
+    bookmark.name = name;
+    let bookmark = bookmark.clone();
+
+    store.save();
+    Ok(bookmark)
+}
+
+#[tauri::command]
+pub async fn remove_bookmark(id: String) -> Result<(), Error> {
+    let mut store = BOOKMARKS.lock().unwrap();
+    store.bookmarks.retain(|bookmark| bookmark.id != id);
+    store.save();
+    Ok(())
+}
+
+/// Follows renames and moves of bookmarked entries, and marks bookmarks whose entry was
+/// deleted as broken.
+pub fn handle_fs_event(event: &Event) {
+    let paths = &event.paths;
+    if paths.is_empty() {
+        return;
+    }
+
+    let Ok(mut store) = BOOKMARKS.lock() else {
+        return;
+    };
+    if store.bookmarks.is_empty() {
+        return;
+    }
+
+    let changed = match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if paths.len() == 2 => {
+            store.move_paths(&paths[0], &paths[1])
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            store.renamed_from = Some((event.attrs.tracker(), paths[0].clone()));
+            store.mark_removed(&paths[0])
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => match store.renamed_from.take() {
+            Some((tracker, from)) if tracker == event.attrs.tracker() => {
+                store.move_paths(&from, &paths[0])
+            }
+            _ => store.reattach(&paths[0]),
+        },
+        EventKind::Create(_) => store.reattach(&paths[0]),
+        EventKind::Remove(_) => store.mark_removed(&paths[0]),
+        _ => false,
+    };
+
+    if changed {
+        store.save();
+    }
+}
+
+/// Indexed paths on the bookmark's volume that might be the bookmarked entry now,
+/// the ones with the same name first and then every directory.
+fn candidate_paths(state: &AppState, bookmark: &Bookmark) -> Vec<String> {
+    let Some(volume) = state.system_cache.get(&bookmark.mount_point) else {
+        return Vec::new();
+    };
+
+    let name = Path::new(&bookmark.path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let same_name = volume.get(&name).into_iter().flatten();
+    let directories = volume
+        .values()
+        .flatten()
+        .filter(|cached| cached.file_type == DIRECTORY);
+
+    same_name
+        .chain(directories)
+        .map(|cached| cached.file_path.clone())
+        .collect()
+}
+
+/// Finds the entry with `id` among `candidates` by identity, preferring earlier ones.
+fn find_by_id(id: FileId, candidates: &[String]) -> Option<PathBuf> {
+    candidates
+        .par_iter()
+        .find_first(|path| {
+            fs::metadata(path)
+                .ok()
+                .and_then(|metadata| file_id(&metadata))
+                == Some(id)
+        })
+        .map(PathBuf::from)
+}
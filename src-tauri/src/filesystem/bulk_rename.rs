@@ -0,0 +1,450 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use regex::{NoExpand, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use super::cache::FsEventHandler;
+use super::operations::{is_same_entry, validate_name};
+use crate::error::Error;
+use crate::StateSafe;
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+const TEMP_RENAME_PREFIX: &str = ".bytes-rename-";
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub enum CaseChange {
+    Lower,
+    Upper,
+    /// Capitalises the first letter of every word.
+    Title,
+    /// Capitalises only the first letter of the name.
+    Sentence,
+}
+
+/// A single step of a bulk rename. Steps apply to the name without its extension,
+/// except for `Extension`, and run in the order they are given.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum RenameRule {
+    FindReplace {
+        find: String,
+        replace: String,
+        #[serde(default)]
+        case_insensitive: bool,
+    },
+    /// `replace` can reference capture groups as `$1` or `${name}`.
+    Regex {
+        pattern: String,
+        replace: String,
+    },
+    Case {
+        case: CaseChange,
+    },
+    /// Builds a new name from tokens: `{name}`, `{ext}`, `{n}` for the counter,
+    /// `{mtime}` or `{mtime:%Y%m%d}` for the modification date and `{exif}` or
+    /// `{exif:%Y%m%d}` for the date a photo was taken, which falls back to the mtime.
+    Template {
+        template: String,
+        #[serde(default)]
+        counter_start: u64,
+        #[serde(default = "default_counter_step")]
+        counter_step: u64,
+        #[serde(default)]
+        counter_padding: usize,
+    },
+    /// Replaces the extension, an empty string removes it.
+    Extension {
+        extension: String,
+    },
+}
+
+fn default_counter_step() -> u64 {
+    1
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RenamePlan {
+    from: String,
+    to: String,
+    /// Why this rename can't happen, `None` when it is safe.
+    collision: Option<String>,
+    /// Set when the rename was attempted and failed.
+    error: Option<String>,
+}
+
+/// A rule with its regex compiled once for the whole batch.
+enum CompiledRule<'a> {
+    Pattern {
+        regex: Regex,
+        replace: &'a str,
+        /// Whether `$1` style references in `replace` are expanded, only for regex rules.
+        expand: bool,
+    },
+    Other(&'a RenameRule),
+}
+
+/// Works out the new name of every path and, unless `dry_run` is set, renames them.
+/// Nothing is renamed if any item has a collision, the plan is returned either way so
+/// the frontend can show which items are in the way.
+#[tauri::command]
+pub async fn bulk_rename(
+    state_mux: State<'_, StateSafe>,
+    paths: Vec<String>,
+    rules: Vec<RenameRule>,
+    dry_run: bool,
+    mount_point: String,
+) -> Result<Vec<RenamePlan>, Error> {
+    let mut plan = plan_renames(&paths, &rules)?;
+
+    if dry_run {
+        return Ok(plan);
+    }
+
+    let collisions = plan.iter().filter(|item| item.collision.is_some()).count();
+    if collisions > 0 {
+        return Err(Error::Custom(format!(
+            "{} item(s) can't be renamed, nothing was changed",
+            collisions
+        )));
+    }
+
+    apply_renames(&mut plan)?;
+
+    let mut fs_event_manager = FsEventHandler::new(state_mux.deref().clone(), mount_point.into());
+    let renamed: Vec<(PathBuf, PathBuf)> = plan
+        .iter()
+        .filter(|item| item.from != item.to && item.error.is_none())
+        .map(|item| (PathBuf::from(&item.from), PathBuf::from(&item.to)))
+        .collect();
+
+    tokio::spawn(async move {
+        for (from, to) in renamed {
+            fs_event_manager.handle_rename_from(&from);
+            fs_event_manager.handle_rename_to(&to);
+        }
+    });
+
+    Ok(plan)
+}
+
+fn plan_renames(paths: &[String], rules: &[RenameRule]) -> Result<Vec<RenamePlan>, Error> {
+    let compiled = rules
+        .iter()
+        .map(|rule| match rule {
+            RenameRule::Regex { pattern, replace } => Regex::new(pattern)
+                .map(|regex| CompiledRule::Pattern {
+                    regex,
+                    replace,
+                    expand: true,
+                })
+                .map_err(|e| Error::Custom(format!("Invalid pattern: {}", e))),
+            RenameRule::FindReplace {
+                find,
+                replace,
+                case_insensitive: true,
+            } if !find.is_empty() => Regex::new(&format!("(?i){}", regex::escape(find)))
+                .map(|regex| CompiledRule::Pattern {
+                    regex,
+                    replace,
+                    expand: false,
+                })
+                .map_err(|e| Error::Custom(e.to_string())),
+            rule => Ok(CompiledRule::Other(rule)),
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let sources: HashSet<&Path> = paths.iter().map(Path::new).collect();
+    let mut targets: HashMap<PathBuf, usize> = HashMap::new();
+    let mut plan = Vec::with_capacity(paths.len());
+
+    for (index, path) in paths.iter().enumerate() {
+        let from = Path::new(path);
+        let parent = from.parent().unwrap_or_else(|| Path::new(""));
+
+        let new_name = apply_rules(from, index as u64, &compiled)?;
+        let to = parent.join(&new_name);
+
+        let collision = if to == from {
+            None
+        } else if let Err(e) = validate_name(parent, &new_name) {
+            Some(e.to_string())
+        } else if fs::symlink_metadata(&to).is_ok()
+            && !sources.contains(to.as_path())
+            && !is_same_entry(from, &to)
+        {
+            Some(format!("\"{}\" already exists", new_name))
+        } else {
+            None
+        };
+
+        *targets.entry(to.clone()).or_insert(0) += 1;
+
+        plan.push(RenamePlan {
+            from: path.clone(),
+            to: to.to_string_lossy().to_string(),
+            collision,
+            error: None,
+        });
+    }
+
+    for item in &mut plan {
+        if item.collision.is_none() && targets[Path::new(&item.to)] > 1 {
+            item.collision = Some("Another item in this batch gets the same name".to_string());
+        }
+    }
+
+    Ok(plan)
+}
+
+fn apply_rules(path: &Path, index: u64, rules: &[CompiledRule]) -> Result<String, Error> {
+    let mut stem = path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let mut extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    for rule in rules {
+        stem = match rule {
+            CompiledRule::Pattern {
+                regex,
+                replace,
+                expand: true,
+            } => regex.replace_all(&stem, *replace).to_string(),
+            CompiledRule::Pattern {
+                regex,
+                replace,
+                expand: false,
+            } => regex.replace_all(&stem, NoExpand(replace)).to_string(),
+            CompiledRule::Other(RenameRule::FindReplace { find, replace, .. }) => {
+                if find.is_empty() {
+                    stem
+                } else {
+                    stem.replace(find.as_str(), replace)
+                }
+            }
+            CompiledRule::Other(RenameRule::Case { case }) => change_case(&stem, *case),
+            CompiledRule::Other(RenameRule::Template {
+                template,
+                counter_start,
+                counter_step,
+                counter_padding,
+            }) => {
+                let counter = counter_start + index * counter_step;
+                let counter = format!("{:0width$}", counter, width = *counter_padding);
+                expand_template(template, path, &stem, &extension, &counter)?
+            }
+            CompiledRule::Other(RenameRule::Extension { extension: new }) => {
+                extension = new.trim_start_matches('.').to_string();
+                stem
+            }
+            CompiledRule::Other(RenameRule::Regex { .. }) => unreachable!("compiled above"),
+        };
+    }
+
+    if extension.is_empty() {
+        Ok(stem)
+    } else {
+        Ok(format!("{}.{}", stem, extension))
+    }
+}
+
+fn change_case(name: &str, case: CaseChange) -> String {
+    match case {
+        CaseChange::Lower => name.to_lowercase(),
+        CaseChange::Upper => name.to_uppercase(),
+        CaseChange::Title => {
+            let mut result = String::with_capacity(name.len());
+            let mut at_word_start = true;
+            for c in name.chars() {
+                if at_word_start {
+                    result.extend(c.to_uppercase());
+                } else {
+                    result.extend(c.to_lowercase());
+                }
+                at_word_start = !c.is_alphanumeric();
+            }
+            result
+        }
+        CaseChange::Sentence => {
+            let lower = name.to_lowercase();
+            let mut chars = lower.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => lower,
+            }
+        }
+    }
+}
+
+fn expand_template(
+    template: &str,
+    path: &Path,
+    stem: &str,
+    extension: &str,
+    counter: &str,
+) -> Result<String, Error> {
+    let mut result = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| Error::Custom("Unclosed \"{\" in template".to_string()))?;
+
+        let token = &rest[start + 1..end];
+        let (name, format) = match token.split_once(':') {
+            Some((name, format)) => (name, format),
+            None => (token, DEFAULT_DATE_FORMAT),
+        };
+
+        match name {
+            "name" => result.push_str(stem),
+            "ext" => result.push_str(extension),
+            "n" => result.push_str(counter),
+            "mtime" => result.push_str(&format_date(modified_date(path)?, format)?),
+            "exif" => {
+                let date = match exif_date(path) {
+                    Some(date) => date,
+                    None => modified_date(path)?,
+                };
+                result.push_str(&format_date(date, format)?);
+            }
+            _ => return Err(Error::Custom(format!("Unknown token {{{}}}", token))),
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn format_date(date: NaiveDateTime, format: &str) -> Result<String, Error> {
+    let items: Vec<Item> = StrftimeItems::new(format).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(Error::Custom(format!("Invalid date format \"{}\"", format)));
+    }
+
+    Ok(date.format_with_items(items.into_iter()).to_string())
+}
+
+fn modified_date(path: &Path) -> Result<NaiveDateTime, Error> {
+    let modified = fs::metadata(path)?.modified()?;
+    Ok(DateTime::<Local>::from(modified).naive_local())
+}
+
+/// Reads the date a photo was taken from its EXIF data, if it has any.
+fn exif_date(path: &Path) -> Option<NaiveDateTime> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+
+    let field = exif
+        .get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)
+        .or_else(|| exif.get_field(exif::Tag::DateTime, exif::In::PRIMARY))?;
+
+    let date = match &field.value {
+        exif::Value::Ascii(values) => exif::DateTime::from_ascii(values.first()?).ok()?,
+        _ => return None,
+    };
+
+    NaiveDate::from_ymd_opt(date.year.into(), date.month.into(), date.day.into())?.and_hms_opt(
+        date.hour.into(),
+        date.minute.into(),
+        date.second.into(),
+    )
+}
+
+/// Renames in two passes through temporary names, so swaps and chains such as
+/// `a -> b, b -> c` inside one batch don't trip over each other.
+/// A failure in the first pass undoes everything, in the second pass it is recorded
+/// on the item, which is put back under its old name when that is still free.
+/// Neither pass replaces anything that appeared at a target after planning.
+fn apply_renames(plan: &mut [RenamePlan]) -> Result<(), Error> {
+    let mut staged: Vec<(PathBuf, usize)> = Vec::new();
+
+    for (index, item) in plan.iter().enumerate() {
+        if item.from == item.to {
+            continue;
+        }
+
+        let from = Path::new(&item.from);
+        let temp = from.with_file_name(format!(
+            "{}{}-{}",
+            TEMP_RENAME_PREFIX,
+            std::process::id(),
+            index
+        ));
+
+        if let Err(e) = fs::rename(from, &temp) {
+            for (temp, index) in &staged {
+                if let Err(e) = rename_no_clobber(temp, Path::new(&plan[*index].from)) {
+                    eprintln!(
+                        "Could not restore {}, it was left at {}: {}",
+                        plan[*index].from,
+                        temp.to_string_lossy(),
+                        e
+                    );
+                }
+            }
+            return Err(Error::Custom(format!(
+                "Could not rename {}: {}",
+                item.from, e
+            )));
+        }
+        staged.push((temp, index));
+    }
+
+    for (temp, index) in staged {
+        let item = &mut plan[index];
+        if let Err(e) = rename_no_clobber(&temp, Path::new(&item.to)) {
+            item.error = Some(match rename_no_clobber(&temp, Path::new(&item.from)) {
+                Ok(()) => e.to_string(),
+                Err(_) => format!("{}, the item was left at {}", e, temp.to_string_lossy()),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Renames `from` to `to`, failing instead of replacing anything that exists at `to`.
+/// Files are hard linked and unlinked, which fails atomically on an existing target.
+/// Directories, and volumes without hard links, are checked right before the rename.
+fn rename_no_clobber(from: &Path, to: &Path) -> io::Result<()> {
+    if !fs::symlink_metadata(from)?.is_dir() {
+        match fs::hard_link(from, to) {
+            Ok(()) => {
+                return fs::remove_file(from).inspect_err(|_| {
+                    let _ = fs::remove_file(to);
+                })
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Err(e),
+            Err(_) => {}
+        }
+    }
+
+    if fs::symlink_metadata(to).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("\"{}\" already exists", to.to_string_lossy()),
+        ));
+    }
+
+    fs::rename(from, to)
+}
@@ -4,6 +4,9 @@ use std::io::BufReader;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, MutexGuard};
 
+use crate::filesystem::bookmarks::handle_fs_event as handle_bookmarks_event;
+use crate::filesystem::disk_usage::handle_fs_event as handle_folder_size_event;
+use crate::filesystem::tags::handle_fs_event as handle_tags_event;
 use crate::filesystem::{DIRECTORY, FILE};
 use crate::search::build_token_index;
 use crate::{AppState, CachedPath, StateSafe, VolumeCache};
@@ -118,6 +121,10 @@ impl FsEventHandler {
     }
 
     pub fn handle_event(&mut self, event: Event) {
+        handle_folder_size_event(&event);
+        handle_tags_event(&event);
+        handle_bookmarks_event(&event);
+
         let paths = event.paths;
 
         match event.kind {
@@ -0,0 +1,410 @@
+use md5::Md5;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::error::Error;
+
+const HASH_BUFFER_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    Sha1,
+    Md5,
+    Blake3,
+    Crc32,
+}
+
+impl HashAlgorithm {
+    /// Length of the checksum as a hex string.
+    fn hex_len(self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 | HashAlgorithm::Blake3 => 64,
+            HashAlgorithm::Sha1 => 40,
+            HashAlgorithm::Md5 => 32,
+            HashAlgorithm::Crc32 => 8,
+        }
+    }
+
+    /// Works out the algorithm from the name of a checksum file, like `SHA256SUMS`
+    /// or `ubuntu.iso.sha256`.
+    fn from_checksum_file(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        let algorithms = [
+            ("sha256", HashAlgorithm::Sha256),
+            ("sha1", HashAlgorithm::Sha1),
+            ("md5", HashAlgorithm::Md5),
+            ("b3", HashAlgorithm::Blake3),
+            ("blake3", HashAlgorithm::Blake3),
+            ("crc32", HashAlgorithm::Crc32),
+        ];
+
+        algorithms
+            .into_iter()
+            .find(|(kind, _)| {
+                name.ends_with(&format!("{kind}sums"))
+                    || name.ends_with(&format!(".{kind}"))
+                    || name.ends_with(&format!(".{kind}sum"))
+            })
+            .map(|(_, algorithm)| algorithm)
+    }
+
+    /// Parses the tag of a BSD style line, like `SHA256 (file) = ...`.
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag.to_lowercase().replace('-', "").as_str() {
+            "sha256" => Some(HashAlgorithm::Sha256),
+            "sha1" => Some(HashAlgorithm::Sha1),
+            "md5" => Some(HashAlgorithm::Md5),
+            "blake3" => Some(HashAlgorithm::Blake3),
+            "crc32" => Some(HashAlgorithm::Crc32),
+            _ => None,
+        }
+    }
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Sha1(Sha1),
+    Md5(Md5),
+    Blake3(Box<blake3::Hasher>),
+    Crc32(crc32fast::Hasher),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Sha1 => Hasher::Sha1(Sha1::new()),
+            HashAlgorithm::Md5 => Hasher::Md5(Md5::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgorithm::Crc32 => Hasher::Crc32(crc32fast::Hasher::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Md5(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            Hasher::Crc32(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Returns the checksum as a lowercase hex string.
+    fn finalize(self) -> String {
+        match self {
+            Hasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Sha1(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Md5(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+            Hasher::Crc32(hasher) => format!("{:08x}", hasher.finalize()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ChecksumResult {
+    path: String,
+    algorithm: HashAlgorithm,
+    checksum: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyStatus {
+    Passed,
+    Failed,
+    /// The checksum file lists a file that doesn't exist.
+    Missing,
+    /// The file exists but couldn't be read.
+    Unreadable,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct VerifiedFile {
+    path: String,
+    /// The checksum file the expected checksum came from.
+    checksum_file: String,
+    algorithm: HashAlgorithm,
+    expected: String,
+    actual: Option<String>,
+    status: VerifyStatus,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerificationReport {
+    files: Vec<VerifiedFile>,
+    passed: usize,
+    failed: usize,
+    /// Checksum files that couldn't be read and lines that couldn't be parsed.
+    errors: Vec<String>,
+}
+
+/// An expected checksum read from a checksum file.
+struct ChecksumEntry {
+    path: PathBuf,
+    checksum_file: PathBuf,
+    algorithm: HashAlgorithm,
+    expected: String,
+}
+
+/// Hashes every file in `paths` in parallel, each one streamed so large images don't
+/// end up in memory.
+#[tauri::command]
+pub async fn compute_checksums(
+    paths: Vec<String>,
+    algorithm: Option<HashAlgorithm>,
+) -> Result<Vec<ChecksumResult>, Error> {
+    let algorithm = algorithm.unwrap_or_default();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        paths
+            .into_par_iter()
+            .map(|path| match hash_file(Path::new(&path), algorithm) {
+                Ok(checksum) => ChecksumResult {
+                    path,
+                    algorithm,
+                    checksum: Some(checksum),
+                    error: None,
+                },
+                Err(e) => ChecksumResult {
+                    path,
+                    algorithm,
+                    checksum: None,
+                    error: Some(e.to_string()),
+                },
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| Error::Custom(e.to_string()))
+}
+
+/// Checks files against the checksum files found in `path`, or against `path` itself
+/// when it is a checksum file. Paths in checksum files are relative to the checksum file.
+#[tauri::command]
+pub async fn verify_checksums(path: String) -> Result<VerificationReport, Error> {
+    // Finding and hashing the files takes a while, keep it off the async command runtime.
+    tauri::async_runtime::spawn_blocking(move || verify(path))
+        .await
+        .map_err(|e| Error::Custom(e.to_string()))?
+}
+
+fn verify(path: String) -> Result<VerificationReport, Error> {
+    let root = PathBuf::from(&path);
+    let metadata = fs::metadata(&root)?;
+
+    let checksum_files: Vec<PathBuf> = if metadata.is_dir() {
+        WalkDir::new(&root)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .filter(|entry| {
+                HashAlgorithm::from_checksum_file(&entry.file_name().to_string_lossy()).is_some()
+            })
+            .map(|entry| entry.into_path())
+            .collect()
+    } else {
+        vec![root]
+    };
+
+    if checksum_files.is_empty() {
+        return Err(Error::Custom(format!(
+            "No checksum files found in {}",
+            path
+        )));
+    }
+
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+
+    for checksum_file in &checksum_files {
+        match read_checksum_file(checksum_file, &mut errors) {
+            Ok(mut parsed) => entries.append(&mut parsed),
+            Err(e) => errors.push(format!("{}: {}", checksum_file.display(), e)),
+        }
+    }
+
+    let files: Vec<VerifiedFile> = entries.into_par_iter().map(verify_entry).collect();
+    let passed = files
+        .iter()
+        .filter(|file| file.status == VerifyStatus::Passed)
+        .count();
+
+    Ok(VerificationReport {
+        passed,
+        failed: files.len() - passed,
+        files,
+        errors,
+    })
+}
+
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> io::Result<String> {
+    hash_reader(File::open(path)?, algorithm)
+}
+
+/// Hashes at most the first `length` bytes of a file.
+pub fn hash_file_prefix(path: &Path, algorithm: HashAlgorithm, length: u64) -> io::Result<String> {
+    hash_reader(File::open(path)?.take(length), algorithm)
+}
+
+fn hash_reader(mut reader: impl Read, algorithm: HashAlgorithm) -> io::Result<String> {
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0; HASH_BUFFER_SIZE];
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.finalize())
+}
+
+fn verify_entry(entry: ChecksumEntry) -> VerifiedFile {
+    let (actual, status, error) = if !entry.path.exists() {
+        (None, VerifyStatus::Missing, None)
+    } else {
+        match hash_file(&entry.path, entry.algorithm) {
+            Ok(actual) if actual.eq_ignore_ascii_case(&entry.expected) => {
+                (Some(actual), VerifyStatus::Passed, None)
+            }
+            Ok(actual) => (Some(actual), VerifyStatus::Failed, None),
+            Err(e) => (None, VerifyStatus::Unreadable, Some(e.to_string())),
+        }
+    };
+
+    VerifiedFile {
+        path: entry.path.to_string_lossy().to_string(),
+        checksum_file: entry.checksum_file.to_string_lossy().to_string(),
+        algorithm: entry.algorithm,
+        expected: entry.expected,
+        actual,
+        status,
+        error,
+    }
+}
+
+/// Reads the GNU (`<hash>  <file>`, `<hash> *<file>`) and BSD (`SHA256 (<file>) = <hash>`)
+/// formats, as well as files holding just a hash for the file they are named after.
+fn read_checksum_file(path: &Path, errors: &mut Vec<String>) -> io::Result<Vec<ChecksumEntry>> {
+    let contents = fs::read_to_string(path)?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let default_algorithm = HashAlgorithm::from_checksum_file(&name);
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    let mut entries = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let parsed = parse_line(line).and_then(|(tag, expected, file)| {
+            let algorithm = match tag {
+                Some(tag) => HashAlgorithm::from_tag(tag)?,
+                None => default_algorithm.or_else(|| guess_algorithm(expected))?,
+            };
+            if expected.len() != algorithm.hex_len() {
+                return None;
+            }
+
+            let file = match file {
+                Some(file) => directory.join(file),
+                // `image.iso.sha256` holding only the hash of `image.iso`.
+                None if path.extension().is_some() => path.with_extension(""),
+                None => return None,
+            };
+            Some((algorithm, expected.to_lowercase(), file))
+        });
+
+        match parsed {
+            Some((algorithm, expected, file)) => entries.push(ChecksumEntry {
+                path: file,
+                checksum_file: path.to_path_buf(),
+                algorithm,
+                expected,
+            }),
+            None => errors.push(format!(
+                "{}:{}: unrecognized checksum line",
+                path.display(),
+                index + 1
+            )),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Splits a line into its algorithm tag, if it has one, the hash and the file name.
+fn parse_line(line: &str) -> Option<(Option<&str>, &str, Option<String>)> {
+    // GNU tools escape names with backslashes or newlines and mark those lines with a `\`.
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let unescape = |name: &str| {
+        if escaped {
+            name.replace("\\n", "\n").replace("\\\\", "\\")
+        } else {
+            name.to_string()
+        }
+    };
+
+    if let Some((tag, rest)) = line.split_once(" (") {
+        if let Some((file, hash)) = rest.rsplit_once(") = ") {
+            if is_hex(hash) && !tag.contains(char::is_whitespace) {
+                return Some((Some(tag), hash, Some(unescape(file))));
+            }
+        }
+    }
+
+    let (hash, rest) = match line.split_once(' ') {
+        Some((hash, rest)) => (hash, Some(rest)),
+        None => (line.trim(), None),
+    };
+    if !is_hex(hash) {
+        return None;
+    }
+
+    let file = rest
+        .map(|rest| rest.strip_prefix([' ', '*']).unwrap_or(rest))
+        .filter(|file| !file.trim().is_empty())
+        .map(unescape);
+
+    Some((None, hash, file))
+}
+
+/// For checksum files with a generic name like `CHECKSUMS`.
+fn guess_algorithm(hash: &str) -> Option<HashAlgorithm> {
+    match hash.len() {
+        64 => Some(HashAlgorithm::Sha256),
+        40 => Some(HashAlgorithm::Sha1),
+        32 => Some(HashAlgorithm::Md5),
+        8 => Some(HashAlgorithm::Crc32),
+        _ => None,
+    }
+}
+
+fn is_hex(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_hexdigit())
+}
@@ -0,0 +1,645 @@
+use lazy_static::lazy_static;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+use super::transfer::{
+    run_transfer, ConflictPolicy, TransferFailure, TransferMode, TransferOptions, TransferReport,
+};
+use crate::error::Error;
+
+lazy_static! {
+    /// Where the state of both sides after the last sync of a pair of directories is kept,
+    /// which is what tells a change from a deletion in bidirectional syncs.
+    pub static ref SYNC_STATE_DIR_PATH: String = {
+        let mut sync_path = dirs::data_dir().expect("Failed to get base data path");
+        sync_path.push(format!("{}-sync", env!("CARGO_PKG_NAME")));
+        sync_path.to_string_lossy().to_string()
+    };
+}
+
+/// Modification times closer than this count as equal, as FAT only stores them in
+/// 2 second steps and USB drives are usually FAT formatted.
+const MTIME_TOLERANCE_SECS: u64 = 2;
+
+const COMPARE_BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompareMethod {
+    /// Files with the same size and modification time are considered identical.
+    #[default]
+    SizeAndDate,
+    /// Files with the same size are read and compared byte by byte.
+    Content,
+}
+
+/// Status of an entry in the left directory relative to the right one.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum EntryStatus {
+    /// Only exists on the left.
+    Added,
+    /// Only exists on the right.
+    Removed,
+    Changed,
+    Identical,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct EntryInfo {
+    is_dir: bool,
+    size: u64,
+    /// Seconds since the Unix epoch.
+    modified: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ComparedEntry {
+    /// Path relative to both roots.
+    path: String,
+    status: EntryStatus,
+    left: Option<EntryInfo>,
+    right: Option<EntryInfo>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Comparison {
+    left: String,
+    right: String,
+    entries: Vec<ComparedEntry>,
+    /// Entries that couldn't be read, they and everything below them are left out.
+    errors: Vec<TransferFailure>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Makes the right side an exact copy of the left, deleting what the left doesn't have.
+    Mirror,
+    /// Copies new and newer files from left to right, never deleting anything.
+    Update,
+    /// Carries changes made on either side over to the other, entries changed on both
+    /// sides since the last sync are reported as conflicts and left alone.
+    Bidirectional,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum SyncAction {
+    CopyToRight,
+    CopyToLeft,
+    DeleteFromRight,
+    DeleteFromLeft,
+    Conflict,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SyncStep {
+    path: String,
+    is_dir: bool,
+    action: SyncAction,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncResult {
+    steps: Vec<SyncStep>,
+    /// `None` for a dry run or when there was nothing to copy.
+    transfer: Option<TransferReport>,
+    deleted: Vec<String>,
+    failures: Vec<TransferFailure>,
+}
+
+type Scan = BTreeMap<String, EntryInfo>;
+
+/// What a scan found below a root, and what it couldn't read.
+#[derive(Default)]
+struct ScanResult {
+    entries: Scan,
+    /// Relative paths of entries whose metadata or contents couldn't be read.
+    unreadable: HashSet<String>,
+    errors: Vec<TransferFailure>,
+}
+
+#[tauri::command]
+pub async fn compare_directories(
+    left: String,
+    right: String,
+    compare_by: Option<CompareMethod>,
+) -> Result<Comparison, Error> {
+    let (left_root, right_root) = check_roots(&left, &right)?;
+    let compare_by = compare_by.unwrap_or_default();
+
+    // Both trees are walked and possibly read, keep that off the async command runtime.
+    let (entries, errors) =
+        tauri::async_runtime::spawn_blocking(move || compare(&left_root, &right_root, compare_by))
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+    Ok(Comparison {
+        left,
+        right,
+        entries,
+        errors,
+    })
+}
+
+/// Works out what a sync in `mode` would do and, unless `dry_run` is set, does it.
+/// Copies go through the regular transfer machinery, so they report progress and can be cancelled.
+#[tauri::command]
+pub async fn sync_directories(
+    left: String,
+    right: String,
+    mode: SyncMode,
+    compare_by: Option<CompareMethod>,
+    dry_run: bool,
+    window: tauri::Window,
+) -> Result<SyncResult, Error> {
+    let (left_root, right_root) = check_roots(&left, &right)?;
+    let compare_by = compare_by.unwrap_or_default();
+
+    // Scanning and deleting walk both trees, keep them off the async command runtime.
+    let roots = (left_root.clone(), right_root.clone());
+    let plan = tauri::async_runtime::spawn_blocking(move || {
+        prepare_sync(&roots.0, &roots.1, mode, compare_by, dry_run)
+    })
+    .await
+    .map_err(|e| Error::Custom(e.to_string()))?;
+
+    if dry_run {
+        return Ok(SyncResult {
+            steps: plan.steps,
+            transfer: None,
+            deleted: plan.deleted,
+            failures: plan.failures,
+        });
+    }
+
+    let transfer = if plan.copies.is_empty() {
+        None
+    } else {
+        let options = TransferOptions {
+            conflict_policy: ConflictPolicy::Overwrite,
+            ..Default::default()
+        };
+        Some(run_transfer(&plan.copies, TransferMode::Copy, options, window).await)
+    };
+
+    if !transfer.as_ref().is_some_and(|report| report.cancelled) {
+        tauri::async_runtime::spawn_blocking(move || save_baseline(&left_root, &right_root))
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+    }
+
+    Ok(SyncResult {
+        steps: plan.steps,
+        transfer,
+        deleted: plan.deleted,
+        failures: plan.failures,
+    })
+}
+
+/// What a sync does, with the deletions already carried out unless it is a dry run.
+struct SyncPlan {
+    steps: Vec<SyncStep>,
+    deleted: Vec<String>,
+    /// `(source, destination)` pairs left for the transfer.
+    copies: Vec<(PathBuf, PathBuf)>,
+    failures: Vec<TransferFailure>,
+}
+
+fn prepare_sync(
+    left_root: &Path,
+    right_root: &Path,
+    mode: SyncMode,
+    compare_by: CompareMethod,
+    dry_run: bool,
+) -> SyncPlan {
+    // Unreadable entries are left out of the plan, so they are never deleted or overwritten
+    // because the other side looks different.
+    let (entries, mut failures) = compare(left_root, right_root, compare_by);
+
+    let steps = match mode {
+        SyncMode::Mirror | SyncMode::Update => plan_one_way(&entries, mode),
+        SyncMode::Bidirectional => {
+            let baseline = load_baseline(left_root, right_root);
+            let left_scan = scan(left_root).entries;
+            let right_scan = scan(right_root).entries;
+            plan_bidirectional(&entries, &baseline, &left_scan, &right_scan)
+        }
+    };
+
+    let mut deleted = Vec::new();
+    let mut copies = Vec::new();
+
+    if dry_run {
+        return SyncPlan {
+            steps,
+            deleted,
+            copies,
+            failures,
+        };
+    }
+
+    for step in &steps {
+        let left_path = left_root.join(&step.path);
+        let right_path = right_root.join(&step.path);
+
+        let (to_delete, copy) = match step.action {
+            SyncAction::CopyToRight => (None, Some((left_path, right_path))),
+            SyncAction::CopyToLeft => (None, Some((right_path, left_path))),
+            SyncAction::DeleteFromRight => (Some(right_path), None),
+            SyncAction::DeleteFromLeft => (Some(left_path), None),
+            SyncAction::Conflict => (None, None),
+        };
+
+        if let Some(path) = to_delete {
+            match remove_entry(&path) {
+                Ok(()) => deleted.push(path.to_string_lossy().to_string()),
+                Err(e) => failures.push(TransferFailure {
+                    path: path.to_string_lossy().to_string(),
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        if let Some((source, destination)) = copy {
+            // A file replaced by a directory or the other way around can't be overwritten in place.
+            let type_changed = match (
+                fs::symlink_metadata(&source),
+                fs::symlink_metadata(&destination),
+            ) {
+                (Ok(source), Ok(destination)) => source.is_dir() != destination.is_dir(),
+                _ => false,
+            };
+            if type_changed {
+                if let Err(e) = remove_entry(&destination) {
+                    failures.push(TransferFailure {
+                        path: destination.to_string_lossy().to_string(),
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            }
+            copies.push((source, destination));
+        }
+    }
+
+    SyncPlan {
+        steps,
+        deleted,
+        copies,
+        failures,
+    }
+}
+
+fn check_roots(left: &str, right: &str) -> Result<(PathBuf, PathBuf), Error> {
+    let left_root = PathBuf::from(left);
+    let right_root = PathBuf::from(right);
+
+    for root in [&left_root, &right_root] {
+        if !root.is_dir() {
+            return Err(Error::Custom(format!(
+                "{} is not a directory",
+                root.to_string_lossy()
+            )));
+        }
+    }
+
+    if left_root.starts_with(&right_root) || right_root.starts_with(&left_root) {
+        return Err(Error::Custom(
+            "Cannot compare a directory with one inside it".to_string(),
+        ));
+    }
+
+    Ok((left_root, right_root))
+}
+
+/// Lists everything below `root`, keyed by relative path with `/` separators.
+/// Entries that can't be read are recorded and the scan goes on.
+fn scan(root: &Path) -> ScanResult {
+    let mut result = ScanResult::default();
+
+    for entry in WalkDir::new(root).min_depth(1) {
+        let read = entry.and_then(|entry| entry.metadata().map(|metadata| (entry, metadata)));
+
+        let (entry, metadata) = match read {
+            Ok(read) => read,
+            Err(e) => {
+                let path = e.path().unwrap_or(root);
+                result.unreadable.insert(relative_path(root, path));
+                result.errors.push(TransferFailure {
+                    path: path.to_string_lossy().to_string(),
+                    error: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        result.entries.insert(
+            relative_path(root, entry.path()),
+            EntryInfo {
+                is_dir: metadata.is_dir(),
+                size: if metadata.is_dir() { 0 } else { metadata.len() },
+                modified: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or(0),
+            },
+        );
+    }
+
+    result
+}
+
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Compares both sides, leaving out anything at or below an entry that couldn't be read
+/// on either side, whose errors are returned next to the entries.
+fn compare(
+    left_root: &Path,
+    right_root: &Path,
+    method: CompareMethod,
+) -> (Vec<ComparedEntry>, Vec<TransferFailure>) {
+    let left_scan = scan(left_root);
+    let right_scan = scan(right_root);
+    let (left, right) = (&left_scan.entries, &right_scan.entries);
+
+    let unreadable: HashSet<&String> = left_scan
+        .unreadable
+        .iter()
+        .chain(&right_scan.unreadable)
+        .collect();
+    let is_unreadable = |path: &str| {
+        unreadable.iter().any(|unreadable| {
+            unreadable.is_empty()
+                || path == unreadable.as_str()
+                || path
+                    .strip_prefix(unreadable.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    };
+
+    let paths: Vec<&String> = left
+        .keys()
+        .chain(right.keys().filter(|path| !left.contains_key(*path)))
+        .filter(|path| !is_unreadable(path))
+        .collect();
+
+    let mut entries: Vec<ComparedEntry> = paths
+        .par_iter()
+        .filter_map(|path| {
+            let left_info = left.get(*path).copied();
+            let right_info = right.get(*path).copied();
+
+            let status = match (left_info, right_info) {
+                (Some(_), None) => EntryStatus::Added,
+                (None, Some(_)) => EntryStatus::Removed,
+                // Directories on both sides are compared through their contents.
+                (Some(l), Some(r)) if l.is_dir && r.is_dir => return None,
+                (Some(l), Some(r)) if l.is_dir != r.is_dir || l.size != r.size => {
+                    EntryStatus::Changed
+                }
+                (Some(l), Some(r)) => match method {
+                    CompareMethod::SizeAndDate if same_mtime(&l, &r) => EntryStatus::Identical,
+                    CompareMethod::SizeAndDate => EntryStatus::Changed,
+                    CompareMethod::Content => {
+                        match same_content(&left_root.join(path), &right_root.join(path)) {
+                            Ok(true) => EntryStatus::Identical,
+                            _ => EntryStatus::Changed,
+                        }
+                    }
+                },
+                (None, None) => return None,
+            };
+
+            Some(ComparedEntry {
+                path: (*path).clone(),
+                status,
+                left: left_info,
+                right: right_info,
+            })
+        })
+        .collect();
+
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut errors = left_scan.errors;
+    errors.extend(right_scan.errors);
+    (entries, errors)
+}
+
+fn same_mtime(a: &EntryInfo, b: &EntryInfo) -> bool {
+    a.modified.abs_diff(b.modified) <= MTIME_TOLERANCE_SECS
+}
+
+fn same_content(a: &Path, b: &Path) -> io::Result<bool> {
+    let mut a = BufReader::new(File::open(a)?);
+    let mut b = BufReader::new(File::open(b)?);
+    let mut a_buffer = vec![0; COMPARE_BUFFER_SIZE];
+    let mut b_buffer = vec![0; COMPARE_BUFFER_SIZE];
+
+    loop {
+        let read = a.read(&mut a_buffer)?;
+        if read == 0 {
+            // Sizes were equal, so the other file is at its end too unless it grew meanwhile.
+            return Ok(b.read(&mut b_buffer[..1])? == 0);
+        }
+
+        b.read_exact(&mut b_buffer[..read])?;
+        if a_buffer[..read] != b_buffer[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+fn plan_one_way(entries: &[ComparedEntry], mode: SyncMode) -> Vec<SyncStep> {
+    let mut planner = Planner::default();
+
+    for entry in entries {
+        let action = match entry.status {
+            EntryStatus::Added => Some(SyncAction::CopyToRight),
+            EntryStatus::Changed if mode == SyncMode::Mirror => Some(SyncAction::CopyToRight),
+            EntryStatus::Changed => match (entry.left, entry.right) {
+                (Some(l), Some(r)) if l.modified > r.modified + MTIME_TOLERANCE_SECS => {
+                    Some(SyncAction::CopyToRight)
+                }
+                _ => None,
+            },
+            EntryStatus::Removed if mode == SyncMode::Mirror => Some(SyncAction::DeleteFromRight),
+            EntryStatus::Removed | EntryStatus::Identical => None,
+        };
+
+        if let Some(action) = action {
+            planner.push(entry, action);
+        }
+    }
+
+    planner.steps
+}
+
+fn plan_bidirectional(
+    entries: &[ComparedEntry],
+    baseline: &Scan,
+    left_scan: &Scan,
+    right_scan: &Scan,
+) -> Vec<SyncStep> {
+    let mut planner = Planner::default();
+
+    for entry in entries {
+        let known = baseline.get(&entry.path);
+        let unchanged = |info: Option<EntryInfo>| match (known, info) {
+            (Some(known), Some(info)) => {
+                known.is_dir == info.is_dir
+                    && (info.is_dir || (known.size == info.size && same_mtime(known, &info)))
+            }
+            _ => false,
+        };
+
+        let action = match entry.status {
+            EntryStatus::Identical => continue,
+            // Known from the last sync, so it was deleted on the other side since.
+            EntryStatus::Added if known.is_some() => {
+                if unchanged(entry.left) && !changed_below(&entry.path, left_scan, baseline) {
+                    SyncAction::DeleteFromLeft
+                } else {
+                    SyncAction::Conflict
+                }
+            }
+            EntryStatus::Added => SyncAction::CopyToRight,
+            EntryStatus::Removed if known.is_some() => {
+                if unchanged(entry.right) && !changed_below(&entry.path, right_scan, baseline) {
+                    SyncAction::DeleteFromRight
+                } else {
+                    SyncAction::Conflict
+                }
+            }
+            EntryStatus::Removed => SyncAction::CopyToLeft,
+            EntryStatus::Changed => match (unchanged(entry.left), unchanged(entry.right)) {
+                (true, false) => SyncAction::CopyToLeft,
+                (false, true) => SyncAction::CopyToRight,
+                _ => SyncAction::Conflict,
+            },
+        };
+
+        planner.push(entry, action);
+    }
+
+    planner.steps
+}
+
+/// Whether anything inside the directory at `path` is new or different since the last sync.
+fn changed_below(path: &str, scan: &Scan, baseline: &Scan) -> bool {
+    let prefix = format!("{}/", path);
+
+    scan.range(prefix.clone()..)
+        .take_while(|(relative, _)| relative.starts_with(&prefix))
+        .any(|(relative, info)| match baseline.get(relative) {
+            Some(known) => {
+                known.is_dir != info.is_dir
+                    || (!info.is_dir && (known.size != info.size || !same_mtime(known, info)))
+            }
+            None => true,
+        })
+}
+
+/// Collects steps, dropping entries inside a directory that is already copied or deleted as a whole.
+#[derive(Default)]
+struct Planner {
+    steps: Vec<SyncStep>,
+    whole_directories: HashSet<String>,
+}
+
+impl Planner {
+    fn push(&mut self, entry: &ComparedEntry, action: SyncAction) {
+        let covered = Path::new(&entry.path).ancestors().skip(1).any(|ancestor| {
+            self.whole_directories
+                .contains(&*ancestor.to_string_lossy())
+        });
+        if covered {
+            return;
+        }
+
+        let is_dir = match action {
+            SyncAction::CopyToLeft | SyncAction::DeleteFromRight => entry.right,
+            _ => entry.left.or(entry.right),
+        }
+        .is_some_and(|info| info.is_dir);
+
+        if is_dir && action != SyncAction::Conflict {
+            self.whole_directories.insert(entry.path.clone());
+        }
+
+        self.steps.push(SyncStep {
+            path: entry.path.clone(),
+            is_dir,
+            action,
+        });
+    }
+}
+
+fn remove_entry(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// The file holding the sync state of a pair of directories.
+fn baseline_path(left: &Path, right: &Path) -> PathBuf {
+    // FNV-1a, it has to stay stable across builds unlike the std hasher.
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in format!("{}\0{}", left.to_string_lossy(), right.to_string_lossy()).bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    Path::new(&*SYNC_STATE_DIR_PATH).join(format!("{:016x}.json", hash))
+}
+
+fn load_baseline(left: &Path, right: &Path) -> Scan {
+    fs::read_to_string(baseline_path(left, right))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Remembers every entry that is the same on both sides after a sync.
+fn save_baseline(left: &Path, right: &Path) {
+    let (left_scan, right_scan) = match (scan(left), scan(right)) {
+        (left_scan, right_scan) if left_scan.errors.is_empty() && right_scan.errors.is_empty() => {
+            (left_scan.entries, right_scan.entries)
+        }
+        _ => return,
+    };
+
+    let baseline: HashMap<&String, &EntryInfo> = left_scan
+        .iter()
+        .filter(|(path, info)| match right_scan.get(*path) {
+            Some(other) => {
+                info.is_dir == other.is_dir
+                    && (info.is_dir || (info.size == other.size && same_mtime(info, other)))
+            }
+            None => false,
+        })
+        .collect();
+
+    let result = fs::create_dir_all(&*SYNC_STATE_DIR_PATH)
+        .and_then(|_| serde_json::to_string(&baseline).map_err(io::Error::other))
+        .and_then(|contents| fs::write(baseline_path(left, right), contents));
+
+    if let Err(e) = result {
+        eprintln!("Failed to save sync state: {}", e);
+    }
+}
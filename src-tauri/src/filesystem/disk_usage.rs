@@ -0,0 +1,539 @@
+use lazy_static::lazy_static;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+
+use super::utils::device_id;
+use crate::error::Error;
+
+/// Number of entries returned in the largest files and directories lists by default.
+const DEFAULT_TOP_ENTRIES: usize = 50;
+
+/// Levels of the hierarchy returned by default, deeper directories are only summed up.
+const DEFAULT_DEPTH: usize = 3;
+
+/// Size of the blocks `st_blocks` counts in, regardless of the filesystem block size.
+#[cfg(unix)]
+const STAT_BLOCK_SIZE: u64 = 512;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct FolderSize {
+    /// Sum of the files directly inside the directory.
+    own: u64,
+    /// Sum of everything below the directory.
+    total: u64,
+}
+
+lazy_static! {
+    /// Apparent sizes of every directory measured so far. Whenever a directory is in here,
+    /// so are all directories below it, which lets watcher events update sizes in place.
+    static ref FOLDER_SIZES: Mutex<HashMap<PathBuf, FolderSize>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Serialize, Clone)]
+pub struct FolderSizeEvent {
+    path: String,
+    size: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct UsageNode {
+    name: String,
+    path: String,
+    is_dir: bool,
+    /// Sum of file lengths, what the user would get copying the files elsewhere.
+    apparent_size: u64,
+    /// Space actually taken on disk, smaller than the apparent size for sparse files.
+    allocated_size: u64,
+    files: u64,
+    directories: u64,
+    /// Largest first, only filled in up to the requested depth.
+    children: Vec<UsageNode>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct UsageEntry {
+    path: String,
+    apparent_size: u64,
+    allocated_size: u64,
+}
+
+#[derive(Serialize)]
+pub struct DiskUsageReport {
+    root: UsageNode,
+    largest_files: Vec<UsageEntry>,
+    largest_directories: Vec<UsageEntry>,
+    /// Extra names of files already counted through another hard link.
+    hard_links: u64,
+    /// Entries that couldn't be read, usually for lack of permissions.
+    errors: u64,
+}
+
+/// Returns the size of a directory, from the cache when it was measured before.
+#[tauri::command]
+pub async fn get_folder_size(path: String) -> Result<u64, String> {
+    tauri::async_runtime::spawn_blocking(move || folder_size(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Error walking directory: {}", e))
+}
+
+/// Measures the given directories on a background thread and emits a `folder_size`
+/// event for each one as it completes.
+#[tauri::command]
+pub async fn compute_folder_sizes(paths: Vec<String>, window: tauri::Window) -> Result<(), Error> {
+    thread::spawn(move || {
+        for path in paths {
+            match folder_size(Path::new(&path)) {
+                Ok(size) => {
+                    if let Err(e) = window.emit("folder_size", FolderSizeEvent { path, size }) {
+                        eprintln!("Failed to emit folder size: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("Failed to measure {}: {}", path, e),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// The cached size of a directory, if it has been measured.
+pub fn cached_folder_size(path: &Path) -> Option<u64> {
+    FOLDER_SIZES.lock().ok()?.get(path).map(|size| size.total)
+}
+
+fn folder_size(path: &Path) -> std::io::Result<u64> {
+    if let Some(size) = cached_folder_size(path) {
+        return Ok(size);
+    }
+
+    if !fs::symlink_metadata(path)?.is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Not a directory",
+        ));
+    }
+
+    let mut measured = Vec::new();
+    let size = measure(path, &mut measured);
+
+    if let Ok(mut sizes) = FOLDER_SIZES.lock() {
+        sizes.extend(measured);
+    }
+
+    Ok(size.total)
+}
+
+/// Walks a directory, collecting the size of every directory below it. Subtrees that
+/// are already cached aren't walked again.
+fn measure(path: &Path, measured: &mut Vec<(PathBuf, FolderSize)>) -> FolderSize {
+    if let Some(size) = FOLDER_SIZES
+        .lock()
+        .ok()
+        .and_then(|sizes| sizes.get(path).copied())
+    {
+        return size;
+    }
+
+    let mut size = FolderSize::default();
+    let mut directories = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(path) {
+        for entry in entries.filter_map(Result::ok) {
+            match entry.metadata() {
+                Ok(metadata) if metadata.is_dir() => directories.push(entry.path()),
+                Ok(metadata) => size.own += metadata.len(),
+                Err(_) => {}
+            }
+        }
+    }
+
+    let children: Vec<(FolderSize, Vec<(PathBuf, FolderSize)>)> = directories
+        .par_iter()
+        .map(|directory| {
+            let mut measured = Vec::new();
+            let size = measure(directory, &mut measured);
+            (size, measured)
+        })
+        .collect();
+
+    size.total = size.own;
+    for (child, child_measured) in children {
+        size.total += child.total;
+        measured.extend(child_measured);
+    }
+
+    measured.push((path.to_path_buf(), size));
+    size
+}
+
+/// Keeps cached folder sizes up to date with a change reported by a volume watcher.
+pub fn handle_fs_event(event: &Event) {
+    let paths = &event.paths;
+    if paths.is_empty() {
+        return;
+    }
+
+    match event.kind {
+        EventKind::Create(_) => path_added(&paths[0]),
+        EventKind::Remove(_) => path_removed(&paths[0]),
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => path_removed(&paths[0]),
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => path_added(&paths[0]),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if paths.len() == 2 => {
+            path_removed(&paths[0]);
+            path_added(&paths[1]);
+        }
+        EventKind::Modify(ModifyKind::Name(_)) => {
+            for path in paths {
+                if path.exists() {
+                    path_added(path);
+                } else {
+                    path_removed(path);
+                }
+            }
+        }
+        EventKind::Modify(_) => {
+            if let Some(parent) = paths[0].parent() {
+                refresh_own_size(parent);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn path_added(path: &Path) {
+    let Some(parent) = path.parent() else {
+        return;
+    };
+
+    // Only a measured parent needs updating, and a path that is already measured
+    // has been counted in it.
+    let needs_update = FOLDER_SIZES
+        .lock()
+        .map(|sizes| sizes.contains_key(parent) && !sizes.contains_key(path))
+        .unwrap_or(false);
+    if !needs_update {
+        return;
+    }
+
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => {
+            // A directory moved in can bring a whole tree with it.
+            let mut measured = Vec::new();
+            let size = measure(path, &mut measured);
+
+            if let Ok(mut sizes) = FOLDER_SIZES.lock() {
+                sizes.extend(measured);
+                apply_delta(&mut sizes, parent, size.total as i64);
+            }
+        }
+        Ok(_) => refresh_own_size(parent),
+        Err(_) => {}
+    }
+}
+
+fn path_removed(path: &Path) {
+    let Ok(mut sizes) = FOLDER_SIZES.lock() else {
+        return;
+    };
+
+    match sizes.remove(path) {
+        Some(size) => {
+            sizes.retain(|cached, _| !cached.starts_with(path));
+            if let Some(parent) = path.parent() {
+                apply_delta(&mut sizes, parent, -(size.total as i64));
+            }
+        }
+        None => {
+            drop(sizes);
+            if let Some(parent) = path.parent() {
+                refresh_own_size(parent);
+            }
+        }
+    }
+}
+
+/// Sums the files directly inside `directory` again and passes the difference up.
+fn refresh_own_size(directory: &Path) {
+    let cached = FOLDER_SIZES
+        .lock()
+        .ok()
+        .and_then(|sizes| sizes.get(directory).copied());
+    let Some(cached) = cached else {
+        return;
+    };
+
+    let own: u64 = match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| entry.metadata().ok())
+            .filter(|metadata| !metadata.is_dir())
+            .map(|metadata| metadata.len())
+            .sum(),
+        Err(_) => return,
+    };
+
+    if let Ok(mut sizes) = FOLDER_SIZES.lock() {
+        if let Some(size) = sizes.get_mut(directory) {
+            size.own = own;
+        }
+        apply_delta(&mut sizes, directory, own as i64 - cached.own as i64);
+    }
+}
+
+/// Adds `delta` to the total of `directory` and every cached directory above it.
+fn apply_delta(sizes: &mut HashMap<PathBuf, FolderSize>, directory: &Path, delta: i64) {
+    if delta == 0 {
+        return;
+    }
+
+    for ancestor in directory.ancestors() {
+        if let Some(size) = sizes.get_mut(ancestor) {
+            size.total = size.total.saturating_add_signed(delta);
+        }
+    }
+}
+
+/// Breaks down the space used below `path`, staying on the same filesystem.
+#[tauri::command]
+pub async fn analyze_disk_usage(
+    path: String,
+    depth: Option<usize>,
+    top: Option<usize>,
+) -> Result<DiskUsageReport, Error> {
+    let root = PathBuf::from(&path);
+    let metadata = fs::symlink_metadata(&root)?;
+    if !metadata.is_dir() {
+        return Err(Error::Custom(format!("{} is not a directory", path)));
+    }
+
+    let analyzer = Analyzer {
+        device: device_id(&metadata),
+        max_depth: depth.unwrap_or(DEFAULT_DEPTH),
+        top: top.unwrap_or(DEFAULT_TOP_ENTRIES),
+        seen_links: Mutex::new(HashSet::new()),
+    };
+
+    // Walking a large tree takes a while, keep it off the async command runtime.
+    let analysis =
+        tauri::async_runtime::spawn_blocking(move || analyzer.analyze_directory(&root, 0))
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+    Ok(DiskUsageReport {
+        root: analysis.node,
+        largest_files: analysis.largest_files,
+        largest_directories: analysis.largest_directories,
+        hard_links: analysis.hard_links,
+        errors: analysis.errors,
+    })
+}
+
+struct Analyzer {
+    device: Option<u64>,
+    max_depth: usize,
+    top: usize,
+    /// Files with more than one link that were already counted, by device and inode.
+    seen_links: Mutex<HashSet<(u64, u64)>>,
+}
+
+struct Analysis {
+    node: UsageNode,
+    largest_files: Vec<UsageEntry>,
+    largest_directories: Vec<UsageEntry>,
+    hard_links: u64,
+    errors: u64,
+}
+
+impl Analyzer {
+    fn analyze_directory(&self, path: &Path, depth: usize) -> Analysis {
+        let mut analysis = Analysis {
+            node: UsageNode {
+                name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.to_string_lossy().to_string()),
+                path: path.to_string_lossy().to_string(),
+                is_dir: true,
+                apparent_size: 0,
+                allocated_size: 0,
+                files: 0,
+                directories: 0,
+                children: Vec::new(),
+            },
+            largest_files: Vec::new(),
+            largest_directories: Vec::new(),
+            hard_links: 0,
+            errors: 0,
+        };
+
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(_) => {
+                analysis.errors += 1;
+                return analysis;
+            }
+        };
+
+        let mut directories = Vec::new();
+        let show_children = depth < self.max_depth;
+
+        for entry in entries {
+            let Ok(entry) = entry else {
+                analysis.errors += 1;
+                continue;
+            };
+            let Ok(metadata) = entry.metadata() else {
+                analysis.errors += 1;
+                continue;
+            };
+
+            if metadata.is_dir() {
+                // Mount points below the root belong to another volume.
+                if self.device.is_some() && device_id(&metadata) != self.device {
+                    continue;
+                }
+                directories.push(entry.path());
+                continue;
+            }
+
+            if self.already_counted(&metadata) {
+                analysis.hard_links += 1;
+                continue;
+            }
+
+            let apparent_size = metadata.len();
+            let allocated_size = allocated_size(&metadata);
+            let file_path = entry.path();
+
+            analysis.node.apparent_size += apparent_size;
+            analysis.node.allocated_size += allocated_size;
+            analysis.node.files += 1;
+
+            push_largest(
+                &mut analysis.largest_files,
+                UsageEntry {
+                    path: file_path.to_string_lossy().to_string(),
+                    apparent_size,
+                    allocated_size,
+                },
+                self.top,
+            );
+
+            if show_children {
+                analysis.node.children.push(UsageNode {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    path: file_path.to_string_lossy().to_string(),
+                    is_dir: false,
+                    apparent_size,
+                    allocated_size,
+                    files: 1,
+                    directories: 0,
+                    children: Vec::new(),
+                });
+            }
+        }
+
+        let children: Vec<Analysis> = directories
+            .par_iter()
+            .map(|directory| self.analyze_directory(directory, depth + 1))
+            .collect();
+
+        for mut child in children {
+            analysis.node.apparent_size += child.node.apparent_size;
+            analysis.node.allocated_size += child.node.allocated_size;
+            analysis.node.files += child.node.files;
+            analysis.node.directories += child.node.directories + 1;
+            analysis.hard_links += child.hard_links;
+            analysis.errors += child.errors;
+
+            push_largest(
+                &mut analysis.largest_directories,
+                UsageEntry {
+                    path: child.node.path.clone(),
+                    apparent_size: child.node.apparent_size,
+                    allocated_size: child.node.allocated_size,
+                },
+                self.top,
+            );
+            for file in child.largest_files {
+                push_largest(&mut analysis.largest_files, file, self.top);
+            }
+            for directory in child.largest_directories {
+                push_largest(&mut analysis.largest_directories, directory, self.top);
+            }
+
+            if show_children {
+                if depth + 1 >= self.max_depth {
+                    child.node.children.clear();
+                }
+                analysis.node.children.push(child.node);
+            }
+        }
+
+        analysis
+            .node
+            .children
+            .sort_by_key(|child| Reverse(child.allocated_size));
+
+        analysis
+    }
+
+    #[cfg(unix)]
+    fn already_counted(&self, metadata: &fs::Metadata) -> bool {
+        use std::os::unix::fs::MetadataExt;
+
+        if metadata.nlink() <= 1 {
+            return false;
+        }
+
+        match self.seen_links.lock() {
+            Ok(mut seen) => !seen.insert((metadata.dev(), metadata.ino())),
+            Err(_) => false,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn already_counted(&self, _metadata: &fs::Metadata) -> bool {
+        false
+    }
+}
+
+/// Keeps `list` as the `top` largest entries by allocated size, largest first.
+fn push_largest(list: &mut Vec<UsageEntry>, entry: UsageEntry, top: usize) {
+    if top == 0 {
+        return;
+    }
+
+    if list.len() == top
+        && list
+            .last()
+            .is_some_and(|last| last.allocated_size >= entry.allocated_size)
+    {
+        return;
+    }
+
+    let index = list.partition_point(|existing| existing.allocated_size >= entry.allocated_size);
+    list.insert(index, entry);
+    list.truncate(top);
+}
+
+#[cfg(unix)]
+fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.blocks() * STAT_BLOCK_SIZE
+}
+
+#[cfg(not(unix))]
+fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
@@ -0,0 +1,321 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use super::cache::FsEventHandler;
+use super::checksum::{hash_file, hash_file_prefix, HashAlgorithm};
+use super::operations::is_same_entry;
+use super::transfer::TransferFailure;
+use super::utils::{file_id, FileId};
+use super::FILE;
+use crate::error::Error;
+use crate::{AppState, StateSafe};
+
+/// Bytes hashed from the start of files of the same size, which rules out most of them
+/// without reading them completely.
+const PARTIAL_HASH_SIZE: u64 = 64 * 1024;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DuplicateGroup {
+    size: u64,
+    /// BLAKE3 of the content.
+    checksum: String,
+    paths: Vec<String>,
+    /// Space taken by all copies but one.
+    wasted: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateReport {
+    /// Sorted by wasted space, largest first.
+    groups: Vec<DuplicateGroup>,
+    wasted: u64,
+    files_scanned: usize,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateAction {
+    Delete,
+    Trash,
+    /// Replaces the copies with hard links to the kept file, only works on the same volume.
+    Hardlink,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DuplicateResolution {
+    keep: String,
+    duplicates: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResolutionReport {
+    resolved: Vec<String>,
+    /// Bytes given back to the volume, trashed files still take up their space.
+    freed: u64,
+    failures: Vec<TransferFailure>,
+}
+
+/// Finds identical files among the indexed files of `mount_points`, or of all volumes.
+/// Files are grouped by size first, then by a hash of their first bytes and only then
+/// read completely. Hard links to the same file don't count as duplicates.
+#[tauri::command]
+pub async fn find_duplicates(
+    state_mux: State<'_, StateSafe>,
+    mount_points: Option<Vec<String>>,
+    min_size: Option<u64>,
+) -> Result<DuplicateReport, Error> {
+    let paths: Vec<String> = {
+        let state = state_mux.lock().unwrap();
+        state
+            .system_cache
+            .iter()
+            .filter(|(mount_point, _)| match &mount_points {
+                Some(wanted) => wanted.contains(mount_point),
+                None => true,
+            })
+            .flat_map(|(_, cache)| cache.values().flatten())
+            .filter(|cached| cached.file_type == FILE)
+            .map(|cached| cached.file_path.clone())
+            .collect()
+    };
+
+    // Reading and hashing the files takes minutes on large volumes, keep it off the
+    // async command runtime.
+    tauri::async_runtime::spawn_blocking(move || group_duplicates(paths, min_size))
+        .await
+        .map_err(|e| Error::Custom(e.to_string()))
+}
+
+fn group_duplicates(paths: Vec<String>, min_size: Option<u64>) -> DuplicateReport {
+    // Empty files are all identical, but there is nothing to reclaim.
+    let min_size = min_size.unwrap_or(1).max(1);
+    let files: Vec<(u64, Option<FileId>, String)> = paths
+        .into_par_iter()
+        .filter_map(|path| {
+            let metadata = fs::symlink_metadata(&path).ok()?;
+            if !metadata.is_file() || metadata.len() < min_size {
+                return None;
+            }
+            Some((metadata.len(), file_id(&metadata), path))
+        })
+        .collect();
+    let files_scanned = files.len();
+
+    let mut by_size: HashMap<u64, Vec<String>> = HashMap::new();
+    let mut seen_ids = HashSet::new();
+    for (size, id, path) in files {
+        if id.is_some_and(|id| !seen_ids.insert(id)) {
+            continue;
+        }
+        by_size.entry(size).or_default().push(path);
+    }
+    let by_size: Vec<(u64, Vec<String>)> = by_size
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect();
+
+    let by_prefix = split_by_hash(by_size, |path| {
+        hash_file_prefix(path, HashAlgorithm::Blake3, PARTIAL_HASH_SIZE)
+    });
+
+    // Files no larger than the prefix have been hashed completely already.
+    let (complete, partial): (Vec<_>, Vec<_>) = by_prefix
+        .into_iter()
+        .partition(|(size, _, _)| *size <= PARTIAL_HASH_SIZE);
+    let partial = partial
+        .into_iter()
+        .map(|(size, _, paths)| (size, paths))
+        .collect();
+    let by_content = split_by_hash(partial, |path| hash_file(path, HashAlgorithm::Blake3));
+
+    let mut groups: Vec<DuplicateGroup> = complete
+        .into_iter()
+        .chain(by_content)
+        .map(|(size, checksum, mut paths)| {
+            paths.sort();
+            DuplicateGroup {
+                size,
+                checksum,
+                wasted: size * (paths.len() as u64 - 1),
+                paths,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| b.wasted.cmp(&a.wasted).then_with(|| a.paths.cmp(&b.paths)));
+
+    DuplicateReport {
+        wasted: groups.iter().map(|group| group.wasted).sum(),
+        groups,
+        files_scanned,
+    }
+}
+
+/// Deletes, trashes or hard links the duplicates of each kept file. Every duplicate is
+/// hashed again first, so a file that changed since the scan is left alone.
+#[tauri::command]
+pub async fn resolve_duplicates(
+    state_mux: State<'_, StateSafe>,
+    resolutions: Vec<DuplicateResolution>,
+    action: DuplicateAction,
+) -> Result<ResolutionReport, Error> {
+    // Every duplicate is hashed again, keep that off the async command runtime.
+    let report = tauri::async_runtime::spawn_blocking(move || resolve(resolutions, action))
+        .await
+        .map_err(|e| Error::Custom(e.to_string()))?;
+
+    if action != DuplicateAction::Hardlink {
+        let removed: Vec<(String, PathBuf)> = {
+            let state = state_mux.lock().unwrap();
+            report
+                .resolved
+                .iter()
+                .filter_map(|path| {
+                    let mount_point = mount_point_for(&state, Path::new(path))?;
+                    Some((mount_point, PathBuf::from(path)))
+                })
+                .collect()
+        };
+
+        let state_mux = state_mux.deref().clone();
+        tokio::spawn(async move {
+            for (mount_point, path) in removed {
+                FsEventHandler::new(state_mux.clone(), mount_point.into()).handle_delete(&path);
+            }
+        });
+    }
+
+    Ok(report)
+}
+
+fn resolve(resolutions: Vec<DuplicateResolution>, action: DuplicateAction) -> ResolutionReport {
+    let mut resolved = Vec::new();
+    let mut freed = 0;
+    let mut failures = Vec::new();
+
+    for resolution in resolutions {
+        let keep = Path::new(&resolution.keep);
+        let kept = fs::metadata(keep).and_then(|metadata| {
+            hash_file(keep, HashAlgorithm::Blake3).map(|checksum| (metadata.len(), checksum))
+        });
+        let (size, checksum) = match kept {
+            Ok(kept) => kept,
+            Err(e) => {
+                let error = format!("Failed to read {}: {}", resolution.keep, e);
+                for path in resolution.duplicates {
+                    failures.push(TransferFailure {
+                        path,
+                        error: error.clone(),
+                    });
+                }
+                continue;
+            }
+        };
+
+        let checked: Vec<(String, Result<(), String>)> = resolution
+            .duplicates
+            .into_par_iter()
+            .map(|path| {
+                let result = check_identical(keep, Path::new(&path), size, &checksum);
+                (path, result)
+            })
+            .collect();
+
+        for (path, result) in checked {
+            let result = result.and_then(|_| {
+                apply_action(keep, Path::new(&path), action).map_err(|e| e.to_string())
+            });
+
+            match result {
+                Ok(()) => {
+                    if action != DuplicateAction::Trash {
+                        freed += size;
+                    }
+                    resolved.push(path);
+                }
+                Err(error) => failures.push(TransferFailure { path, error }),
+            }
+        }
+    }
+
+    ResolutionReport {
+        resolved,
+        freed,
+        failures,
+    }
+}
+
+/// Splits every group by a hash of its files, dropping files that can't be read and
+/// groups left with a single file.
+fn split_by_hash<F>(groups: Vec<(u64, Vec<String>)>, hash: F) -> Vec<(u64, String, Vec<String>)>
+where
+    F: Fn(&Path) -> io::Result<String> + Sync,
+{
+    groups
+        .into_par_iter()
+        .flat_map_iter(|(size, paths)| {
+            let hashed: Vec<(String, String)> = paths
+                .into_par_iter()
+                .filter_map(|path| hash(Path::new(&path)).ok().map(|checksum| (checksum, path)))
+                .collect();
+
+            let mut by_hash: HashMap<String, Vec<String>> = HashMap::new();
+            for (checksum, path) in hashed {
+                by_hash.entry(checksum).or_default().push(path);
+            }
+
+            by_hash
+                .into_iter()
+                .filter(|(_, paths)| paths.len() > 1)
+                .map(move |(checksum, paths)| (size, checksum, paths))
+        })
+        .collect()
+}
+
+fn check_identical(keep: &Path, path: &Path, size: u64, checksum: &str) -> Result<(), String> {
+    if is_same_entry(keep, path) {
+        return Err("Same file as the one to keep".to_string());
+    }
+
+    let metadata = fs::symlink_metadata(path).map_err(|e| e.to_string())?;
+    let identical = metadata.is_file()
+        && metadata.len() == size
+        && hash_file(path, HashAlgorithm::Blake3).map_err(|e| e.to_string())? == checksum;
+
+    if identical {
+        Ok(())
+    } else {
+        Err(format!("No longer identical to {}", keep.display()))
+    }
+}
+
+fn apply_action(keep: &Path, path: &Path, action: DuplicateAction) -> io::Result<()> {
+    match action {
+        DuplicateAction::Delete => fs::remove_file(path),
+        DuplicateAction::Trash => trash::delete(path).map_err(io::Error::other),
+        DuplicateAction::Hardlink => {
+            // Linked next to the duplicate and renamed over it, so the duplicate is never
+            // missing if linking fails.
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let temporary = path.with_file_name(format!(".{}.link", name));
+            fs::hard_link(keep, &temporary)?;
+            fs::rename(&temporary, path).inspect_err(|_| {
+                let _ = fs::remove_file(&temporary);
+            })
+        }
+    }
+}
+
+/// Finds the indexed volume a path is on, the most specific one for nested mount points.
+fn mount_point_for(state: &AppState, path: &Path) -> Option<String> {
+    state
+        .system_cache
+        .keys()
+        .filter(|mount_point| path.starts_with(mount_point))
+        .max_by_key(|mount_point| mount_point.len())
+        .cloned()
+}
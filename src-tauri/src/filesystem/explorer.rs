@@ -1,26 +1,29 @@
 use crate::error::{Error, GitError};
 use crate::{StateSafe, CREATE_NO_WINDOW};
 use serde::{Deserialize, Serialize};
-use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fs::{self, DirEntry};
+use std::fs;
 use std::io::{self};
 use std::ops::Deref;
 use std::os::windows::process::CommandExt;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use tokio::fs::File;
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
-use walkdir::WalkDir;
+use tokio::io::AsyncReadExt;
 
 #[cfg(target_os = "windows")]
 extern crate winapi;
 
-use super::audio::generate_waveform;
 use super::cache::FsEventHandler;
 use super::git_utils::get_user_git_config_signature;
+use super::hex_preview::read_hex_preview;
+use super::history::{record_access, AccessSource};
+use super::listing::{list_directory, ListOptions};
+use super::text_preview::{read_text_preview, TextRange};
+use super::transfer::{
+    run_transfer, ConflictPolicy, TransferMode, TransferOptions, TransferReport,
+};
 use super::volume::DirectoryChild;
-use super::{get_file_description, AUDIO_EXTENSIONS, IMAGE_EXTENSIONS, TEXT_EXTENSIONS};
 use git2::{ErrorCode, Repository, StashFlags};
 use serde_json::Value as JsonValue;
 use tauri::State;
@@ -30,6 +33,8 @@ use toml::Value as TomlValue;
 pub struct DirectoryResult {
     data: Option<Vec<DirectoryChild>>,
     error: Option<String>,
+    /// Number of entries across all pages when the listing was paged.
+    total: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -63,246 +68,154 @@ pub struct GitMeta {
 }
 
 #[tauri::command]
-pub async fn paste_file_at(from: String, destination: String) -> Result<bool, String> {
-    let from_path = Path::new(&from);
-    let mut dest_path = PathBuf::from(destination);
-
-    let mut counter = 1;
-    while dest_path.exists() {
-        let extension = dest_path
-            .extension()
-            .and_then(|os_str| os_str.to_str())
-            .unwrap_or("");
-        let without_extension = dest_path
-            .file_stem()
-            .and_then(|os_str| os_str.to_str())
-            .unwrap_or("");
-        dest_path.set_file_name(format!(
-            "{}_{}{}",
-            without_extension,
-            counter,
-            if extension.is_empty() {
-                "".to_string()
-            } else {
-                format!(".{}", extension)
-            }
-        ));
-        counter += 1;
-    }
+pub async fn paste_file_at(
+    from: String,
+    destination: String,
+    conflict_policy: Option<ConflictPolicy>,
+    dereference_symlinks: Option<bool>,
+    window: tauri::Window,
+) -> Result<bool, String> {
+    let items = [(PathBuf::from(from), PathBuf::from(destination))];
+    let options = TransferOptions {
+        conflict_policy: conflict_policy.unwrap_or_default(),
+        dereference_symlinks: dereference_symlinks.unwrap_or(false),
+    };
 
-    match fs::copy(&from_path, &dest_path) {
-        Ok(_) => Ok(true),
-        Err(e) => Err(format!("Could not copy file: {}", e)),
-    }
+    let report = run_transfer(&items, TransferMode::Copy, options, window).await;
+
+    report_to_result(report, "Could not copy file")
 }
 
 #[tauri::command]
-pub async fn paste_directory_at(from: String, destination: String) -> Result<bool, String> {
-    let from_path = Path::new(&from);
-    let mut dest_path = PathBuf::from(&destination);
-
-    if !from_path.is_dir() {
+pub async fn paste_directory_at(
+    from: String,
+    destination: String,
+    conflict_policy: Option<ConflictPolicy>,
+    dereference_symlinks: Option<bool>,
+    window: tauri::Window,
+) -> Result<bool, String> {
+    if !Path::new(&from).is_dir() {
         return Err("Source is not a directory".to_string());
     }
 
-    let mut counter = 1;
-    while dest_path.exists() {
-        dest_path = PathBuf::from(format!("{}_{}", destination, counter));
-        counter += 1;
-    }
+    let items = [(PathBuf::from(from), PathBuf::from(destination))];
+    let options = TransferOptions {
+        conflict_policy: conflict_policy.unwrap_or_default(),
+        dereference_symlinks: dereference_symlinks.unwrap_or(false),
+    };
 
-    let copy_result = copy_dir(&from_path, &dest_path);
-    match copy_result {
-        Ok(_) => Ok(true),
-        Err(e) => Err(format!("Could not copy directory: {}", e)),
-    }
-}
+    let report = run_transfer(&items, TransferMode::Copy, options, window).await;
 
-#[tauri::command]
-pub async fn cut_file_from(from: String, destination: String) -> Result<bool, String> {
-    let from_path = Path::new(&from);
-    let dest_path = Path::new(&destination);
-
-    // First, try the rename operation
-    match fs::rename(&from_path, &dest_path) {
-        Ok(_) => Ok(true),
-        Err(_) => {
-            // If rename fails, try copying and then deleting the original
-            match fs::copy(&from_path, &dest_path) {
-                Ok(_) => {
-                    // After copying, delete the original file
-                    match fs::remove_file(&from_path) {
-                        Ok(_) => Ok(true),
-                        Err(e) => Err(format!("Could not delete original file: {}", e)),
-                    }
-                }
-                Err(e) => Err(format!("Could not copy file: {}", e)),
-            }
-        }
-    }
+    report_to_result(report, "Could not copy directory")
 }
 
 #[tauri::command]
-pub async fn cut_directory_from(from: String, destination: String) -> Result<bool, String> {
-    let from_path = Path::new(&from);
-    let dest_path = Path::new(&destination);
+pub async fn cut_file_from(
+    from: String,
+    destination: String,
+    conflict_policy: Option<ConflictPolicy>,
+    window: tauri::Window,
+) -> Result<bool, String> {
+    let items = [(PathBuf::from(from), PathBuf::from(destination))];
+    let options = TransferOptions {
+        conflict_policy: conflict_policy.unwrap_or_default(),
+        ..Default::default()
+    };
 
-    if !from_path.is_dir() {
-        return Err("Source is not a directory".to_string());
-    }
+    let report = run_transfer(&items, TransferMode::Move, options, window).await;
 
-    // First, try the rename operation
-    match fs::rename(&from_path, &dest_path) {
-        Ok(_) => Ok(true),
-        Err(_) => {
-            // If rename fails, try copying the directory recursively and then deleting the original
-            match copy_dir_recursive(&from_path, &dest_path) {
-                Ok(_) => {
-                    // After copying, delete the original directory
-                    match fs::remove_dir_all(&from_path) {
-                        Ok(_) => Ok(true),
-                        Err(e) => Err(format!("Could not delete original directory: {}", e)),
-                    }
-                }
-                Err(e) => Err(format!("Could not copy directory: {}", e)),
-            }
-        }
-    }
+    report_to_result(report, "Could not move file")
 }
 
-fn copy_dir_recursive(src: &Path, dest: &Path) -> std::io::Result<()> {
-    if !src.is_dir() {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            "Source is not a directory",
-        ));
+#[tauri::command]
+pub async fn cut_directory_from(
+    from: String,
+    destination: String,
+    conflict_policy: Option<ConflictPolicy>,
+    window: tauri::Window,
+) -> Result<bool, String> {
+    if !Path::new(&from).is_dir() {
+        return Err("Source is not a directory".to_string());
     }
 
-    fs::create_dir_all(&dest)?;
-
-    for entry_result in src.read_dir()? {
-        let entry = entry_result?;
-        let entry_path = entry.path();
-        let dest_child = dest.join(entry.file_name());
+    let items = [(PathBuf::from(from), PathBuf::from(destination))];
+    let options = TransferOptions {
+        conflict_policy: conflict_policy.unwrap_or_default(),
+        ..Default::default()
+    };
 
-        if entry_path.is_dir() {
-            copy_dir_recursive(&entry_path, &dest_child)?;
-        } else {
-            fs::copy(&entry_path, &dest_child)?;
-        }
-    }
+    let report = run_transfer(&items, TransferMode::Move, options, window).await;
 
-    Ok(())
+    report_to_result(report, "Could not move directory")
 }
 
-// Helper function to recursively copy a directory
-fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
-    if !from.is_dir() {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            "Source is not a directory",
-        ));
+/// Collapses a transfer report for the single item commands.
+/// Returns `Ok(false)` when the item was skipped because of a conflict.
+fn report_to_result(report: TransferReport, context: &str) -> Result<bool, String> {
+    if report.cancelled {
+        return Err(format!("{}: transfer cancelled", context));
     }
 
-    fs::create_dir_all(to)?;
-
-    for entry_result in fs::read_dir(from)? {
-        let entry = entry_result?;
-        let from_path = entry.path();
-        let to_path = to.join(entry.file_name());
-
-        if from_path.is_dir() {
-            copy_dir(&from_path, &to_path)?;
-        } else {
-            fs::copy(&from_path, &to_path)?;
-        }
+    match report.failures.first() {
+        Some(failure) => Err(format!(
+            "{}: {} ({} item(s) failed)",
+            context,
+            failure.error,
+            report.failures.len()
+        )),
+        None => Ok(!report.transferred.is_empty()),
     }
-    Ok(())
 }
 
 #[tauri::command]
-pub async fn open_directory(path: String) -> DirectoryResult {
-    match fetch_directory(path).await {
-        Ok(data) => DirectoryResult {
-            data: Some(data),
-            error: None,
-        },
+pub async fn open_directory(path: String, options: Option<ListOptions>) -> DirectoryResult {
+    let options = options.unwrap_or_default();
+
+    match list_directory(Path::new(&path), &options) {
+        Ok(listing) => {
+            // Further pages of the same listing aren't another visit.
+            if options.offset == 0 {
+                record_access(&path, AccessSource::Directory);
+            }
+            DirectoryResult {
+                data: Some(listing.entries),
+                error: None,
+                total: Some(listing.total),
+            }
+        }
         Err(err) => DirectoryResult {
             data: None,
             error: Some(err.to_string()),
+            total: None,
         },
     }
 }
 
+/// The first lines of a text file, or a hex dump of the start of anything else.
+/// Images and audio are previewed through the `bytes` protocol instead.
 #[tauri::command]
 pub async fn get_file_preview(path: String) -> Result<String, Error> {
-    let extension = get_extension(&path).to_lowercase();
-
-    if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
-        process_image(&path).await
-    } else if TEXT_EXTENSIONS.contains(&extension.as_str()) {
-        process_text(&path).await
-    } else if AUDIO_EXTENSIONS.contains(&extension.as_str()) {
-        process_audio(&path).await
-    } else {
-        Err(Error::Custom("Unsupported file type".to_string()))
-    }
-}
+    let preview = process_text(&path).await?;
 
-fn get_extension(path: &str) -> Cow<str> {
-    Path::new(path)
-        .extension()
-        .unwrap_or_default()
-        .to_string_lossy()
+    record_access(&path, AccessSource::Preview);
+    Ok(preview)
 }
 
 async fn process_text(path: &str) -> Result<String, Error> {
-    let file = File::open(&path)
-        .await
-        .map_err(|e| Error::Custom(e.to_string()))?;
-    let mut reader = BufReader::new(file);
-    let mut preview = String::new();
-    for _ in 0..10 {
-        let mut line = String::new();
-        match reader.read_line(&mut line).await {
-            Ok(bytes) => {
-                if bytes == 0 {
-                    break; // EOF reached
-                }
-                preview.push_str(&line);
-            }
-            Err(e) => return Err(Error::Custom(e.to_string())),
-        }
+    let range = TextRange::Lines {
+        start: 0,
+        count: 10,
+    };
+    let preview = read_text_preview(Path::new(path), range)?;
+    if preview.binary {
+        return process_binary(path).await;
     }
-    Ok(preview)
-}
-
-async fn process_image(path: &str) -> Result<String, Error> {
-    let img = image::open(path).map_err(|e| Error::Custom(e.to_string()))?;
-    let thumbnail = img.resize(150, 150, image::imageops::FilterType::Gaussian);
-
-    // Convert image to bytes
-    let mut buffer = std::io::Cursor::new(Vec::new());
-    thumbnail
-        .write_to(&mut buffer, image::ImageOutputFormat::Png)
-        .map_err(|e| Error::Custom(e.to_string()))?;
-
-    let base64_image = base64::encode(buffer.get_ref());
-    Ok(format!("data:image/png;base64,{base64_image}"))
+    Ok(preview.text)
 }
 
-async fn process_audio(path: &str) -> Result<String, Error> {
-    match generate_waveform(Path::new(&path)) {
-        Ok(img) => {
-            // Convert image to bytes
-            let mut buffer = std::io::Cursor::new(Vec::new());
-            img.write_to(&mut buffer, image::ImageOutputFormat::Png)
-                .map_err(|e| Error::Custom(e.to_string()))?;
-            let base64_image = base64::encode(buffer.get_ref());
-            Ok(format!("data:image/png;base64,{base64_image}"))
-        }
-        Err(e) => Err(Error::Custom(e)),
-    }
+async fn process_binary(path: &str) -> Result<String, Error> {
+    let preview = read_hex_preview(Path::new(path), 0, 256)?;
+    Ok(preview.to_text())
 }
 
 #[tauri::command]
@@ -337,22 +250,6 @@ pub async fn delete_file(
     }
 }
 
-async fn fetch_directory(path: String) -> io::Result<Vec<DirectoryChild>> {
-    let directory = fs::read_dir(&path)?;
-
-    let mut results = Vec::new();
-    for entry in directory.filter_map(Result::ok) {
-        match handle_entry(entry).await {
-            Ok(child_vec) => results.extend(child_vec),
-            Err(e) => {
-                // Handle or log the error as needed.
-            }
-        }
-    }
-
-    Ok(results)
-}
-
 pub fn check_is_supported_project(path: String) -> Result<bool, std::io::Error> {
     // Check for NPM project
     let npm_project_path = Path::new(&path).join("package.json");
@@ -586,60 +483,16 @@ pub async fn get_supported_project_metadata(path: String) -> Result<ProjectMetad
     Err(Error::Custom("Not a supported project".to_string()))
 }
 
-async fn handle_entry(entry: DirEntry) -> io::Result<Vec<DirectoryChild>> {
-    let file_name = entry.file_name().to_string_lossy().to_string();
-    let path = entry.path().to_string_lossy().to_string();
-    let size = fs::metadata(&path)?.len();
-    let last_modified_sys_time = fs::metadata(&path)?.modified()?;
-
-    let last_modified = match last_modified_sys_time.elapsed() {
-        Ok(duration) => duration.as_secs(),
-        Err(_) => 0,
-    };
-
-    let extension = entry
-        .path()
-        .extension()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
-    let file_type = get_file_description(&extension);
-
-    if entry.file_type()?.is_file() {
-        Ok(vec![DirectoryChild::File(
-            file_name,
-            path,
-            size,
-            last_modified,
-            file_type.to_string(),
-        )])
-    } else {
-        let is_git = is_git_directory(&path).unwrap_or(false);
-
-        let is_project = match check_is_supported_project(path.clone()) {
-            Ok(is_project) => is_project,
-            Err(_) => false,
-        };
-
-        Ok(vec![DirectoryChild::Directory(
-            file_name,
-            path,
-            size,
-            last_modified,
-            "File".to_string(),
-            is_git,
-            is_project,
-        )])
-    }
-}
-
 #[tauri::command]
 pub async fn open_file(path: &str) -> Result<(), Error> {
     let commands = open::commands(path);
 
     for mut command in commands {
         match command.output() {
-            Ok(output) if output.status.success() => return Ok(()),
+            Ok(output) if output.status.success() => {
+                record_access(path, AccessSource::Open);
+                return Ok(());
+            }
             Ok(output) => {
                 let err_msg = String::from_utf8(output.stderr)
                     .unwrap_or_else(|_| "Failed to deserialize stderr.".to_string());
@@ -731,21 +584,7 @@ pub fn is_git_directory(path: &str) -> Result<bool, io::Error> {
         return Ok(false);
     }
 
-    let entries = fs::read_dir(path)?;
-
-    for entry in entries {
-        match entry {
-            Ok(entry) => {
-                let path = entry.path();
-                if path.is_dir() && path.file_name().unwrap() == ".git" {
-                    return Ok(true);
-                }
-            }
-            Err(_) => {}
-        }
-    }
-
-    Ok(false)
+    Ok(path.join(".git").is_dir())
 }
 
 #[tauri::command]
@@ -1031,44 +870,28 @@ pub async fn get_files_for_paths(
 
     for path in &paths {
         let path_obj = std::path::Path::new(path);
-        let file_name = match path_obj.file_name() {
-            Some(name) => name.to_string_lossy().to_string(),
-            None => continue, // Skip this entry if it doesn't have a file name
-        };
+        if path_obj.file_name().is_none() {
+            continue; // Skip this entry if it doesn't have a file name
+        }
 
-        let file_result = match fs::metadata(path) {
-            Ok(metadata) => {
-                if metadata.is_file() {
-                    let size = metadata.len();
-                    let last_modified_sys_time = metadata.modified()?;
-                    let last_modified = last_modified_sys_time.elapsed().unwrap().as_secs();
-                    let extension = std::path::Path::new(path)
-                        .extension()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string();
-                    let file_type = get_file_description(&extension);
-
-                    DirectoryResult {
-                        data: Some(vec![DirectoryChild::File(
-                            file_name,
-                            path.to_string(),
-                            size,
-                            last_modified,
-                            file_type.to_string(),
-                        )]),
-                        error: None,
-                    }
-                } else {
-                    DirectoryResult {
-                        data: None,
-                        error: Some("Not a file".to_string()),
-                    }
+        let file_result = match DirectoryChild::from_path(path_obj) {
+            Ok(mut child) if !child.is_dir => {
+                child.detect_content_type();
+                DirectoryResult {
+                    data: Some(vec![child]),
+                    error: None,
+                    total: None,
                 }
             }
+            Ok(_) => DirectoryResult {
+                data: None,
+                error: Some("Not a file".to_string()),
+                total: None,
+            },
             Err(err) => DirectoryResult {
                 data: None,
                 error: Some(err.to_string()),
+                total: None,
             },
         };
 
@@ -1077,22 +900,3 @@ pub async fn get_files_for_paths(
 
     Ok(result_map)
 }
-
-#[tauri::command]
-pub async fn get_folder_size(path: String) -> Result<u64, String> {
-    let mut total_size: u64 = 0;
-
-    for entry in WalkDir::new(path) {
-        let entry = match entry {
-            Ok(e) => e,
-            Err(e) => return Err(format!("Error walking directory: {}", e)),
-        };
-
-        // If it's a file, add its size to the total size.
-        if entry.file_type().is_file() {
-            total_size += entry.metadata().map(|m| m.len()).unwrap_or(0);
-        }
-    }
-
-    Ok(total_size)
-}
@@ -0,0 +1,551 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use pulldown_cmark::{html, Event, Options, Parser, Tag};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+use std::path::Path;
+use toml::Value as TomlValue;
+
+use super::text_preview::{read_text_preview, TextRange};
+use crate::error::Error;
+
+/// Bytes read for a preview, structured files larger than this aren't parsed.
+const MAX_PREVIEW_SIZE: u64 = 1024 * 1024;
+
+/// Table rows returned when the frontend doesn't ask for a number.
+const DEFAULT_ROWS: usize = 100;
+
+/// Nodes of a tree returned at most, the rest are left out.
+const MAX_TREE_NODES: usize = 10_000;
+
+/// Delimiters tried on files that aren't `.tsv`, in order of preference.
+const DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+
+/// Lines looked at to tell the delimiter.
+const DELIMITER_SAMPLE_LINES: usize = 20;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+pub enum FormattedPreview {
+    Markdown(MarkdownPreview),
+    Table(TablePreview),
+    Tree(TreePreview),
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarkdownPreview {
+    /// Rendered without raw HTML, scripts or unsafe links.
+    html: String,
+    /// Set when only the start of the document was rendered.
+    truncated: bool,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    /// Every cell is empty.
+    Empty,
+    Integer,
+    Float,
+    Boolean,
+    Date,
+    Text,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Column {
+    /// From the header row, `None` when the file doesn't have one.
+    name: Option<String>,
+    column_type: ColumnType,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TablePreview {
+    delimiter: char,
+    columns: Vec<Column>,
+    rows: Vec<Vec<String>>,
+    /// Set when the file has more rows than returned.
+    truncated: bool,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Object,
+    Array,
+    String,
+    Number,
+    Boolean,
+    Null,
+    Date,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TreeNode {
+    /// Key in the parent object, `None` for array items and the root.
+    key: Option<String>,
+    kind: NodeKind,
+    /// Scalars only.
+    value: Option<String>,
+    /// Number of entries of objects and arrays, even when their children were left out.
+    length: Option<usize>,
+    children: Vec<TreeNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParseError {
+    message: String,
+    /// Both start at 1, and are missing when the parser doesn't tell.
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TreePreview {
+    /// `JSON`, `YAML` or `TOML`.
+    format: String,
+    /// `None` when the document doesn't parse.
+    root: Option<TreeNode>,
+    error: Option<ParseError>,
+    /// Set when the tree has more than `MAX_TREE_NODES` nodes.
+    truncated: bool,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct FormattedPreviewOptions {
+    /// Table rows to return.
+    rows: Option<usize>,
+    /// Overrides the detected delimiter of tables.
+    delimiter: Option<char>,
+}
+
+/// A preview fitting the format of the file: Markdown is rendered to HTML, CSV and TSV
+/// to a table, and JSON, YAML and TOML to a tree of their values.
+#[tauri::command]
+pub async fn get_formatted_preview(
+    path: String,
+    options: Option<FormattedPreviewOptions>,
+) -> Result<FormattedPreview, Error> {
+    let path = Path::new(&path);
+    let options = options.unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let preview = read_text_preview(
+        path,
+        TextRange::Bytes {
+            start: 0,
+            length: MAX_PREVIEW_SIZE,
+        },
+    )?;
+    if preview.binary {
+        return Err(Error::Custom("Unsupported file type".to_string()));
+    }
+    let text = &preview.text;
+
+    let format = match extension.as_str() {
+        "md" | "markdown" => {
+            return Ok(FormattedPreview::Markdown(MarkdownPreview {
+                html: render_markdown(text),
+                truncated: !preview.eof,
+            }))
+        }
+        "csv" | "tsv" | "tab" => {
+            let delimiter = options
+                .delimiter
+                .unwrap_or_else(|| match extension.as_str() {
+                    "csv" => detect_delimiter(text),
+                    _ => '\t',
+                });
+            if !delimiter.is_ascii() {
+                return Err(Error::Custom("Delimiters must be ASCII".to_string()));
+            }
+            let rows = options.rows.unwrap_or(DEFAULT_ROWS);
+            return Ok(FormattedPreview::Table(parse_table(
+                text,
+                delimiter,
+                rows,
+                preview.eof,
+            )));
+        }
+        "json" => "JSON",
+        "yaml" | "yml" => "YAML",
+        "toml" => "TOML",
+        _ => return Err(Error::Custom("Unsupported file type".to_string())),
+    };
+
+    // Half a document would only show a misleading error at its end.
+    if !preview.eof {
+        return Err(Error::Custom("File is too large to preview".to_string()));
+    }
+
+    let mut builder = TreeBuilder::new();
+    let result = match format {
+        "JSON" => parse_json(text, &mut builder),
+        "YAML" => parse_yaml(text, &mut builder),
+        _ => parse_toml(text, &mut builder),
+    };
+    let (root, error) = match result {
+        Ok(root) => (Some(root), None),
+        Err(error) => (None, Some(error)),
+    };
+
+    Ok(FormattedPreview::Tree(TreePreview {
+        format: format.to_string(),
+        root,
+        error,
+        truncated: builder.truncated,
+    }))
+}
+
+/// Renders Markdown with raw HTML shown as text, and links and images that could run
+/// scripts or read local files left without a target.
+fn render_markdown(text: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+
+    let events = Parser::new_ext(text, options).map(|event| match event {
+        Event::Html(html) => Event::Text(html),
+        Event::Start(Tag::Link(kind, url, title)) if !is_safe_url(&url, false) => {
+            Event::Start(Tag::Link(kind, "".into(), title))
+        }
+        Event::Start(Tag::Image(kind, url, title)) if !is_safe_url(&url, true) => {
+            Event::Start(Tag::Image(kind, "".into(), title))
+        }
+        event => event,
+    });
+
+    let mut html = String::new();
+    html::push_html(&mut html, events);
+    html
+}
+
+/// Web and mail links, and links within the document. Images may also be inline data.
+fn is_safe_url(url: &str, image: bool) -> bool {
+    let url = url.trim().to_lowercase();
+    let scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| scheme)
+        .filter(|scheme| !scheme.contains(['/', '?', '#']));
+
+    match scheme {
+        None => !url.starts_with("//"),
+        Some("http" | "https") => true,
+        Some("mailto") => !image,
+        Some("data") => image && url.starts_with("data:image/") && !url.contains("svg"),
+        Some(_) => false,
+    }
+}
+
+/// The delimiter splitting the first lines into the same number of fields, the one
+/// doing so for most lines wins.
+fn detect_delimiter(text: &str) -> char {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .take(DELIMITER_SAMPLE_LINES)
+        .collect();
+
+    let score = |delimiter: char| {
+        let counts: Vec<usize> = lines
+            .iter()
+            .map(|line| count_outside_quotes(line, delimiter))
+            .collect();
+        match counts.first() {
+            Some(&first) if first > 0 => counts.iter().filter(|&&count| count == first).count(),
+            _ => 0,
+        }
+    };
+
+    // Ties go to the earlier delimiter, `max_by_key` would pick the last.
+    DELIMITERS
+        .iter()
+        .copied()
+        .rev()
+        .max_by_key(|&delimiter| score(delimiter))
+        .filter(|&delimiter| score(delimiter) > 0)
+        .unwrap_or(',')
+}
+
+fn count_outside_quotes(line: &str, delimiter: char) -> usize {
+    let mut quoted = false;
+    line.chars()
+        .filter(|&c| {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            c == delimiter && !quoted
+        })
+        .count()
+}
+
+/// Up to `limit` rows of the table. The first row is taken as a header when it's all
+/// distinct text.
+fn parse_table(text: &str, delimiter: char, limit: usize, complete: bool) -> TablePreview {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter as u8)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let mut rows: Vec<Vec<String>> = reader
+        .records()
+        .map_while(Result::ok)
+        .take(limit.saturating_add(2))
+        .map(|record| record.iter().map(str::to_string).collect())
+        .collect();
+
+    // The last row of a cut off file may be cut off itself.
+    if !complete && rows.len() <= limit.saturating_add(1) {
+        rows.pop();
+    }
+
+    let header = rows.first().is_some_and(|first| {
+        let mut names: Vec<&String> = first.iter().collect();
+        names.sort();
+        names.dedup();
+        names.len() == first.len()
+            && first
+                .iter()
+                .all(|cell| !cell.trim().is_empty() && cell_type(cell) == ColumnType::Text)
+    });
+    let names = match header {
+        true => rows.remove(0),
+        false => Vec::new(),
+    };
+
+    let truncated = rows.len() > limit || !complete;
+    rows.truncate(limit);
+
+    let width = rows
+        .iter()
+        .map(Vec::len)
+        .chain([names.len()])
+        .max()
+        .unwrap_or(0);
+    let columns = (0..width)
+        .map(|index| Column {
+            name: names.get(index).cloned(),
+            column_type: column_type(rows.iter().filter_map(|row| row.get(index))),
+        })
+        .collect();
+
+    TablePreview {
+        delimiter,
+        columns,
+        rows,
+        truncated,
+    }
+}
+
+/// The narrowest type fitting every non-empty cell.
+fn column_type<'a>(cells: impl Iterator<Item = &'a String>) -> ColumnType {
+    cells
+        .filter(|cell| !cell.trim().is_empty())
+        .map(|cell| cell_type(cell))
+        .reduce(|column, cell| match (column, cell) {
+            (a, b) if a == b => a,
+            (ColumnType::Integer, ColumnType::Float) | (ColumnType::Float, ColumnType::Integer) => {
+                ColumnType::Float
+            }
+            _ => ColumnType::Text,
+        })
+        .unwrap_or(ColumnType::Empty)
+}
+
+fn cell_type(cell: &str) -> ColumnType {
+    let cell = cell.trim();
+    if cell.parse::<i64>().is_ok() {
+        ColumnType::Integer
+    } else if cell.parse::<f64>().is_ok_and(f64::is_finite) {
+        ColumnType::Float
+    } else if ["true", "false", "yes", "no"]
+        .iter()
+        .any(|word| word.eq_ignore_ascii_case(cell))
+    {
+        ColumnType::Boolean
+    } else if is_date(cell) {
+        ColumnType::Date
+    } else {
+        ColumnType::Text
+    }
+}
+
+fn is_date(cell: &str) -> bool {
+    NaiveDate::parse_from_str(cell, "%Y-%m-%d").is_ok()
+        || NaiveDateTime::parse_from_str(cell, "%Y-%m-%d %H:%M:%S").is_ok()
+        || DateTime::parse_from_rfc3339(cell).is_ok()
+}
+
+/// Builds trees of at most `MAX_TREE_NODES` nodes, leaving out the rest.
+struct TreeBuilder {
+    nodes_left: usize,
+    truncated: bool,
+}
+
+impl TreeBuilder {
+    fn new() -> Self {
+        TreeBuilder {
+            nodes_left: MAX_TREE_NODES,
+            truncated: false,
+        }
+    }
+
+    fn node(&mut self, key: Option<String>, kind: NodeKind, value: Option<String>) -> TreeNode {
+        self.nodes_left = self.nodes_left.saturating_sub(1);
+        TreeNode {
+            key,
+            kind,
+            value,
+            length: None,
+            children: Vec::new(),
+        }
+    }
+
+    /// An object or array node with a child for each entry, as long as there's room.
+    fn parent<T>(
+        &mut self,
+        key: Option<String>,
+        kind: NodeKind,
+        entries: impl ExactSizeIterator<Item = T>,
+        mut child: impl FnMut(&mut Self, T) -> TreeNode,
+    ) -> TreeNode {
+        let mut node = self.node(key, kind, None);
+        node.length = Some(entries.len());
+        for entry in entries {
+            if self.nodes_left == 0 {
+                self.truncated = true;
+                break;
+            }
+            let child = child(self, entry);
+            node.children.push(child);
+        }
+        node
+    }
+
+    fn json(&mut self, value: &JsonValue, key: Option<String>) -> TreeNode {
+        let (kind, scalar) = match value {
+            JsonValue::Null => (NodeKind::Null, "null".to_string()),
+            JsonValue::Bool(value) => (NodeKind::Boolean, value.to_string()),
+            JsonValue::Number(value) => (NodeKind::Number, value.to_string()),
+            JsonValue::String(value) => (NodeKind::String, value.clone()),
+            JsonValue::Array(items) => {
+                return self.parent(key, NodeKind::Array, items.iter(), |builder, item| {
+                    builder.json(item, None)
+                })
+            }
+            JsonValue::Object(entries) => {
+                return self.parent(key, NodeKind::Object, entries.iter(), |builder, entry| {
+                    builder.json(entry.1, Some(entry.0.clone()))
+                })
+            }
+        };
+        self.node(key, kind, Some(scalar))
+    }
+
+    fn yaml(&mut self, value: &YamlValue, key: Option<String>) -> TreeNode {
+        let (kind, scalar) = match value {
+            YamlValue::Null => (NodeKind::Null, "null".to_string()),
+            YamlValue::Bool(value) => (NodeKind::Boolean, value.to_string()),
+            YamlValue::Number(value) => (NodeKind::Number, value.to_string()),
+            YamlValue::String(value) => (NodeKind::String, value.clone()),
+            // Tags like `!Ref` only say how to read the value, which is shown as it is.
+            YamlValue::Tagged(tagged) => return self.yaml(&tagged.value, key),
+            YamlValue::Sequence(items) => {
+                return self.parent(key, NodeKind::Array, items.iter(), |builder, item| {
+                    builder.yaml(item, None)
+                })
+            }
+            YamlValue::Mapping(entries) => {
+                return self.parent(key, NodeKind::Object, entries.iter(), |builder, entry| {
+                    builder.yaml(entry.1, Some(yaml_key(entry.0)))
+                })
+            }
+        };
+        self.node(key, kind, Some(scalar))
+    }
+
+    fn toml(&mut self, value: &TomlValue, key: Option<String>) -> TreeNode {
+        let (kind, scalar) = match value {
+            TomlValue::String(value) => (NodeKind::String, value.clone()),
+            TomlValue::Integer(value) => (NodeKind::Number, value.to_string()),
+            TomlValue::Float(value) => (NodeKind::Number, value.to_string()),
+            TomlValue::Boolean(value) => (NodeKind::Boolean, value.to_string()),
+            TomlValue::Datetime(value) => (NodeKind::Date, value.to_string()),
+            TomlValue::Array(items) => {
+                return self.parent(key, NodeKind::Array, items.iter(), |builder, item| {
+                    builder.toml(item, None)
+                })
+            }
+            TomlValue::Table(entries) => {
+                return self.parent(key, NodeKind::Object, entries.iter(), |builder, entry| {
+                    builder.toml(entry.1, Some(entry.0.clone()))
+                })
+            }
+        };
+        self.node(key, kind, Some(scalar))
+    }
+}
+
+fn parse_json(text: &str, builder: &mut TreeBuilder) -> Result<TreeNode, ParseError> {
+    let value: JsonValue = serde_json::from_str(text).map_err(|e| ParseError {
+        message: e.to_string(),
+        line: Some(e.line()),
+        column: Some(e.column()),
+    })?;
+    Ok(builder.json(&value, None))
+}
+
+/// Every document of the stream, as an array when there's more than one.
+fn parse_yaml(text: &str, builder: &mut TreeBuilder) -> Result<TreeNode, ParseError> {
+    let mut documents = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(text) {
+        let value = YamlValue::deserialize(document).map_err(|e| ParseError {
+            message: e.to_string(),
+            line: e.location().map(|location| location.line()),
+            column: e.location().map(|location| location.column()),
+        })?;
+        documents.push(value);
+    }
+
+    let value = match documents.len() {
+        0 => YamlValue::Null,
+        1 => documents.remove(0),
+        _ => YamlValue::Sequence(documents),
+    };
+    Ok(builder.yaml(&value, None))
+}
+
+/// Keys of YAML mappings can be any value, not only strings.
+fn yaml_key(key: &YamlValue) -> String {
+    match key {
+        YamlValue::String(key) => key.clone(),
+        key => serde_yaml::to_string(key)
+            .map(|key| key.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn parse_toml(text: &str, builder: &mut TreeBuilder) -> Result<TreeNode, ParseError> {
+    let table: toml::Table = toml::from_str(text).map_err(|e| {
+        let position = e.span().map(|span| line_and_column(text, span.start));
+        ParseError {
+            message: e.message().to_string(),
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+        }
+    })?;
+    Ok(builder.toml(&TomlValue::Table(table), None))
+}
+
+/// Line and column of a byte offset, both starting at 1.
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
@@ -0,0 +1,744 @@
+use chrono::{TimeZone, Utc};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::error::Error;
+
+const BYTES_PER_ROW: u64 = 16;
+
+/// Bytes returned when the frontend doesn't ask for a length.
+const DEFAULT_LENGTH: u64 = 4096;
+
+/// Most bytes returned for a single page.
+const MAX_LENGTH: u64 = 1024 * 1024;
+
+/// Sections, load commands, chunks or entries listed at most for a header.
+const MAX_SECTIONS: usize = 1000;
+
+/// Bytes read from the start of a file to decode its header.
+const HEADER_SIZE: u64 = 4096;
+
+#[derive(Debug, Serialize)]
+pub struct HexRow {
+    offset: u64,
+    /// Space separated bytes, with a wider gap after the eighth.
+    hex: String,
+    /// Printable ASCII, other bytes shown as dots.
+    ascii: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HexPreview {
+    /// Where the page starts, the asked offset rounded down to a row.
+    offset: u64,
+    rows: Vec<HexRow>,
+    total_bytes: u64,
+    eof: bool,
+}
+
+impl HexPreview {
+    /// The page laid out like `hexdump -C` does.
+    pub fn to_text(&self) -> String {
+        self.rows
+            .iter()
+            .map(|row| format!("{:08x}  {:<49} |{}|", row.offset, row.hex, row.ascii))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HeaderField {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HeaderSection {
+    name: String,
+    /// Where the section is in the file.
+    offset: u64,
+    size: u64,
+    /// Where the section is loaded in memory, for executables.
+    address: Option<u64>,
+    details: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BinaryHeader {
+    /// `ELF`, `PE`, `Mach-O`, `Mach-O universal`, `PNG` or `ZIP`.
+    format: String,
+    fields: Vec<HeaderField>,
+    sections: Vec<HeaderSection>,
+    /// Set when not every section could be listed, because there are too many or the
+    /// table is cut off.
+    truncated: bool,
+}
+
+impl BinaryHeader {
+    fn new(format: &str) -> Self {
+        BinaryHeader {
+            format: format.to_string(),
+            fields: Vec::new(),
+            sections: Vec::new(),
+            truncated: false,
+        }
+    }
+
+    fn field(&mut self, name: &str, value: impl ToString) {
+        self.fields.push(HeaderField {
+            name: name.to_string(),
+            value: value.to_string(),
+        });
+    }
+}
+
+/// `length` bytes of `path` from `offset` as hex and ASCII rows. Works for any file.
+#[tauri::command]
+pub async fn get_hex_preview(
+    path: String,
+    offset: Option<u64>,
+    length: Option<u64>,
+) -> Result<HexPreview, Error> {
+    read_hex_preview(
+        Path::new(&path),
+        offset.unwrap_or(0),
+        length.unwrap_or(DEFAULT_LENGTH),
+    )
+}
+
+/// Decodes the header of executables (ELF, PE, Mach-O), PNG images and ZIP archives.
+/// Other formats, and files too damaged to make sense of, have none.
+#[tauri::command]
+pub async fn get_binary_header(path: String) -> Result<Option<BinaryHeader>, Error> {
+    let mut file = File::open(&path)?;
+    let head = read_at(&mut file, 0, HEADER_SIZE)?;
+
+    let header = if head.starts_with(b"\x7fELF") {
+        decode_elf(&mut file, &head)
+    } else if head.starts_with(b"MZ") {
+        decode_pe(&mut file, &head)
+    } else if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        decode_png(&mut file)
+    } else if head.starts_with(b"PK") {
+        decode_zip(&mut file)
+    } else {
+        decode_mach_o(&mut file, &head)
+    };
+
+    Ok(header)
+}
+
+pub fn read_hex_preview(path: &Path, offset: u64, length: u64) -> Result<HexPreview, Error> {
+    let mut file = File::open(path)?;
+    let total_bytes = file.metadata()?.len();
+
+    let offset = offset - offset % BYTES_PER_ROW;
+    let bytes = read_at(&mut file, offset, length.min(MAX_LENGTH))?;
+
+    let rows = bytes
+        .chunks(BYTES_PER_ROW as usize)
+        .enumerate()
+        .map(|(index, row)| HexRow {
+            offset: offset + index as u64 * BYTES_PER_ROW,
+            hex: hex_row(row),
+            ascii: row
+                .iter()
+                .map(|&byte| match byte {
+                    0x20..=0x7e => byte as char,
+                    _ => '.',
+                })
+                .collect(),
+        })
+        .collect();
+
+    Ok(HexPreview {
+        offset,
+        rows,
+        total_bytes,
+        eof: offset + bytes.len() as u64 >= total_bytes,
+    })
+}
+
+fn hex_row(row: &[u8]) -> String {
+    let mut hex = String::with_capacity(49);
+    for (index, byte) in row.iter().enumerate() {
+        if index == 8 {
+            hex.push(' ');
+        }
+        if index > 0 {
+            hex.push(' ');
+        }
+        hex.push_str(&format!("{:02x}", byte));
+    }
+    hex
+}
+
+/// Up to `length` bytes from `offset`, fewer at the end of the file.
+fn read_at(file: &mut File, offset: u64, length: u64) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut bytes = Vec::with_capacity(length.min(MAX_LENGTH) as usize);
+    file.take(length).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Integers read from a byte slice, `None` past its end.
+struct Data<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Data<'a> {
+    fn new(bytes: &'a [u8], big_endian: bool) -> Self {
+        Data { bytes, big_endian }
+    }
+
+    fn array<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        self.bytes.get(offset..offset + N)?.try_into().ok()
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.array(offset)?;
+        Some(match self.big_endian {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.array(offset)?;
+        Some(match self.big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        })
+    }
+
+    fn u64(&self, offset: usize) -> Option<u64> {
+        let bytes = self.array(offset)?;
+        Some(match self.big_endian {
+            true => u64::from_be_bytes(bytes),
+            false => u64::from_le_bytes(bytes),
+        })
+    }
+
+    /// A 64 bit integer in 64 bit formats, a 32 bit one otherwise.
+    fn word(&self, offset: usize, wide: bool) -> Option<u64> {
+        match wide {
+            true => self.u64(offset),
+            false => self.u32(offset).map(u64::from),
+        }
+    }
+
+    /// A NUL terminated string of at most `length` bytes.
+    fn string(&self, offset: usize, length: usize) -> Option<String> {
+        let bytes = self.bytes.get(offset..)?;
+        let bytes = &bytes[..length.min(bytes.len())];
+        let end = bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(bytes.len());
+        Some(String::from_utf8_lossy(&bytes[..end]).to_string())
+    }
+}
+
+fn decode_elf(file: &mut File, head: &[u8]) -> Option<BinaryHeader> {
+    let wide = match head.get(4)? {
+        1 => false,
+        2 => true,
+        _ => return None,
+    };
+    let big_endian = *head.get(5)? == 2;
+    let data = Data::new(head, big_endian);
+
+    let mut header = BinaryHeader::new("ELF");
+    header.field("Class", if wide { "64-bit" } else { "32-bit" });
+    header.field("Byte order", byte_order(big_endian));
+    header.field(
+        "OS ABI",
+        match head.get(7)? {
+            0 => "System V",
+            3 => "Linux",
+            6 => "Solaris",
+            9 => "FreeBSD",
+            12 => "OpenBSD",
+            _ => "Other",
+        },
+    );
+    header.field(
+        "Type",
+        match data.u16(16)? {
+            1 => "Relocatable",
+            2 => "Executable",
+            3 => "Shared object",
+            4 => "Core dump",
+            _ => "Unknown",
+        },
+    );
+    header.field(
+        "Architecture",
+        match data.u16(18)? {
+            3 => "x86",
+            8 => "MIPS",
+            20 => "PowerPC",
+            21 => "PowerPC64",
+            40 => "ARM",
+            62 => "x86-64",
+            183 => "AArch64",
+            243 => "RISC-V",
+            258 => "LoongArch",
+            _ => "Unknown",
+        },
+    );
+    header.field("Entry point", format!("{:#x}", data.word(24, wide)?));
+
+    // Offsets past the entry point move by four bytes in 64 bit files.
+    let shift = if wide { 12 } else { 0 };
+    let section_offset = data.word(32 + if wide { 8 } else { 0 }, wide)?;
+    let program_headers = data.u16(44 + shift)?;
+    let entry_size = data.u16(46 + shift)? as u64;
+    let count = data.u16(48 + shift)? as u64;
+    let names_index = data.u16(50 + shift)? as u64;
+    header.field("Program headers", program_headers);
+    header.field("Section headers", count);
+
+    if section_offset == 0 || count == 0 {
+        return Some(header);
+    }
+    let listed = count.min(MAX_SECTIONS as u64);
+    let table = read_at(file, section_offset, entry_size * listed).ok()?;
+    let table = Data::new(&table, big_endian);
+    header.truncated = listed < count;
+
+    // Section names are offsets into the string table section, which may be past the
+    // listed ones. Sections are still listed without names when it can't be read.
+    let names = (names_index < count)
+        .then(|| {
+            let start = section_offset + names_index * entry_size;
+            let entry = read_at(file, start, entry_size).ok()?;
+            let entry = Data::new(&entry, big_endian);
+            let offset = entry.word(if wide { 24 } else { 16 }, wide)?;
+            let size = entry.word(if wide { 32 } else { 20 }, wide)?;
+            read_at(file, offset, size.min(MAX_LENGTH)).ok()
+        })
+        .flatten()
+        .unwrap_or_default();
+    let names = Data::new(&names, big_endian);
+
+    for index in 0..listed {
+        let start = (index * entry_size) as usize;
+        // The file ends inside the table, list what is there.
+        let Some((kind, address, offset, size)) = elf_section(&table, start, wide) else {
+            header.truncated = true;
+            break;
+        };
+        let name = table
+            .u32(start)
+            .and_then(|name| names.string(name as usize, 256))
+            .unwrap_or_default();
+        let kind = match kind {
+            0 => continue,
+            1 => "Program data",
+            2 => "Symbol table",
+            3 => "String table",
+            4 => "Relocations",
+            6 => "Dynamic linking",
+            7 => "Note",
+            8 => "Uninitialised data",
+            11 => "Dynamic symbols",
+            _ => "Other",
+        };
+
+        header.sections.push(HeaderSection {
+            name,
+            offset,
+            size,
+            address: (address != 0).then_some(address),
+            details: Some(kind.to_string()),
+        });
+    }
+
+    Some(header)
+}
+
+/// The type, address, offset and size of the section header at `start`.
+fn elf_section(table: &Data, start: usize, wide: bool) -> Option<(u32, u64, u64, u64)> {
+    let kind = table.u32(start + 4)?;
+    match wide {
+        true => Some((
+            kind,
+            table.u64(start + 16)?,
+            table.u64(start + 24)?,
+            table.u64(start + 32)?,
+        )),
+        false => Some((
+            kind,
+            table.u32(start + 12)? as u64,
+            table.u32(start + 16)? as u64,
+            table.u32(start + 20)? as u64,
+        )),
+    }
+}
+
+fn decode_pe(file: &mut File, head: &[u8]) -> Option<BinaryHeader> {
+    let dos = Data::new(head, false);
+    let pe_offset = dos.u32(0x3c)? as u64;
+    let pe = read_at(file, pe_offset, 24 + 240).ok()?;
+    if !pe.starts_with(b"PE\0\0") {
+        // A DOS program without a Windows one behind it.
+        let mut header = BinaryHeader::new("MZ");
+        header.field("Type", "DOS executable");
+        return Some(header);
+    }
+    let data = Data::new(&pe, false);
+
+    let mut header = BinaryHeader::new("PE");
+    header.field(
+        "Architecture",
+        match data.u16(4)? {
+            0x14c => "x86",
+            0x8664 => "x86-64",
+            0x1c0 | 0x1c4 => "ARM",
+            0xaa64 => "AArch64",
+            0x5064 => "RISC-V",
+            _ => "Unknown",
+        },
+    );
+    let count = data.u16(6)? as u64;
+    let timestamp = data.u32(8)? as i64;
+    if let Some(time) = Utc.timestamp_opt(timestamp, 0).single() {
+        header.field("Linked", time.to_rfc3339());
+    }
+    let optional_size = data.u16(20)? as u64;
+    let characteristics = data.u16(22)?;
+    header.field(
+        "Type",
+        if characteristics & 0x2000 != 0 {
+            "Dynamic-link library"
+        } else {
+            "Executable"
+        },
+    );
+
+    // The optional header is only optional for object files.
+    if optional_size > 0 {
+        let wide = match data.u16(24)? {
+            0x10b => false,
+            0x20b => true,
+            _ => return Some(header),
+        };
+        header.field("Format", if wide { "PE32+" } else { "PE32" });
+        header.field("Entry point", format!("{:#x}", data.u32(24 + 16)?));
+        let image_base = match wide {
+            true => data.u64(24 + 24)?,
+            false => data.u32(24 + 28)? as u64,
+        };
+        header.field("Image base", format!("{:#x}", image_base));
+        header.field(
+            "Subsystem",
+            match data.u16(24 + 68)? {
+                1 => "Native",
+                2 => "Windows GUI",
+                3 => "Windows console",
+                10..=13 => "EFI",
+                _ => "Other",
+            },
+        );
+    }
+    header.field("Sections", count);
+
+    let table_offset = pe_offset + 24 + optional_size;
+    let table = read_at(file, table_offset, 40 * count.min(MAX_SECTIONS as u64)).ok()?;
+    let table = Data::new(&table, false);
+    for start in (0..table.bytes.len() / 40).map(|index| index * 40) {
+        header.sections.push(HeaderSection {
+            name: table.string(start, 8)?,
+            offset: table.u32(start + 20)? as u64,
+            size: table.u32(start + 16)? as u64,
+            // Relative to the image base.
+            address: Some(table.u32(start + 12)? as u64),
+            details: Some(format!("{} bytes in memory", table.u32(start + 8)?)),
+        });
+    }
+
+    Some(header)
+}
+
+fn decode_mach_o(file: &mut File, head: &[u8]) -> Option<BinaryHeader> {
+    let magic = head.get(..4)?;
+    let (wide, big_endian) = match magic {
+        b"\xfe\xed\xfa\xce" => (false, true),
+        b"\xfe\xed\xfa\xcf" => (true, true),
+        b"\xce\xfa\xed\xfe" => (false, false),
+        b"\xcf\xfa\xed\xfe" => (true, false),
+        b"\xca\xfe\xba\xbe" => return decode_universal(head),
+        _ => return None,
+    };
+    let data = Data::new(head, big_endian);
+
+    let mut header = BinaryHeader::new("Mach-O");
+    header.field("Class", if wide { "64-bit" } else { "32-bit" });
+    header.field("Byte order", byte_order(big_endian));
+    header.field("Architecture", cpu_type(data.u32(4)?));
+    header.field(
+        "Type",
+        match data.u32(12)? {
+            1 => "Object",
+            2 => "Executable",
+            6 => "Dynamic library",
+            7 => "Dynamic linker",
+            8 => "Bundle",
+            10 => "Debug symbols",
+            _ => "Other",
+        },
+    );
+    let commands = data.u32(16)?;
+    let commands_size = data.u32(20)? as u64;
+    header.field("Load commands", commands);
+
+    // Segments are the parts of the file that get mapped into memory.
+    let header_size = if wide { 32 } else { 28 };
+    let commands_data = read_at(file, header_size, commands_size.min(MAX_LENGTH)).ok()?;
+    let commands_data = Data::new(&commands_data, big_endian);
+    let mut start = 0;
+    header.truncated = commands as usize > MAX_SECTIONS;
+    for _ in 0..commands.min(MAX_SECTIONS as u32) {
+        // The commands were cut off when read, list the complete ones.
+        let Some((size, segment)) = mach_o_command(&commands_data, start) else {
+            header.truncated = true;
+            break;
+        };
+        if size == 0 {
+            break;
+        }
+
+        header.sections.extend(segment);
+        start += size;
+    }
+
+    Some(header)
+}
+
+/// The size of the load command at `start`, and the segment it maps if it is one.
+fn mach_o_command(data: &Data, start: usize) -> Option<(usize, Option<HeaderSection>)> {
+    let command = data.u32(start)?;
+    let size = data.u32(start + 4)? as usize;
+
+    let (address, offset, file_size, memory_size) = match command {
+        0x1 => (
+            data.u32(start + 24)? as u64,
+            data.u32(start + 32)? as u64,
+            data.u32(start + 36)? as u64,
+            data.u32(start + 28)? as u64,
+        ),
+        0x19 => (
+            data.u64(start + 24)?,
+            data.u64(start + 40)?,
+            data.u64(start + 48)?,
+            data.u64(start + 32)?,
+        ),
+        _ => return Some((size, None)),
+    };
+
+    let segment = HeaderSection {
+        name: data.string(start + 8, 16)?,
+        offset,
+        size: file_size,
+        address: Some(address),
+        details: Some(format!("{} bytes in memory", memory_size)),
+    };
+    Some((size, Some(segment)))
+}
+
+/// Universal binaries hold a Mach-O binary for each architecture. Java class files
+/// share their magic, but have a version of 45 or more where these have a small count.
+fn decode_universal(head: &[u8]) -> Option<BinaryHeader> {
+    let data = Data::new(head, true);
+    let count = data.u32(4)?;
+    if count == 0 || count >= 45 {
+        return None;
+    }
+
+    let mut header = BinaryHeader::new("Mach-O universal");
+    header.field("Architectures", count);
+    for start in (0..count as usize).map(|index| 8 + index * 20) {
+        header.sections.push(HeaderSection {
+            name: cpu_type(data.u32(start)?).to_string(),
+            offset: data.u32(start + 8)? as u64,
+            size: data.u32(start + 12)? as u64,
+            address: None,
+            details: None,
+        });
+    }
+
+    Some(header)
+}
+
+fn cpu_type(cpu: u32) -> &'static str {
+    match cpu {
+        7 => "x86",
+        0x0100_0007 => "x86-64",
+        12 => "ARM",
+        0x0100_000c => "ARM64",
+        18 => "PowerPC",
+        0x0100_0012 => "PowerPC64",
+        _ => "Unknown",
+    }
+}
+
+fn byte_order(big_endian: bool) -> &'static str {
+    match big_endian {
+        true => "Big endian",
+        false => "Little endian",
+    }
+}
+
+fn decode_png(file: &mut File) -> Option<BinaryHeader> {
+    let total = file.metadata().ok()?.len();
+    let mut header = BinaryHeader::new("PNG");
+
+    // Chunks follow the signature, each a length, a type, the data and a checksum.
+    let mut offset = 8;
+    while offset + 8 <= total && header.sections.len() < MAX_SECTIONS {
+        let chunk = read_at(file, offset, 8 + 13).ok()?;
+        let data = Data::new(&chunk, true);
+        let length = data.u32(0)? as u64;
+        let kind = data.string(4, 4)?;
+
+        if kind == "IHDR" {
+            header.field("Width", data.u32(8)?);
+            header.field("Height", data.u32(12)?);
+            header.field("Bit depth", *chunk.get(16)?);
+            header.field(
+                "Colour type",
+                match chunk.get(17)? {
+                    0 => "Greyscale",
+                    2 => "RGB",
+                    3 => "Indexed",
+                    4 => "Greyscale with alpha",
+                    6 => "RGBA",
+                    _ => "Unknown",
+                },
+            );
+            header.field(
+                "Interlaced",
+                if *chunk.get(20)? == 1 { "Yes" } else { "No" },
+            );
+        }
+        if kind == "acTL" {
+            header.field("Animated", "Yes");
+        }
+
+        header.sections.push(HeaderSection {
+            name: kind.clone(),
+            offset,
+            size: length,
+            address: None,
+            details: None,
+        });
+        offset += 12 + length;
+        if kind == "IEND" {
+            break;
+        }
+    }
+
+    Some(header)
+}
+
+fn decode_zip(file: &mut File) -> Option<BinaryHeader> {
+    let total = file.metadata().ok()?.len();
+
+    // The end of central directory record is last, followed only by a comment of up
+    // to 64KiB.
+    let tail_start = total.saturating_sub(22 + 0xffff);
+    let tail = read_at(file, tail_start, total - tail_start).ok()?;
+    let end = tail
+        .windows(4)
+        .rposition(|window| window == b"PK\x05\x06")?;
+    let record = Data::new(&tail[end..], false);
+
+    let mut header = BinaryHeader::new("ZIP");
+    let entries = record.u16(10)? as u64;
+    let directory_size = record.u32(12)? as u64;
+    let directory_offset = record.u32(16)? as u64;
+    let comment_length = record.u16(20)? as usize;
+
+    // Archives too large for these fields keep the real values in ZIP64 records.
+    if entries == 0xffff || directory_offset == 0xffff_ffff {
+        header.field("Format", "ZIP64");
+        return Some(header);
+    }
+    header.field("Entries", entries);
+    if comment_length > 0 {
+        header.field("Comment", record.string(22, comment_length)?);
+    }
+
+    let directory = read_at(file, directory_offset, directory_size.min(MAX_LENGTH)).ok()?;
+    let directory = Data::new(&directory, false);
+    let (mut compressed_total, mut uncompressed_total) = (0, 0);
+    let mut start = 0;
+    for _ in 0..entries {
+        // Anything but the next entry means the directory is damaged.
+        if directory
+            .array::<4>(start)
+            .is_some_and(|signature| signature != *b"PK\x01\x02")
+        {
+            break;
+        }
+        // Only the first `MAX_LENGTH` bytes of the directory are read, the entries after
+        // that are left out of the list and the totals.
+        let Some((entry, uncompressed, length)) = zip_entry(&directory, start) else {
+            header.truncated = true;
+            break;
+        };
+        compressed_total += entry.size;
+        uncompressed_total += uncompressed;
+
+        match header.sections.len() < MAX_SECTIONS {
+            true => header.sections.push(entry),
+            false => header.truncated = true,
+        }
+        start += length;
+    }
+    header.field("Compressed size", compressed_total);
+    header.field("Uncompressed size", uncompressed_total);
+
+    Some(header)
+}
+
+/// The central directory entry at `start`, with its uncompressed size and its length in
+/// the directory, if all of it is there.
+fn zip_entry(directory: &Data, start: usize) -> Option<(HeaderSection, u64, usize)> {
+    let flags = directory.u16(start + 8)?;
+    let method = directory.u16(start + 10)?;
+    let compressed = directory.u32(start + 20)? as u64;
+    let uncompressed = directory.u32(start + 24)? as u64;
+    let name_length = directory.u16(start + 28)? as usize;
+    let extra_length = directory.u16(start + 30)? as usize;
+    let comment_length = directory.u16(start + 32)? as usize;
+    let length = 46 + name_length + extra_length + comment_length;
+    directory.bytes.get(start..start + length)?;
+
+    let method = match method {
+        0 => "Stored",
+        8 => "Deflate",
+        9 => "Deflate64",
+        12 => "Bzip2",
+        14 => "LZMA",
+        93 => "Zstandard",
+        _ => "Other",
+    };
+    let encrypted = if flags & 1 != 0 { ", encrypted" } else { "" };
+    let entry = HeaderSection {
+        name: directory.string(start + 46, name_length)?,
+        offset: directory.u32(start + 42)? as u64,
+        size: compressed,
+        address: None,
+        details: Some(format!(
+            "{}{}, {} bytes uncompressed",
+            method, encrypted, uncompressed
+        )),
+    };
+    Some((entry, uncompressed, length))
+}
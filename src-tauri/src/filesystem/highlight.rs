@@ -0,0 +1,2067 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::text_preview::{read_text_preview, TextPreview, TextRange};
+use super::utils::escape_html;
+use crate::error::Error;
+use crate::themes::provider::get_theme_by_name;
+
+/// The light theme that ships with the app, which isn't in the themes directory.
+const BUILT_IN_LIGHT_THEME: &str = "Bytes Browser Light";
+
+const OPERATORS: &str = "+-*/%=<>!&|^~?:";
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Plain,
+    Keyword,
+    Type,
+    Constant,
+    Function,
+    String,
+    Number,
+    Comment,
+    Operator,
+    Tag,
+    Attribute,
+}
+
+impl TokenKind {
+    const ALL: [TokenKind; 11] = [
+        TokenKind::Plain,
+        TokenKind::Keyword,
+        TokenKind::Type,
+        TokenKind::Constant,
+        TokenKind::Function,
+        TokenKind::String,
+        TokenKind::Number,
+        TokenKind::Comment,
+        TokenKind::Operator,
+        TokenKind::Tag,
+        TokenKind::Attribute,
+    ];
+
+    /// Key of the colour in the `syntax` object of a theme, e.g. `"syntax": {"keyword": "#c586c0"}`.
+    fn theme_key(self) -> &'static str {
+        match self {
+            TokenKind::Plain => "plain",
+            TokenKind::Keyword => "keyword",
+            TokenKind::Type => "type",
+            TokenKind::Constant => "constant",
+            TokenKind::Function => "function",
+            TokenKind::String => "string",
+            TokenKind::Number => "number",
+            TokenKind::Comment => "comment",
+            TokenKind::Operator => "operator",
+            TokenKind::Tag => "tag",
+            TokenKind::Attribute => "attribute",
+        }
+    }
+}
+
+/// Colours of the token kinds in the same order as `TokenKind::ALL`.
+const DARK_PALETTE: [&str; 11] = [
+    "#d4d4d4", "#569cd6", "#4ec9b0", "#4fc1ff", "#dcdcaa", "#ce9178", "#b5cea8", "#6a9955",
+    "#d4d4d4", "#569cd6", "#9cdcfe",
+];
+
+const LIGHT_PALETTE: [&str; 11] = [
+    "#000000", "#0000ff", "#267f99", "#0070c1", "#795e26", "#a31515", "#098658", "#008000",
+    "#000000", "#800000", "#e50000",
+];
+
+#[derive(Debug, Serialize)]
+pub struct Span {
+    text: String,
+    kind: TokenKind,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HighlightedPreview {
+    #[serde(flatten)]
+    preview: TextPreview,
+    /// `None` when the language isn't known, the whole page is one plain span then.
+    language: Option<String>,
+    background: Option<String>,
+    colors: HashMap<TokenKind, String>,
+    spans: Vec<Span>,
+    /// The spans as a `<pre>` block with inline colours, when asked for.
+    html: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    Code,
+    /// HTML and XML.
+    Markup,
+    Markdown,
+    /// Keys and values, like INI, TOML and YAML, split by `separator`.
+    Config {
+        separator: char,
+    },
+}
+
+pub struct Language {
+    name: &'static str,
+    extensions: &'static [&'static str],
+    /// Names of interpreters in shebang lines, without versions.
+    interpreters: &'static [&'static str],
+    syntax: Syntax,
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    constants: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    /// Quotes of strings that may span lines.
+    multiline_quotes: &'static [char],
+    /// Strings can be quoted with three quotes, and span lines then.
+    triple_quotes: bool,
+    /// Characters besides letters, digits and `_` that are part of words.
+    word_chars: &'static str,
+    case_insensitive: bool,
+    /// Words starting with a capital letter are types, and all capitals are constants.
+    capitalized_types: bool,
+    /// `'` only quotes single characters, so lifetimes and labels aren't strings.
+    char_literals: bool,
+    /// Strings followed by a `:` are keys.
+    string_keys: bool,
+    /// Words followed by a `:` are keys, like CSS properties.
+    colon_keys: bool,
+}
+
+const CODE: Language = Language {
+    name: "",
+    extensions: &[],
+    interpreters: &[],
+    syntax: Syntax::Code,
+    keywords: &[],
+    types: &[],
+    constants: &["true", "false", "null"],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\''],
+    multiline_quotes: &[],
+    triple_quotes: false,
+    word_chars: "",
+    case_insensitive: false,
+    capitalized_types: true,
+    char_literals: false,
+    string_keys: false,
+    colon_keys: false,
+};
+
+const C_KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "const", "continue", "default", "do", "else", "enum", "extern", "for",
+    "goto", "if", "inline", "register", "restrict", "return", "sizeof", "static", "struct",
+    "switch", "typedef", "union", "volatile", "while", "#include", "#define", "#if", "#ifdef",
+    "#ifndef", "#else", "#elif", "#endif", "#pragma", "#undef", "#error", "#import",
+];
+
+const C_TYPES: &[&str] = &[
+    "char",
+    "short",
+    "int",
+    "long",
+    "float",
+    "double",
+    "signed",
+    "unsigned",
+    "void",
+    "bool",
+    "size_t",
+    "ssize_t",
+    "int8_t",
+    "int16_t",
+    "int32_t",
+    "int64_t",
+    "uint8_t",
+    "uint16_t",
+    "uint32_t",
+    "uint64_t",
+    "uintptr_t",
+    "wchar_t",
+];
+
+const CPP_KEYWORDS: &[&str] = &[
+    "auto",
+    "break",
+    "case",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "else",
+    "enum",
+    "extern",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "register",
+    "return",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "typedef",
+    "union",
+    "volatile",
+    "while",
+    "alignas",
+    "alignof",
+    "catch",
+    "class",
+    "constexpr",
+    "consteval",
+    "const_cast",
+    "decltype",
+    "delete",
+    "dynamic_cast",
+    "explicit",
+    "export",
+    "friend",
+    "mutable",
+    "namespace",
+    "new",
+    "noexcept",
+    "operator",
+    "private",
+    "protected",
+    "public",
+    "reinterpret_cast",
+    "static_assert",
+    "static_cast",
+    "template",
+    "this",
+    "throw",
+    "try",
+    "typeid",
+    "typename",
+    "using",
+    "virtual",
+    "override",
+    "final",
+    "concept",
+    "requires",
+    "co_await",
+    "co_return",
+    "co_yield",
+    "#include",
+    "#define",
+    "#if",
+    "#ifdef",
+    "#ifndef",
+    "#else",
+    "#elif",
+    "#endif",
+    "#pragma",
+    "#undef",
+    "#error",
+];
+
+const JS_KEYWORDS: &[&str] = &[
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "finally",
+    "for",
+    "from",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "of",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+const TS_KEYWORDS: &[&str] = &[
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "finally",
+    "for",
+    "from",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "of",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+    "abstract",
+    "as",
+    "declare",
+    "enum",
+    "implements",
+    "interface",
+    "keyof",
+    "namespace",
+    "private",
+    "protected",
+    "public",
+    "readonly",
+    "type",
+    "satisfies",
+    "is",
+];
+
+const JS_CONSTANTS: &[&str] = &["true", "false", "null", "undefined", "NaN", "Infinity"];
+
+const JAVA_KEYWORDS: &[&str] = &[
+    "abstract",
+    "assert",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "else",
+    "enum",
+    "extends",
+    "final",
+    "finally",
+    "for",
+    "goto",
+    "if",
+    "implements",
+    "import",
+    "instanceof",
+    "interface",
+    "native",
+    "new",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "strictfp",
+    "super",
+    "switch",
+    "synchronized",
+    "this",
+    "throw",
+    "throws",
+    "transient",
+    "try",
+    "var",
+    "volatile",
+    "while",
+    "record",
+    "sealed",
+    "permits",
+    "yield",
+];
+
+const JAVA_TYPES: &[&str] = &[
+    "boolean", "byte", "char", "double", "float", "int", "long", "short", "void",
+];
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        name: "Rust",
+        extensions: &["rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+            "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+            "type", "union", "unsafe", "use", "where", "while", "yield",
+        ],
+        types: &[
+            "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32",
+            "i64", "i128", "isize", "f32", "f64",
+        ],
+        constants: &["true", "false", "None", "Some", "Ok", "Err"],
+        multiline_quotes: &['"'],
+        char_literals: true,
+        ..CODE
+    },
+    Language {
+        name: "TypeScript",
+        extensions: &["ts", "tsx", "mts", "cts"],
+        interpreters: &["deno", "ts-node"],
+        keywords: TS_KEYWORDS,
+        types: &[
+            "any", "bigint", "boolean", "never", "number", "object", "string", "symbol", "unknown",
+        ],
+        constants: JS_CONSTANTS,
+        quotes: &['"', '\'', '`'],
+        multiline_quotes: &['`'],
+        word_chars: "$",
+        ..CODE
+    },
+    Language {
+        name: "JavaScript",
+        extensions: &["js", "jsx", "mjs", "cjs"],
+        interpreters: &["node"],
+        keywords: JS_KEYWORDS,
+        constants: JS_CONSTANTS,
+        quotes: &['"', '\'', '`'],
+        multiline_quotes: &['`'],
+        word_chars: "$",
+        ..CODE
+    },
+    Language {
+        name: "JSON",
+        extensions: &["json", "jsonc", "json5"],
+        quotes: &['"'],
+        capitalized_types: false,
+        string_keys: true,
+        ..CODE
+    },
+    Language {
+        name: "Python",
+        extensions: &["py", "pyw", "pyi"],
+        interpreters: &["python"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "case", "class", "continue", "def",
+            "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+            "in", "is", "lambda", "match", "nonlocal", "not", "or", "pass", "raise", "return",
+            "try", "while", "with", "yield",
+        ],
+        types: &[
+            "bool", "bytes", "dict", "float", "int", "list", "object", "set", "str", "tuple",
+            "type",
+        ],
+        constants: &["True", "False", "None"],
+        line_comments: &["#"],
+        block_comment: None,
+        triple_quotes: true,
+        ..CODE
+    },
+    Language {
+        name: "C",
+        extensions: &["c", "h"],
+        keywords: C_KEYWORDS,
+        types: C_TYPES,
+        constants: &["NULL", "true", "false"],
+        word_chars: "#",
+        ..CODE
+    },
+    Language {
+        name: "C++",
+        extensions: &["cpp", "cc", "cxx", "hpp", "hh", "hxx"],
+        keywords: CPP_KEYWORDS,
+        types: C_TYPES,
+        constants: &["nullptr", "NULL", "true", "false"],
+        word_chars: "#",
+        ..CODE
+    },
+    Language {
+        name: "Objective-C",
+        extensions: &["m", "mm"],
+        keywords: &[
+            "auto",
+            "break",
+            "case",
+            "const",
+            "continue",
+            "default",
+            "do",
+            "else",
+            "enum",
+            "extern",
+            "for",
+            "goto",
+            "if",
+            "inline",
+            "return",
+            "sizeof",
+            "static",
+            "struct",
+            "switch",
+            "typedef",
+            "union",
+            "while",
+            "self",
+            "super",
+            "#include",
+            "#import",
+            "#define",
+            "#if",
+            "#ifdef",
+            "#ifndef",
+            "#else",
+            "#endif",
+            "#pragma",
+            "@interface",
+            "@implementation",
+            "@end",
+            "@property",
+            "@synthesize",
+            "@protocol",
+            "@class",
+            "@selector",
+            "@autoreleasepool",
+            "@try",
+            "@catch",
+            "@finally",
+        ],
+        types: &[
+            "char",
+            "short",
+            "int",
+            "long",
+            "float",
+            "double",
+            "signed",
+            "unsigned",
+            "void",
+            "id",
+            "BOOL",
+            "instancetype",
+        ],
+        constants: &["nil", "Nil", "YES", "NO", "NULL"],
+        word_chars: "#@",
+        ..CODE
+    },
+    Language {
+        name: "Java",
+        extensions: &["java"],
+        keywords: JAVA_KEYWORDS,
+        types: JAVA_TYPES,
+        triple_quotes: true,
+        word_chars: "@",
+        ..CODE
+    },
+    Language {
+        name: "C#",
+        extensions: &["cs", "csx"],
+        keywords: &[
+            "abstract",
+            "as",
+            "async",
+            "await",
+            "base",
+            "break",
+            "case",
+            "catch",
+            "checked",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delegate",
+            "do",
+            "else",
+            "enum",
+            "event",
+            "explicit",
+            "extern",
+            "finally",
+            "fixed",
+            "for",
+            "foreach",
+            "get",
+            "goto",
+            "if",
+            "implicit",
+            "in",
+            "init",
+            "interface",
+            "internal",
+            "is",
+            "lock",
+            "namespace",
+            "new",
+            "operator",
+            "out",
+            "override",
+            "params",
+            "private",
+            "protected",
+            "public",
+            "readonly",
+            "record",
+            "ref",
+            "return",
+            "sealed",
+            "set",
+            "sizeof",
+            "stackalloc",
+            "static",
+            "struct",
+            "switch",
+            "this",
+            "throw",
+            "try",
+            "typeof",
+            "unchecked",
+            "unsafe",
+            "using",
+            "var",
+            "virtual",
+            "void",
+            "volatile",
+            "when",
+            "where",
+            "while",
+            "yield",
+        ],
+        types: &[
+            "bool", "byte", "char", "decimal", "double", "dynamic", "float", "int", "long",
+            "object", "sbyte", "short", "string", "uint", "ulong", "ushort",
+        ],
+        ..CODE
+    },
+    Language {
+        name: "Go",
+        extensions: &["go"],
+        keywords: &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "fallthrough",
+            "for",
+            "func",
+            "go",
+            "goto",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "type",
+            "var",
+        ],
+        types: &[
+            "any",
+            "bool",
+            "byte",
+            "complex64",
+            "complex128",
+            "error",
+            "float32",
+            "float64",
+            "int",
+            "int8",
+            "int16",
+            "int32",
+            "int64",
+            "rune",
+            "string",
+            "uint",
+            "uint8",
+            "uint16",
+            "uint32",
+            "uint64",
+            "uintptr",
+        ],
+        constants: &["true", "false", "nil", "iota"],
+        quotes: &['"', '\'', '`'],
+        multiline_quotes: &['`'],
+        // Capitals mark exported names in Go, not types.
+        capitalized_types: false,
+        ..CODE
+    },
+    Language {
+        name: "Kotlin",
+        extensions: &["kt", "kts"],
+        keywords: &[
+            "abstract",
+            "annotation",
+            "as",
+            "break",
+            "by",
+            "catch",
+            "class",
+            "companion",
+            "const",
+            "constructor",
+            "continue",
+            "data",
+            "do",
+            "else",
+            "enum",
+            "external",
+            "final",
+            "finally",
+            "for",
+            "fun",
+            "get",
+            "if",
+            "import",
+            "in",
+            "infix",
+            "init",
+            "inline",
+            "inner",
+            "interface",
+            "internal",
+            "is",
+            "lateinit",
+            "object",
+            "open",
+            "operator",
+            "out",
+            "override",
+            "package",
+            "private",
+            "protected",
+            "public",
+            "reified",
+            "return",
+            "sealed",
+            "set",
+            "super",
+            "suspend",
+            "this",
+            "throw",
+            "try",
+            "typealias",
+            "val",
+            "var",
+            "vararg",
+            "when",
+            "where",
+            "while",
+        ],
+        triple_quotes: true,
+        word_chars: "@",
+        ..CODE
+    },
+    Language {
+        name: "Swift",
+        extensions: &["swift"],
+        keywords: &[
+            "any",
+            "as",
+            "associatedtype",
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "continue",
+            "default",
+            "defer",
+            "deinit",
+            "do",
+            "else",
+            "enum",
+            "extension",
+            "fallthrough",
+            "fileprivate",
+            "for",
+            "func",
+            "guard",
+            "if",
+            "import",
+            "in",
+            "init",
+            "inout",
+            "internal",
+            "is",
+            "let",
+            "open",
+            "operator",
+            "private",
+            "protocol",
+            "public",
+            "repeat",
+            "rethrows",
+            "return",
+            "self",
+            "Self",
+            "some",
+            "static",
+            "struct",
+            "subscript",
+            "super",
+            "switch",
+            "throw",
+            "throws",
+            "try",
+            "typealias",
+            "var",
+            "where",
+            "while",
+        ],
+        constants: &["true", "false", "nil"],
+        triple_quotes: true,
+        word_chars: "@#",
+        ..CODE
+    },
+    Language {
+        name: "Scala",
+        extensions: &["scala", "sc"],
+        keywords: &[
+            "abstract",
+            "case",
+            "catch",
+            "class",
+            "def",
+            "do",
+            "else",
+            "enum",
+            "export",
+            "extends",
+            "final",
+            "finally",
+            "for",
+            "given",
+            "if",
+            "implicit",
+            "import",
+            "lazy",
+            "match",
+            "new",
+            "object",
+            "override",
+            "package",
+            "private",
+            "protected",
+            "return",
+            "sealed",
+            "super",
+            "then",
+            "this",
+            "throw",
+            "trait",
+            "try",
+            "type",
+            "using",
+            "val",
+            "var",
+            "while",
+            "with",
+            "yield",
+        ],
+        triple_quotes: true,
+        ..CODE
+    },
+    Language {
+        name: "Groovy",
+        extensions: &["groovy", "gradle"],
+        interpreters: &["groovy"],
+        keywords: &[
+            "as",
+            "assert",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "continue",
+            "def",
+            "default",
+            "do",
+            "else",
+            "enum",
+            "extends",
+            "finally",
+            "for",
+            "if",
+            "implements",
+            "import",
+            "in",
+            "instanceof",
+            "interface",
+            "new",
+            "package",
+            "return",
+            "super",
+            "switch",
+            "this",
+            "throw",
+            "throws",
+            "trait",
+            "try",
+            "var",
+            "while",
+        ],
+        types: JAVA_TYPES,
+        triple_quotes: true,
+        word_chars: "@$",
+        ..CODE
+    },
+    Language {
+        name: "Ruby",
+        extensions: &["rb", "rake", "gemspec"],
+        interpreters: &["ruby"],
+        keywords: &[
+            "BEGIN",
+            "END",
+            "alias",
+            "and",
+            "begin",
+            "break",
+            "case",
+            "class",
+            "def",
+            "defined?",
+            "do",
+            "else",
+            "elsif",
+            "end",
+            "ensure",
+            "for",
+            "if",
+            "in",
+            "module",
+            "next",
+            "not",
+            "or",
+            "redo",
+            "rescue",
+            "retry",
+            "return",
+            "self",
+            "super",
+            "then",
+            "undef",
+            "unless",
+            "until",
+            "when",
+            "while",
+            "yield",
+            "require",
+            "require_relative",
+        ],
+        constants: &["true", "false", "nil"],
+        line_comments: &["#"],
+        block_comment: Some(("=begin", "=end")),
+        multiline_quotes: &['"', '\''],
+        word_chars: "@$?!",
+        ..CODE
+    },
+    Language {
+        name: "Perl",
+        extensions: &["pl", "pm"],
+        interpreters: &["perl"],
+        keywords: &[
+            "my", "our", "local", "sub", "if", "elsif", "else", "unless", "while", "until", "for",
+            "foreach", "do", "last", "next", "redo", "return", "use", "require", "package", "no",
+            "print", "say", "and", "or", "not", "eq", "ne", "lt", "gt", "le", "ge", "cmp",
+        ],
+        constants: &["undef"],
+        line_comments: &["#"],
+        block_comment: Some(("=pod", "=cut")),
+        multiline_quotes: &['"', '\''],
+        word_chars: "$@%",
+        capitalized_types: false,
+        ..CODE
+    },
+    Language {
+        name: "PHP",
+        extensions: &["php", "phtml"],
+        interpreters: &["php"],
+        keywords: &[
+            "abstract",
+            "and",
+            "array",
+            "as",
+            "break",
+            "callable",
+            "case",
+            "catch",
+            "class",
+            "clone",
+            "const",
+            "continue",
+            "declare",
+            "default",
+            "do",
+            "echo",
+            "else",
+            "elseif",
+            "empty",
+            "enum",
+            "extends",
+            "final",
+            "finally",
+            "fn",
+            "for",
+            "foreach",
+            "function",
+            "global",
+            "goto",
+            "if",
+            "implements",
+            "include",
+            "include_once",
+            "instanceof",
+            "insteadof",
+            "interface",
+            "isset",
+            "list",
+            "match",
+            "namespace",
+            "new",
+            "or",
+            "print",
+            "private",
+            "protected",
+            "public",
+            "readonly",
+            "require",
+            "require_once",
+            "return",
+            "static",
+            "switch",
+            "throw",
+            "trait",
+            "try",
+            "unset",
+            "use",
+            "var",
+            "while",
+            "xor",
+            "yield",
+        ],
+        line_comments: &["//", "#"],
+        multiline_quotes: &['"', '\''],
+        word_chars: "$",
+        case_insensitive: true,
+        ..CODE
+    },
+    Language {
+        name: "Shell",
+        extensions: &["sh", "bash", "zsh", "ksh"],
+        interpreters: &["sh", "bash", "zsh", "dash", "ksh"],
+        keywords: &[
+            "if", "then", "else", "elif", "fi", "case", "esac", "for", "select", "while", "until",
+            "do", "done", "in", "function", "time", "return", "exit", "local", "export",
+            "readonly", "declare", "unset", "shift", "source", "alias", "echo", "cd", "set",
+            "trap",
+        ],
+        constants: &["true", "false"],
+        line_comments: &["#"],
+        block_comment: None,
+        multiline_quotes: &['"', '\''],
+        word_chars: "$",
+        capitalized_types: false,
+        ..CODE
+    },
+    Language {
+        name: "PowerShell",
+        extensions: &["ps1", "psm1", "psd1"],
+        interpreters: &["pwsh", "powershell"],
+        keywords: &[
+            "begin",
+            "break",
+            "catch",
+            "class",
+            "continue",
+            "data",
+            "do",
+            "dynamicparam",
+            "else",
+            "elseif",
+            "end",
+            "enum",
+            "exit",
+            "filter",
+            "finally",
+            "for",
+            "foreach",
+            "function",
+            "if",
+            "in",
+            "param",
+            "process",
+            "return",
+            "switch",
+            "throw",
+            "trap",
+            "try",
+            "until",
+            "using",
+            "while",
+            "-eq",
+            "-ne",
+            "-gt",
+            "-ge",
+            "-lt",
+            "-le",
+            "-and",
+            "-or",
+            "-not",
+            "-like",
+            "-match",
+        ],
+        constants: &["$true", "$false", "$null"],
+        line_comments: &["#"],
+        block_comment: Some(("<#", "#>")),
+        multiline_quotes: &['"', '\''],
+        word_chars: "$-",
+        case_insensitive: true,
+        capitalized_types: false,
+        ..CODE
+    },
+    Language {
+        name: "Lua",
+        extensions: &["lua"],
+        interpreters: &["lua", "luajit"],
+        keywords: &[
+            "and", "break", "do", "else", "elseif", "end", "for", "function", "goto", "if", "in",
+            "local", "not", "or", "repeat", "return", "then", "until", "while",
+        ],
+        constants: &["true", "false", "nil"],
+        line_comments: &["--"],
+        block_comment: Some(("--[[", "]]")),
+        capitalized_types: false,
+        ..CODE
+    },
+    Language {
+        name: "SQL",
+        extensions: &["sql"],
+        keywords: &[
+            "add",
+            "all",
+            "alter",
+            "and",
+            "as",
+            "asc",
+            "begin",
+            "between",
+            "by",
+            "case",
+            "check",
+            "column",
+            "commit",
+            "constraint",
+            "create",
+            "declare",
+            "default",
+            "delete",
+            "desc",
+            "distinct",
+            "drop",
+            "else",
+            "end",
+            "exists",
+            "foreign",
+            "from",
+            "full",
+            "function",
+            "group",
+            "having",
+            "if",
+            "in",
+            "index",
+            "inner",
+            "insert",
+            "into",
+            "is",
+            "join",
+            "key",
+            "left",
+            "like",
+            "limit",
+            "not",
+            "offset",
+            "on",
+            "or",
+            "order",
+            "outer",
+            "primary",
+            "procedure",
+            "references",
+            "replace",
+            "returning",
+            "right",
+            "rollback",
+            "select",
+            "set",
+            "table",
+            "then",
+            "transaction",
+            "trigger",
+            "union",
+            "unique",
+            "update",
+            "values",
+            "view",
+            "when",
+            "where",
+            "with",
+        ],
+        types: &[
+            "bigint",
+            "blob",
+            "boolean",
+            "char",
+            "date",
+            "decimal",
+            "double",
+            "float",
+            "int",
+            "integer",
+            "numeric",
+            "real",
+            "serial",
+            "smallint",
+            "text",
+            "time",
+            "timestamp",
+            "varchar",
+        ],
+        line_comments: &["--"],
+        quotes: &['\'', '"', '`'],
+        multiline_quotes: &['\''],
+        case_insensitive: true,
+        capitalized_types: false,
+        ..CODE
+    },
+    Language {
+        name: "R",
+        extensions: &["r"],
+        interpreters: &["Rscript"],
+        keywords: &[
+            "if", "else", "repeat", "while", "function", "for", "in", "next", "break", "return",
+            "library", "require",
+        ],
+        constants: &["TRUE", "FALSE", "NULL", "NA", "Inf", "NaN"],
+        line_comments: &["#"],
+        block_comment: None,
+        word_chars: ".",
+        capitalized_types: false,
+        ..CODE
+    },
+    Language {
+        name: "Fortran",
+        extensions: &["f", "f90", "f95", "f03", "for"],
+        keywords: &[
+            "program",
+            "end",
+            "module",
+            "use",
+            "implicit",
+            "none",
+            "parameter",
+            "dimension",
+            "allocatable",
+            "intent",
+            "in",
+            "out",
+            "inout",
+            "subroutine",
+            "function",
+            "call",
+            "return",
+            "if",
+            "then",
+            "else",
+            "elseif",
+            "endif",
+            "do",
+            "enddo",
+            "while",
+            "select",
+            "case",
+            "contains",
+            "print",
+            "write",
+            "read",
+            "stop",
+            "type",
+            "interface",
+        ],
+        types: &[
+            "integer",
+            "real",
+            "double",
+            "precision",
+            "complex",
+            "character",
+            "logical",
+        ],
+        constants: &[".true.", ".false."],
+        line_comments: &["!"],
+        block_comment: None,
+        word_chars: ".",
+        case_insensitive: true,
+        capitalized_types: false,
+        ..CODE
+    },
+    Language {
+        name: "Visual Basic",
+        extensions: &["vb", "vbs", "bas"],
+        keywords: &[
+            "and",
+            "as",
+            "byref",
+            "byval",
+            "call",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "dim",
+            "do",
+            "each",
+            "else",
+            "elseif",
+            "end",
+            "enum",
+            "exit",
+            "for",
+            "function",
+            "get",
+            "if",
+            "imports",
+            "in",
+            "inherits",
+            "interface",
+            "is",
+            "loop",
+            "me",
+            "module",
+            "mybase",
+            "new",
+            "next",
+            "not",
+            "of",
+            "on",
+            "or",
+            "private",
+            "property",
+            "protected",
+            "public",
+            "redim",
+            "return",
+            "select",
+            "set",
+            "shared",
+            "sub",
+            "then",
+            "to",
+            "try",
+            "while",
+            "with",
+        ],
+        types: &[
+            "boolean", "byte", "char", "date", "decimal", "double", "integer", "long", "object",
+            "short", "single", "string",
+        ],
+        constants: &["true", "false", "nothing"],
+        line_comments: &["'"],
+        block_comment: None,
+        quotes: &['"'],
+        case_insensitive: true,
+        capitalized_types: false,
+        ..CODE
+    },
+    Language {
+        name: "CSS",
+        extensions: &["css", "scss", "less"],
+        keywords: &[
+            "@media",
+            "@import",
+            "@keyframes",
+            "@font-face",
+            "@supports",
+            "@use",
+            "@mixin",
+            "@include",
+            "!important",
+        ],
+        constants: &[],
+        word_chars: "-@!",
+        colon_keys: true,
+        capitalized_types: false,
+        ..CODE
+    },
+    Language {
+        name: "HTML",
+        extensions: &["html", "htm", "xhtml", "asp", "aspx", "jsp", "vue"],
+        syntax: Syntax::Markup,
+        ..CODE
+    },
+    Language {
+        name: "XML",
+        extensions: &["xml", "svg", "xsd", "xsl", "xslt", "plist", "csproj"],
+        syntax: Syntax::Markup,
+        ..CODE
+    },
+    Language {
+        name: "Markdown",
+        extensions: &["md", "markdown"],
+        syntax: Syntax::Markdown,
+        ..CODE
+    },
+    Language {
+        name: "TOML",
+        extensions: &["toml"],
+        syntax: Syntax::Config { separator: '=' },
+        line_comments: &["#"],
+        block_comment: None,
+        triple_quotes: true,
+        capitalized_types: false,
+        ..CODE
+    },
+    Language {
+        name: "INI",
+        extensions: &["ini", "cfg", "conf", "properties"],
+        syntax: Syntax::Config { separator: '=' },
+        line_comments: &[";", "#"],
+        block_comment: None,
+        capitalized_types: false,
+        ..CODE
+    },
+    Language {
+        name: "YAML",
+        extensions: &["yml", "yaml"],
+        syntax: Syntax::Config { separator: ':' },
+        constants: &["true", "false", "null", "yes", "no", "on", "off", "~"],
+        line_comments: &["#"],
+        block_comment: None,
+        case_insensitive: true,
+        capitalized_types: false,
+        ..CODE
+    },
+];
+
+/// A page of `path` split into highlighted spans, coloured like the installed `theme`.
+/// Each page is highlighted on its own, so a page starting inside a block comment or a
+/// multiline string shows its start as code.
+#[tauri::command]
+pub async fn get_highlighted_preview(
+    path: String,
+    range: Option<TextRange>,
+    theme: Option<String>,
+    html: Option<bool>,
+) -> Result<HighlightedPreview, Error> {
+    let path = Path::new(&path);
+    let preview = read_text_preview(path, range.unwrap_or_default())?;
+    if preview.binary {
+        return Err(Error::Custom(
+            "Binary files can't be highlighted".to_string(),
+        ));
+    }
+
+    let language = detect_language(path)?;
+    let spans = match language {
+        Some(language) => Lexer::new(&preview.text, language).run(),
+        None => vec![Span {
+            text: preview.text.clone(),
+            kind: TokenKind::Plain,
+        }],
+    };
+
+    let theme_content = match &theme {
+        Some(name) => load_theme(name).await,
+        None => None,
+    };
+    let (colors, background) = palette(theme.as_deref(), theme_content.as_ref());
+    let html = html
+        .unwrap_or(false)
+        .then(|| to_html(&spans, &colors, background.as_deref()));
+
+    Ok(HighlightedPreview {
+        preview,
+        language: language.map(|language| language.name.to_string()),
+        background,
+        colors,
+        spans,
+        html,
+    })
+}
+
+/// The language of a file by its extension, or by the interpreter in its shebang line.
+pub fn detect_language(path: &Path) -> Result<Option<&'static Language>, Error> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if let Some(language) = LANGUAGES
+        .iter()
+        .find(|language| language.extensions.contains(&extension.as_str()))
+    {
+        return Ok(Some(language));
+    }
+
+    let first_line = read_text_preview(path, TextRange::Lines { start: 0, count: 1 })?.text;
+    let Some(shebang) = first_line.strip_prefix("#!") else {
+        return Ok(None);
+    };
+
+    // `#!/usr/bin/env -S python3 -u` runs `python3`, and `python3.11` is `python`.
+    let mut words = shebang.split_whitespace();
+    let mut program = words.next().unwrap_or_default();
+    if program.ends_with("/env") {
+        program = words
+            .find(|word| !word.starts_with('-'))
+            .unwrap_or_default();
+    }
+    let interpreter = program
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    Ok(LANGUAGES
+        .iter()
+        .find(|language| language.interpreters.contains(&interpreter)))
+}
+
+/// The content of an installed theme, `None` for the built-in themes.
+async fn load_theme(name: &str) -> Option<Value> {
+    let file = get_theme_by_name(name.to_string()).await.ok()?;
+    let theme: Value = serde_json::from_str(&file).ok()?;
+
+    // Installed themes keep their content as a JSON string.
+    match &theme["content"] {
+        Value::String(content) => serde_json::from_str(content).ok(),
+        content @ Value::Object(_) => Some(content.clone()),
+        _ => None,
+    }
+}
+
+/// Colours of the token kinds and the background. Themes pick colours with a `syntax`
+/// object, anything they leave out comes from a dark or light palette matching their
+/// background.
+fn palette(
+    name: Option<&str>,
+    theme: Option<&Value>,
+) -> (HashMap<TokenKind, String>, Option<String>) {
+    let background = theme
+        .and_then(|theme| theme["primaryBG"].as_str())
+        .map(str::to_string);
+    let light = match background.as_deref().and_then(luminance) {
+        Some(luminance) => luminance > 0.5,
+        None => name == Some(BUILT_IN_LIGHT_THEME),
+    };
+    let base = if light { LIGHT_PALETTE } else { DARK_PALETTE };
+
+    let mut colors: HashMap<TokenKind, String> = TokenKind::ALL
+        .into_iter()
+        .zip(base)
+        .map(|(kind, color)| (kind, color.to_string()))
+        .collect();
+
+    if let Some(theme) = theme {
+        if let Some(text) = theme["primaryTextColor"].as_str() {
+            colors.insert(TokenKind::Plain, text.to_string());
+        }
+        for kind in TokenKind::ALL {
+            if let Some(color) = theme["syntax"][kind.theme_key()].as_str() {
+                colors.insert(kind, color.to_string());
+            }
+        }
+    }
+
+    (colors, background)
+}
+
+/// Relative luminance of a `#rgb` or `#rrggbb` colour, from 0 to 1.
+fn luminance(color: &str) -> Option<f32> {
+    let hex = color.strip_prefix('#')?;
+    let channel = |index: usize, width: usize| {
+        let value = u8::from_str_radix(hex.get(index * width..(index + 1) * width)?, 16).ok()?;
+        // `#fff` is short for `#ffffff`.
+        let value = if width == 1 { value * 17 } else { value };
+        Some(value as f32 / 255.0)
+    };
+
+    let width = match hex.len() {
+        3 => 1,
+        6 => 2,
+        _ => return None,
+    };
+    Some(0.2126 * channel(0, width)? + 0.7152 * channel(1, width)? + 0.0722 * channel(2, width)?)
+}
+
+fn to_html(
+    spans: &[Span],
+    colors: &HashMap<TokenKind, String>,
+    background: Option<&str>,
+) -> String {
+    let mut html = String::from("<pre style=\"");
+    if let Some(background) = background {
+        html.push_str(&format!("background-color: {}; ", escape_html(background)));
+    }
+    html.push_str(&format!(
+        "color: {}\"><code>",
+        escape_html(&colors[&TokenKind::Plain])
+    ));
+
+    for span in spans {
+        if span.kind == TokenKind::Plain {
+            html.push_str(&escape_html(&span.text));
+        } else {
+            html.push_str(&format!(
+                "<span style=\"color: {}\">{}</span>",
+                escape_html(&colors[&span.kind]),
+                escape_html(&span.text)
+            ));
+        }
+    }
+
+    html.push_str("</code></pre>");
+    html
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    position: usize,
+    language: &'static Language,
+    spans: Vec<Span>,
+}
+
+impl Lexer {
+    fn new(text: &str, language: &'static Language) -> Self {
+        Lexer {
+            chars: text.chars().collect(),
+            position: 0,
+            language,
+            spans: Vec::new(),
+        }
+    }
+
+    fn run(mut self) -> Vec<Span> {
+        while self.position < self.chars.len() {
+            match self.language.syntax {
+                Syntax::Code => self.code_token(),
+                Syntax::Markup => self.markup_token(),
+                Syntax::Markdown => self.markdown_line(),
+                Syntax::Config { separator } => self.config_line(separator),
+            }
+        }
+        self.spans
+    }
+
+    /// Adds the characters up to `end` as a span, joined to the last one if it's of the
+    /// same kind.
+    fn push(&mut self, kind: TokenKind, end: usize) {
+        let end = end.clamp(self.position, self.chars.len());
+        let text: String = self.chars[self.position..end].iter().collect();
+        self.position = end;
+
+        match self.spans.last_mut() {
+            Some(last) if last.kind == kind => last.text.push_str(&text),
+            _ if text.is_empty() => {}
+            _ => self.spans.push(Span { text, kind }),
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+
+    fn starts_with(&self, at: usize, prefix: &str) -> bool {
+        prefix
+            .chars()
+            .enumerate()
+            .all(|(index, c)| self.chars.get(at + index) == Some(&c))
+    }
+
+    /// Where `pattern` starts, at or after `from`.
+    fn find(&self, from: usize, pattern: &str) -> Option<usize> {
+        (from..self.chars.len()).find(|&at| self.starts_with(at, pattern))
+    }
+
+    /// Index of the line feed ending the line at `from`, or the end of the text.
+    fn line_end(&self, from: usize) -> usize {
+        self.chars[from..]
+            .iter()
+            .position(|&c| c == '\n')
+            .map_or(self.chars.len(), |index| from + index)
+    }
+
+    fn is_word_char(&self, c: char) -> bool {
+        c.is_alphanumeric() || c == '_' || self.language.word_chars.contains(c)
+    }
+
+    fn code_token(&mut self) {
+        let language = self.language;
+        let start = self.position;
+        let c = self.chars[start];
+
+        // Block comments go first, Lua's `--[[` starts like a line comment.
+        if let Some((open, close)) = language.block_comment {
+            if self.starts_with(start, open) {
+                let end = self
+                    .find(start + open.chars().count(), close)
+                    .map_or(self.chars.len(), |at| at + close.chars().count());
+                return self.push(TokenKind::Comment, end);
+            }
+        }
+        if language
+            .line_comments
+            .iter()
+            .any(|prefix| self.starts_with(start, prefix))
+        {
+            return self.push(TokenKind::Comment, self.line_end(start));
+        }
+
+        if language.quotes.contains(&c) {
+            if let Some(end) = self.string_end(start) {
+                let kind = match language.string_keys && self.next_non_space(end) == Some(':') {
+                    true => TokenKind::Attribute,
+                    false => TokenKind::String,
+                };
+                return self.push(kind, end);
+            }
+        }
+
+        if c.is_ascii_digit() || (c == '.' && self.peek(1).is_some_and(|c| c.is_ascii_digit())) {
+            let mut end = start + 1;
+            while let Some(&c) = self.chars.get(end) {
+                // `0..10` is a range, not a number with two points.
+                let range = c == '.' && self.chars.get(end + 1) == Some(&'.');
+                if range || !(c.is_alphanumeric() || c == '_' || c == '.') {
+                    break;
+                }
+                end += 1;
+            }
+            return self.push(TokenKind::Number, end);
+        }
+
+        if self.is_word_char(c) && !c.is_ascii_digit() {
+            let mut end = start + 1;
+            while self.chars.get(end).is_some_and(|&c| self.is_word_char(c)) {
+                end += 1;
+            }
+            let kind = self.word_kind(start, end);
+            return self.push(kind, end);
+        }
+
+        let kind = match OPERATORS.contains(c) {
+            true => TokenKind::Operator,
+            false => TokenKind::Plain,
+        };
+        self.push(kind, start + 1);
+    }
+
+    /// Where the string starting at `start` ends, `None` when the quote doesn't start one.
+    fn string_end(&self, start: usize) -> Option<usize> {
+        let language = self.language;
+        let quote = self.chars[start];
+
+        if language.triple_quotes {
+            let triple: String = [quote; 3].iter().collect();
+            if self.starts_with(start, &triple) {
+                return Some(
+                    self.find(start + 3, &triple)
+                        .map_or(self.chars.len(), |at| at + 3),
+                );
+            }
+        }
+
+        if language.char_literals && quote == '\'' {
+            return match self.peek(1) {
+                Some('\\') => (start + 2..(start + 12).min(self.chars.len()))
+                    .find(|&at| self.chars[at] == '\'')
+                    .map(|at| at + 1),
+                Some(_) if self.peek(2) == Some('\'') => Some(start + 3),
+                _ => None,
+            };
+        }
+
+        let multiline = language.multiline_quotes.contains(&quote);
+        let mut at = start + 1;
+        while let Some(&c) = self.chars.get(at) {
+            match c {
+                '\\' => at += 2,
+                '\n' if !multiline => return Some(at),
+                c if c == quote => return Some(at + 1),
+                _ => at += 1,
+            }
+        }
+        Some(self.chars.len())
+    }
+
+    fn next_non_space(&self, from: usize) -> Option<char> {
+        self.chars[from.min(self.chars.len())..]
+            .iter()
+            .copied()
+            .find(|c| !c.is_whitespace())
+    }
+
+    fn word_kind(&self, start: usize, end: usize) -> TokenKind {
+        let language = self.language;
+        let word: String = self.chars[start..end].iter().collect();
+        let listed = |words: &[&str]| match language.case_insensitive {
+            true => words.iter().any(|w| w.eq_ignore_ascii_case(&word)),
+            false => words.contains(&word.as_str()),
+        };
+
+        if listed(language.keywords) {
+            TokenKind::Keyword
+        } else if listed(language.types) {
+            TokenKind::Type
+        } else if listed(language.constants) {
+            TokenKind::Constant
+        } else if self.next_non_space(end) == Some('(') {
+            TokenKind::Function
+        } else if language.colon_keys && self.chars.get(end) == Some(&':') {
+            TokenKind::Attribute
+        } else if language.capitalized_types && word.chars().next().is_some_and(char::is_uppercase)
+        {
+            let letters = word.chars().filter(|c| c.is_alphabetic()).count();
+            match word.chars().all(|c| !c.is_lowercase()) && letters > 1 {
+                true => TokenKind::Constant,
+                false => TokenKind::Type,
+            }
+        } else {
+            TokenKind::Plain
+        }
+    }
+
+    fn markup_token(&mut self) {
+        let start = self.position;
+
+        if self.starts_with(start, "<!--") {
+            let end = self
+                .find(start + 4, "-->")
+                .map_or(self.chars.len(), |at| at + 3);
+            return self.push(TokenKind::Comment, end);
+        }
+        if self.starts_with(start, "<![CDATA[") {
+            let end = self
+                .find(start + 9, "]]>")
+                .map_or(self.chars.len(), |at| at + 3);
+            return self.push(TokenKind::String, end);
+        }
+
+        let opens_tag = self.chars[start] == '<'
+            && self
+                .peek(1)
+                .is_some_and(|c| c.is_alphabetic() || "/!?".contains(c));
+        if opens_tag {
+            return self.markup_tag();
+        }
+
+        // Entities like `&amp;` and `&#x20;`.
+        if self.chars[start] == '&' {
+            let end = (start + 1..(start + 12).min(self.chars.len()))
+                .take_while(|&at| self.chars[at].is_alphanumeric() || "#;".contains(self.chars[at]))
+                .find(|&at| self.chars[at] == ';');
+            if let Some(end) = end {
+                return self.push(TokenKind::Constant, end + 1);
+            }
+        }
+
+        let end = (start + 1..self.chars.len())
+            .find(|&at| "<&".contains(self.chars[at]))
+            .unwrap_or(self.chars.len());
+        self.push(TokenKind::Plain, end);
+    }
+
+    /// A tag with its name and attributes, from `<` to `>`.
+    fn markup_tag(&mut self) {
+        let name_char = |c: char| c.is_alphanumeric() || "-:_./!?".contains(c);
+        let mut end = self.position + 1;
+        while self.chars.get(end).is_some_and(|&c| name_char(c)) {
+            end += 1;
+        }
+        self.push(TokenKind::Tag, end);
+
+        while let Some(c) = self.peek(0) {
+            let start = self.position;
+            match c {
+                '>' => return self.push(TokenKind::Tag, start + 1),
+                '/' | '?' if self.peek(1) == Some('>') => {
+                    return self.push(TokenKind::Tag, start + 2)
+                }
+                '"' | '\'' => {
+                    let end = self.find(start + 1, &c.to_string());
+                    self.push(TokenKind::String, end.map_or(self.chars.len(), |at| at + 1));
+                }
+                '=' => {
+                    self.push(TokenKind::Operator, start + 1);
+                    // Values don't have to be quoted.
+                    let mut end = self.position;
+                    while self.chars.get(end).is_some_and(|&c| name_char(c)) {
+                        end += 1;
+                    }
+                    self.push(TokenKind::String, end);
+                }
+                c if name_char(c) => {
+                    let mut end = start + 1;
+                    while self.chars.get(end).is_some_and(|&c| name_char(c)) {
+                        end += 1;
+                    }
+                    self.push(TokenKind::Attribute, end);
+                }
+                _ => self.push(TokenKind::Plain, start + 1),
+            }
+        }
+    }
+
+    fn markdown_line(&mut self) {
+        let start = self.position;
+        let end = (self.line_end(start) + 1).min(self.chars.len());
+        let line: String = self.chars[start..end].iter().collect();
+        let trimmed = line.trim_start();
+
+        // Fenced code blocks run until the same fence comes again.
+        if let Some(fence) = ["```", "~~~"]
+            .iter()
+            .find(|fence| trimmed.starts_with(*fence))
+        {
+            let close = self.find(end, fence).map_or(self.chars.len(), |at| {
+                (self.line_end(at) + 1).min(self.chars.len())
+            });
+            return self.push(TokenKind::String, close);
+        }
+        if trimmed.starts_with('#') {
+            return self.push(TokenKind::Keyword, end);
+        }
+        if trimmed.starts_with('>') {
+            return self.push(TokenKind::Comment, end);
+        }
+
+        let indent = line.len() - trimmed.len();
+        self.push(TokenKind::Plain, start + line[..indent].chars().count());
+        let marker = if ["- ", "* ", "+ "].iter().any(|m| trimmed.starts_with(m)) {
+            1
+        } else {
+            let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+            match digits > 0 && trimmed[digits..].starts_with(". ") {
+                true => digits + 1,
+                false => 0,
+            }
+        };
+        self.push(TokenKind::Operator, self.position + marker);
+
+        // Inline code between backticks.
+        while self.position < end {
+            let at = self.position;
+            if self.chars[at] == '`' {
+                if let Some(close) = self.find(at + 1, "`").filter(|&close| close < end) {
+                    self.push(TokenKind::String, close + 1);
+                    continue;
+                }
+            }
+            self.push(TokenKind::Plain, at + 1);
+        }
+    }
+
+    fn config_line(&mut self, separator: char) {
+        let language = self.language;
+        let start = self.position;
+        let end = self.line_end(start);
+
+        let indent = (start..end)
+            .find(|&at| !self.chars[at].is_whitespace())
+            .unwrap_or(end);
+        self.push(TokenKind::Plain, indent);
+
+        // Only YAML separates keys with colons, and starts documents and list items.
+        let yaml = separator == ':';
+        let comment = language
+            .line_comments
+            .iter()
+            .any(|prefix| self.starts_with(indent, prefix));
+
+        if self.starts_with(indent, "[") {
+            self.push(TokenKind::Type, end);
+        } else if yaml && (self.starts_with(indent, "---") || self.starts_with(indent, "...")) {
+            self.push(TokenKind::Keyword, end);
+        } else if !comment {
+            if yaml && self.starts_with(indent, "- ") {
+                self.push(TokenKind::Operator, indent + 1);
+                self.push(TokenKind::Plain, indent + 2);
+            }
+            if let Some(at) = self.key_separator(separator, end) {
+                let key_end = (self.position..at)
+                    .rfind(|&index| !self.chars[index].is_whitespace())
+                    .map_or(at, |index| index + 1);
+                self.push(TokenKind::Attribute, key_end);
+                self.push(TokenKind::Plain, at);
+                self.push(TokenKind::Operator, at + 1);
+            }
+        }
+
+        while self.position < end {
+            self.code_token();
+        }
+        if self.position < self.chars.len() {
+            self.push(TokenKind::Plain, self.position + 1);
+        }
+    }
+
+    /// Where the separator after the key at the current position is. Colons need a space
+    /// after them, so URLs and times in values aren't keys.
+    fn key_separator(&self, separator: char, end: usize) -> Option<usize> {
+        let mut quote = None;
+        for at in self.position..end {
+            let c = self.chars[at];
+            match quote {
+                Some(open) if c == open => quote = None,
+                Some(_) => {}
+                None if self.language.quotes.contains(&c) => quote = Some(c),
+                None if c == separator => {
+                    let next = self.chars.get(at + 1);
+                    if separator != ':' || next.is_none_or(|c| c.is_whitespace()) {
+                        return Some(at);
+                    }
+                }
+                None => {}
+            }
+        }
+        None
+    }
+}
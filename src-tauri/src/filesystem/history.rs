@@ -0,0 +1,366 @@
+use chrono::DateTime;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::utils::percent_decode;
+use super::{DIRECTORY, FILE};
+use crate::error::Error;
+
+/// Unpinned entries kept at most, the least recently used ones are dropped first.
+const MAX_ENTRIES: usize = 1000;
+
+/// Number of items returned when the frontend doesn't ask for a number.
+const DEFAULT_LIMIT: usize = 50;
+
+/// How long accesses are collected before the history is written, so browsing doesn't
+/// rewrite the whole file on every step.
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+static SAVE_SCHEDULED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    pub static ref HISTORY_FILE_PATH: String = {
+        let mut history_path = dirs::data_dir().expect("Failed to get base data path");
+        history_path.push(format!("{}-history.json", env!("CARGO_PKG_NAME")));
+        history_path.to_string_lossy().to_string()
+    };
+    static ref HISTORY: Mutex<HashMap<String, HistoryEntry>> = Mutex::new(load_history());
+    static ref XBEL_BOOKMARK: Regex = Regex::new(r"<bookmark\s[^>]*>").unwrap();
+    static ref XML_ATTRIBUTE: Regex = Regex::new(r#"([\w:-]+)="([^"]*)""#).unwrap();
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AccessSource {
+    Open,
+    Directory,
+    Preview,
+    Search,
+    /// Imported from the desktop's list of recently used files.
+    System,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    path: String,
+    file_type: String,
+    /// How it was last accessed.
+    source: AccessSource,
+    /// Seconds since the epoch.
+    last_accessed: u64,
+    access_count: u32,
+    /// Pinned entries are listed first and never dropped or cleared.
+    pinned: bool,
+}
+
+/// Filters for recent items, everything left out matches.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct RecentQuery {
+    /// `file` or `directory`.
+    file_type: Option<String>,
+    /// Lowercase file extensions without the dot.
+    extensions: Option<Vec<String>>,
+    /// Mount point of the volume the items are on.
+    volume: Option<String>,
+    /// Seconds since the epoch, inclusive.
+    since: Option<u64>,
+    until: Option<u64>,
+    sources: Option<Vec<AccessSource>>,
+    pinned_only: bool,
+    limit: Option<usize>,
+}
+
+impl RecentQuery {
+    fn matches(&self, entry: &HistoryEntry) -> bool {
+        let extension = Path::new(&entry.path)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        self.file_type
+            .as_ref()
+            .is_none_or(|t| *t == entry.file_type)
+            && self
+                .extensions
+                .as_ref()
+                .is_none_or(|extensions| extensions.contains(&extension))
+            && self
+                .volume
+                .as_ref()
+                .is_none_or(|volume| Path::new(&entry.path).starts_with(volume))
+            && self.since.is_none_or(|since| entry.last_accessed >= since)
+            && self.until.is_none_or(|until| entry.last_accessed <= until)
+            && self
+                .sources
+                .as_ref()
+                .is_none_or(|sources| sources.contains(&entry.source))
+            && (!self.pinned_only || entry.pinned)
+    }
+}
+
+/// Remembers that `path` was just accessed. Failing to save the history is only logged,
+/// it shouldn't keep anything from being opened.
+pub fn record_access(path: &str, source: AccessSource) {
+    let Ok(metadata) = fs::metadata(path) else {
+        return;
+    };
+
+    let mut history = HISTORY.lock().unwrap();
+    let entry = history
+        .entry(path.to_string())
+        .or_insert_with(|| new_entry(path, &metadata, source, 0));
+    entry.file_type = file_type(&metadata).to_string();
+    entry.source = source;
+    entry.last_accessed = now();
+    entry.access_count += 1;
+
+    trim_history(&mut history);
+    schedule_save();
+}
+
+/// Pinned items first, then the most recently accessed ones.
+#[tauri::command]
+pub async fn get_recent_items(query: Option<RecentQuery>) -> Result<Vec<HistoryEntry>, Error> {
+    let query = query.unwrap_or_default();
+    let history = HISTORY.lock().unwrap();
+
+    let mut items: Vec<HistoryEntry> = history
+        .values()
+        .filter(|entry| query.matches(entry))
+        .cloned()
+        .collect();
+    items.sort_by(|a, b| {
+        b.pinned
+            .cmp(&a.pinned)
+            .then_with(|| b.last_accessed.cmp(&a.last_accessed))
+    });
+    items.truncate(query.limit.unwrap_or(DEFAULT_LIMIT));
+
+    Ok(items)
+}
+
+/// Called by the frontend when a search result is opened.
+#[tauri::command]
+pub async fn record_search_pick(path: String) -> Result<(), Error> {
+    record_access(&path, AccessSource::Search);
+    Ok(())
+}
+
+/// Called by the frontend when it shows a preview it loads over the bytes protocol,
+/// which doesn't go through `get_file_preview`.
+#[tauri::command]
+pub async fn record_preview(path: String) -> Result<(), Error> {
+    record_access(&path, AccessSource::Preview);
+    Ok(())
+}
+
+/// Pinning something that isn't in the history yet adds it.
+#[tauri::command]
+pub async fn pin_recent_item(path: String, pinned: bool) -> Result<HistoryEntry, Error> {
+    let mut history = HISTORY.lock().unwrap();
+
+    if pinned && !history.contains_key(&path) {
+        let metadata = fs::metadata(&path)?;
+        let entry = new_entry(&path, &metadata, AccessSource::Open, now());
+        history.insert(path.clone(), entry);
+    }
+
+    let entry = history
+        .get_mut(&path)
+        .ok_or_else(|| Error::Custom(format!("{} isn't in the history", path)))?;
+    entry.pinned = pinned;
+    let entry = entry.clone();
+
+    save_history(&history);
+    Ok(entry)
+}
+
+#[tauri::command]
+pub async fn remove_recent_item(path: String) -> Result<(), Error> {
+    let mut history = HISTORY.lock().unwrap();
+    if history.remove(&path).is_some() {
+        save_history(&history);
+    }
+    Ok(())
+}
+
+/// Forgets unpinned items, only the ones accessed before `before` when given.
+#[tauri::command]
+pub async fn clear_history(before: Option<u64>) -> Result<(), Error> {
+    let mut history = HISTORY.lock().unwrap();
+    history.retain(|_, entry| entry.pinned || before.is_some_and(|b| entry.last_accessed >= b));
+    save_history(&history);
+    Ok(())
+}
+
+/// Adds the files other applications opened recently, from the freedesktop
+/// `recently-used.xbel`. Returns how many items were imported.
+#[tauri::command]
+pub async fn import_system_history() -> Result<usize, Error> {
+    if !cfg!(target_os = "linux") {
+        return Err(Error::Custom(
+            "Importing recently used files is only supported on Linux".to_string(),
+        ));
+    }
+
+    let xbel_path = dirs::data_dir()
+        .ok_or_else(|| Error::Custom("Failed to get base data path".to_string()))?
+        .join("recently-used.xbel");
+    let contents = fs::read_to_string(xbel_path)?;
+
+    let mut history = HISTORY.lock().unwrap();
+    let mut imported = 0;
+
+    for (path, accessed) in parse_xbel(&contents) {
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+
+        match history.get_mut(&path) {
+            Some(entry) if entry.last_accessed >= accessed => continue,
+            Some(entry) => {
+                entry.last_accessed = accessed;
+                entry.source = AccessSource::System;
+            }
+            None => {
+                let entry = new_entry(&path, &metadata, AccessSource::System, accessed);
+                history.insert(path, entry);
+            }
+        }
+        imported += 1;
+    }
+
+    trim_history(&mut history);
+    save_history(&history);
+    Ok(imported)
+}
+
+fn new_entry(
+    path: &str,
+    metadata: &fs::Metadata,
+    source: AccessSource,
+    accessed: u64,
+) -> HistoryEntry {
+    HistoryEntry {
+        path: path.to_string(),
+        file_type: file_type(metadata).to_string(),
+        source,
+        last_accessed: accessed,
+        access_count: 0,
+        pinned: false,
+    }
+}
+
+fn file_type(metadata: &fs::Metadata) -> &'static str {
+    if metadata.is_dir() {
+        DIRECTORY
+    } else {
+        FILE
+    }
+}
+
+/// Local paths in an xbel file with the last time they were used, in seconds since the epoch.
+fn parse_xbel(contents: &str) -> Vec<(String, u64)> {
+    XBEL_BOOKMARK
+        .find_iter(contents)
+        .filter_map(|bookmark| {
+            let attributes: HashMap<&str, String> = XML_ATTRIBUTE
+                .captures_iter(bookmark.as_str())
+                .map(|c| (c.get(1).unwrap().as_str(), unescape_xml(&c[2])))
+                .collect();
+
+            let path = attributes.get("href")?.strip_prefix("file://")?;
+            let path = percent_decode(path)?;
+
+            let accessed = ["added", "modified", "visited"]
+                .iter()
+                .filter_map(|name| attributes.get(name))
+                .filter_map(|time| DateTime::parse_from_rfc3339(time).ok())
+                .map(|time| time.timestamp().max(0) as u64)
+                .max()?;
+
+            Some((path, accessed))
+        })
+        .collect()
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Drops the least recently used unpinned entries above `MAX_ENTRIES`.
+fn trim_history(history: &mut HashMap<String, HistoryEntry>) {
+    let mut unpinned: Vec<(u64, String)> = history
+        .values()
+        .filter(|entry| !entry.pinned)
+        .map(|entry| (entry.last_accessed, entry.path.clone()))
+        .collect();
+    if unpinned.len() <= MAX_ENTRIES {
+        return;
+    }
+
+    unpinned.sort();
+    let excess = unpinned.len() - MAX_ENTRIES;
+    for (_, path) in unpinned.into_iter().take(excess) {
+        history.remove(&path);
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+fn load_history() -> HashMap<String, HistoryEntry> {
+    let entries: Vec<HistoryEntry> = fs::read(&HISTORY_FILE_PATH[..])
+        .ok()
+        .and_then(|contents| serde_json::from_slice(&contents).ok())
+        .unwrap_or_default();
+
+    entries
+        .into_iter()
+        .map(|entry| (entry.path.clone(), entry))
+        .collect()
+}
+
+/// Saves the history after `SAVE_DELAY`, unless a save is already waiting.
+fn schedule_save() {
+    if SAVE_SCHEDULED.swap(true, Ordering::AcqRel) {
+        return;
+    }
+
+    thread::spawn(|| {
+        thread::sleep(SAVE_DELAY);
+        // Cleared first, so anything recorded while saving schedules another save.
+        SAVE_SCHEDULED.store(false, Ordering::Release);
+        save_history(&HISTORY.lock().unwrap());
+    });
+}
+
+fn save_history(history: &HashMap<String, HistoryEntry>) {
+    let entries: Vec<&HistoryEntry> = history.values().collect();
+    let result = serde_json::to_vec(&entries)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| {
+            fs::write(&HISTORY_FILE_PATH[..], contents).map_err(|e| e.to_string())
+        });
+
+    if let Err(e) = result {
+        eprintln!("Failed to save the history: {}", e);
+    }
+}
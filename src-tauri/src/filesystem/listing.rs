@@ -0,0 +1,359 @@
+use lazy_static::lazy_static;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use super::disk_usage::cached_folder_size;
+use super::explorer::{check_is_supported_project, is_git_directory};
+use super::history::{record_access, AccessSource};
+use super::volume::DirectoryChild;
+use crate::error::Error;
+
+/// Number of entries sent per `directory_chunk` event.
+const STREAM_CHUNK_SIZE: usize = 500;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    /// The order the filesystem returns entries in, which is the fastest.
+    #[default]
+    None,
+    /// Natural order, so "file2" comes before "file10".
+    Name,
+    Size,
+    Modified,
+    Type,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ListOptions {
+    pub sort_by: SortKey,
+    pub descending: bool,
+    pub directories_first: bool,
+    /// Only keeps entries whose name contains this, ignoring case.
+    pub filter: Option<String>,
+    pub hide_hidden: bool,
+    /// Index of the first entry to return, after sorting and filtering.
+    pub offset: usize,
+    /// Maximum number of entries to return, everything when `None`.
+    pub limit: Option<usize>,
+}
+
+/// One window of a directory listing.
+pub struct Listing {
+    pub entries: Vec<DirectoryChild>,
+    /// Number of entries that matched the filter, across all windows.
+    pub total: usize,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DirectoryChunk {
+    id: String,
+    entries: Vec<DirectoryChild>,
+    done: bool,
+}
+
+struct CachedListing {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    entries: Vec<DirectoryChild>,
+}
+
+lazy_static! {
+    /// The last directory read, so paging through a huge directory only reads it once.
+    static ref LAST_LISTING: Mutex<Option<CachedListing>> = Mutex::new(None);
+}
+
+/// Reads, filters and sorts a directory and returns the requested window of it.
+/// Only the entries in that window are probed for git repositories and projects.
+pub fn list_directory(path: &Path, options: &ListOptions) -> io::Result<Listing> {
+    // The first page always reads the directory again so a refresh picks up changes,
+    // later pages reuse it as long as the directory itself hasn't changed.
+    let entries = match cached_entries(path, options.offset > 0) {
+        Some(entries) => entries,
+        None => {
+            let entries = read_entries(path)?;
+            cache_entries(path, &entries);
+            entries
+        }
+    };
+
+    let mut entries = filter_entries(entries, options);
+    sort_entries(&mut entries, options);
+
+    let total = entries.len();
+    let mut window: Vec<DirectoryChild> = entries
+        .into_iter()
+        .skip(options.offset)
+        .take(options.limit.unwrap_or(usize::MAX))
+        .collect();
+
+    probe_entries(&mut window);
+
+    Ok(Listing {
+        entries: window,
+        total,
+    })
+}
+
+/// Lists a directory in chunks sent as `directory_chunk` events tagged with `id`.
+/// Unsorted listings are sent while the directory is still being read, sorted ones
+/// have to be read completely first.
+#[tauri::command]
+pub async fn stream_directory(
+    id: String,
+    path: String,
+    options: Option<ListOptions>,
+    window: tauri::Window,
+) -> Result<(), Error> {
+    let options = ListOptions {
+        offset: 0,
+        limit: None,
+        ..options.unwrap_or_default()
+    };
+    let path = PathBuf::from(path);
+
+    let emit = |entries: Vec<DirectoryChild>, done: bool| {
+        let chunk = DirectoryChunk {
+            id: id.clone(),
+            entries,
+            done,
+        };
+        if let Err(e) = window.emit("directory_chunk", chunk) {
+            eprintln!("Failed to emit directory chunk: {}", e);
+        }
+    };
+
+    if options.sort_by != SortKey::None || options.directories_first {
+        let entries = read_entries(&path)?;
+        cache_entries(&path, &entries);
+        record_access(&path.to_string_lossy(), AccessSource::Directory);
+
+        let mut entries = filter_entries(entries, &options);
+        sort_entries(&mut entries, &options);
+
+        for chunk in entries.chunks(STREAM_CHUNK_SIZE) {
+            let mut chunk = chunk.to_vec();
+            probe_entries(&mut chunk);
+            emit(chunk, false);
+        }
+
+        emit(Vec::new(), true);
+        return Ok(());
+    }
+
+    let mut paths = Vec::with_capacity(STREAM_CHUNK_SIZE);
+    let mut all_entries = Vec::new();
+
+    for entry in fs::read_dir(&path)? {
+        match entry {
+            Ok(entry) => paths.push(entry.path()),
+            Err(e) => eprintln!("Failed to read entry in {}: {}", path.display(), e),
+        }
+
+        if paths.len() == STREAM_CHUNK_SIZE {
+            let entries = read_children(&paths);
+            all_entries.extend(entries.iter().cloned());
+            send_chunk(entries, &options, &emit);
+            paths.clear();
+        }
+    }
+
+    let entries = read_children(&paths);
+    all_entries.extend(entries.iter().cloned());
+    send_chunk(entries, &options, &emit);
+
+    cache_entries(&path, &all_entries);
+    record_access(&path.to_string_lossy(), AccessSource::Directory);
+    emit(Vec::new(), true);
+
+    Ok(())
+}
+
+fn send_chunk<F>(entries: Vec<DirectoryChild>, options: &ListOptions, emit: &F)
+where
+    F: Fn(Vec<DirectoryChild>, bool),
+{
+    let mut entries = filter_entries(entries, options);
+    if entries.is_empty() {
+        return;
+    }
+
+    probe_entries(&mut entries);
+    emit(entries, false);
+}
+
+fn read_entries(path: &Path) -> io::Result<Vec<DirectoryChild>> {
+    let paths: Vec<PathBuf> = fs::read_dir(path)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .collect();
+
+    Ok(read_children(&paths))
+}
+
+/// Reads the details of every path in parallel, skipping the ones that vanished meanwhile.
+fn read_children(paths: &[PathBuf]) -> Vec<DirectoryChild> {
+    paths
+        .par_iter()
+        .filter_map(|path| DirectoryChild::from_path(path).ok())
+        .collect()
+}
+
+fn cached_entries(path: &Path, allow: bool) -> Option<Vec<DirectoryChild>> {
+    if !allow {
+        return None;
+    }
+
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+    let cache = LAST_LISTING.lock().ok()?;
+
+    match cache.as_ref() {
+        Some(cached) if cached.path == path && cached.modified == modified => {
+            Some(cached.entries.clone())
+        }
+        _ => None,
+    }
+}
+
+fn cache_entries(path: &Path, entries: &[DirectoryChild]) {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+
+    if let Ok(mut cache) = LAST_LISTING.lock() {
+        *cache = Some(CachedListing {
+            path: path.to_path_buf(),
+            modified,
+            entries: entries.to_vec(),
+        });
+    }
+}
+
+fn filter_entries(entries: Vec<DirectoryChild>, options: &ListOptions) -> Vec<DirectoryChild> {
+    let filter = options
+        .filter
+        .as_ref()
+        .map(|filter| filter.to_lowercase())
+        .filter(|filter| !filter.is_empty());
+
+    if filter.is_none() && !options.hide_hidden {
+        return entries;
+    }
+
+    entries
+        .into_iter()
+        .filter(|entry| !(options.hide_hidden && entry.hidden))
+        .filter(|entry| match &filter {
+            Some(filter) => entry.name.to_lowercase().contains(filter),
+            None => true,
+        })
+        .collect()
+}
+
+fn sort_entries(entries: &mut [DirectoryChild], options: &ListOptions) {
+    if options.sort_by == SortKey::None && !options.directories_first {
+        return;
+    }
+
+    entries.par_sort_unstable_by(|a, b| {
+        let directories = if options.directories_first {
+            b.is_dir.cmp(&a.is_dir)
+        } else {
+            Ordering::Equal
+        };
+
+        let ordering = match options.sort_by {
+            SortKey::None => Ordering::Equal,
+            SortKey::Name => natural_cmp(&a.name, &b.name),
+            SortKey::Size => a
+                .size
+                .unwrap_or(0)
+                .cmp(&b.size.unwrap_or(0))
+                .then_with(|| natural_cmp(&a.name, &b.name)),
+            SortKey::Modified => a
+                .modified
+                .cmp(&b.modified)
+                .then_with(|| natural_cmp(&a.name, &b.name)),
+            SortKey::Type => a
+                .file_type
+                .cmp(&b.file_type)
+                .then_with(|| natural_cmp(&a.name, &b.name)),
+        };
+
+        let ordering = if options.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+
+        directories.then(ordering)
+    });
+}
+
+/// Compares names the way people expect: ignoring case and treating runs of digits
+/// as numbers, so "IMG_2" sorts before "img_10".
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x_digits = take_digits(&mut a_chars);
+                let y_digits = take_digits(&mut b_chars);
+
+                let x_trimmed = x_digits.trim_start_matches('0');
+                let y_trimmed = y_digits.trim_start_matches('0');
+
+                let ordering = x_trimmed
+                    .len()
+                    .cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed));
+
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+        digits.push(c);
+        chars.next();
+    }
+    digits
+}
+
+/// Fills in what needs extra reads per entry, so it is only done for the entries that
+/// are sent: the type of files from their content, and for directories the git and
+/// project flags and the size if they have been measured before.
+pub fn probe_entries(entries: &mut [DirectoryChild]) {
+    entries.par_iter_mut().for_each(|entry| {
+        if !entry.is_dir {
+            entry.detect_content_type();
+            return;
+        }
+
+        entry.size = cached_folder_size(Path::new(&entry.path));
+        entry.is_git = is_git_directory(&entry.path).unwrap_or(false);
+        entry.is_project = check_is_supported_project(entry.path.clone()).unwrap_or(false);
+    });
+}
@@ -0,0 +1,402 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use super::{get_file_description, AUDIO_EXTENSIONS, IMAGE_EXTENSIONS, TEXT_EXTENSIONS};
+
+/// Bytes read from the start of a file, enough for every signature below including the
+/// tar header at offset 257.
+const HEADER_SIZE: usize = 512;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum FileCategory {
+    Directory,
+    Image,
+    Audio,
+    Video,
+    Text,
+    Document,
+    Archive,
+    Executable,
+    Font,
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct DetectedType {
+    pub mime_type: String,
+    pub category: FileCategory,
+    /// Human readable type, e.g. "PNG image".
+    pub description: String,
+}
+
+/// A file signature. Generic containers like ZIP are used by many formats, so when
+/// one of them matches, a known extension says more about the file.
+struct Signature {
+    offset: usize,
+    magic: &'static [u8],
+    mime_type: &'static str,
+    description: &'static str,
+    container: bool,
+}
+
+const fn signature(
+    offset: usize,
+    magic: &'static [u8],
+    mime_type: &'static str,
+    description: &'static str,
+) -> Signature {
+    Signature {
+        offset,
+        magic,
+        mime_type,
+        description,
+        container: false,
+    }
+}
+
+const fn container(
+    magic: &'static [u8],
+    mime_type: &'static str,
+    description: &'static str,
+) -> Signature {
+    Signature {
+        offset: 0,
+        magic,
+        mime_type,
+        description,
+        container: true,
+    }
+}
+
+const SIGNATURES: &[Signature] = &[
+    signature(0, b"\x89PNG\r\n\x1a\n", "image/png", "PNG image"),
+    signature(0, b"\xff\xd8\xff", "image/jpeg", "JPEG image"),
+    signature(0, b"GIF87a", "image/gif", "GIF image"),
+    signature(0, b"GIF89a", "image/gif", "GIF image"),
+    signature(0, b"BM", "image/bmp", "Bitmap image"),
+    signature(0, b"II*\0", "image/tiff", "TIFF image"),
+    signature(0, b"MM\0*", "image/tiff", "TIFF image"),
+    signature(0, b"\0\0\x01\0", "image/x-icon", "Icon"),
+    signature(
+        0,
+        b"8BPS",
+        "image/vnd.adobe.photoshop",
+        "Adobe Photoshop document",
+    ),
+    signature(0, b"%PDF-", "application/pdf", "PDF document"),
+    signature(0, b"{\\rtf", "application/rtf", "Rich Text document"),
+    signature(0, b"\x1f\x8b", "application/gzip", "Gzip archive"),
+    signature(0, b"BZh", "application/x-bzip2", "Bzip2 archive"),
+    signature(0, b"\xfd7zXZ\0", "application/x-xz", "XZ archive"),
+    signature(
+        0,
+        b"\x28\xb5\x2f\xfd",
+        "application/zstd",
+        "Zstandard archive",
+    ),
+    signature(
+        0,
+        b"7z\xbc\xaf\x27\x1c",
+        "application/x-7z-compressed",
+        "7-Zip archive",
+    ),
+    signature(0, b"Rar!\x1a\x07", "application/vnd.rar", "RAR archive"),
+    signature(257, b"ustar", "application/x-tar", "Tarball archive"),
+    signature(0, b"\x7fELF", "application/x-executable", "ELF executable"),
+    signature(
+        0,
+        b"\xfe\xed\xfa\xce",
+        "application/x-mach-binary",
+        "Mach-O binary",
+    ),
+    signature(
+        0,
+        b"\xfe\xed\xfa\xcf",
+        "application/x-mach-binary",
+        "Mach-O binary",
+    ),
+    signature(
+        0,
+        b"\xce\xfa\xed\xfe",
+        "application/x-mach-binary",
+        "Mach-O binary",
+    ),
+    signature(
+        0,
+        b"\xcf\xfa\xed\xfe",
+        "application/x-mach-binary",
+        "Mach-O binary",
+    ),
+    signature(0, b"\0asm", "application/wasm", "WebAssembly module"),
+    signature(
+        0,
+        b"SQLite format 3\0",
+        "application/vnd.sqlite3",
+        "SQLite database",
+    ),
+    signature(0, b"ID3", "audio/mpeg", "MP3 audio"),
+    signature(0, b"\xff\xfb", "audio/mpeg", "MP3 audio"),
+    signature(0, b"\xff\xf3", "audio/mpeg", "MP3 audio"),
+    signature(0, b"\xff\xf2", "audio/mpeg", "MP3 audio"),
+    signature(0, b"fLaC", "audio/flac", "FLAC audio"),
+    signature(0, b"OggS", "audio/ogg", "Ogg audio"),
+    signature(0, b"MThd", "audio/midi", "MIDI audio"),
+    signature(0, b"wOFF", "font/woff", "Web Open Font Format (WOFF) font"),
+    signature(
+        0,
+        b"wOF2",
+        "font/woff2",
+        "Web Open Font Format (WOFF2) font",
+    ),
+    signature(0, b"OTTO", "font/otf", "OpenType font"),
+    signature(0, b"\0\x01\0\0\0", "font/ttf", "TrueType font"),
+    container(b"PK\x03\x04", "application/zip", "ZIP archive"),
+    container(b"PK\x05\x06", "application/zip", "ZIP archive"),
+    // Compound files hold old Office documents and installers alike.
+    container(
+        b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1",
+        "application/x-ole-storage",
+        "Compound document",
+    ),
+    container(b"MZ", "application/x-msdownload", "Executable"),
+];
+
+/// Works out the type of `path` from its first bytes, falling back to the extension
+/// when the content isn't recognised or can't be read.
+pub fn detect_file_type(path: &Path) -> DetectedType {
+    if path.is_dir() {
+        return directory_type();
+    }
+
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    let read =
+        File::open(path).and_then(|file| file.take(HEADER_SIZE as u64).read_to_end(&mut header));
+    let extension = lowercase_extension(path);
+
+    match read {
+        Ok(_) => detect_from_header(&header, &extension),
+        Err(_) => from_extension(&extension),
+    }
+}
+
+/// Same as `detect_file_type`, for when the first bytes have been read already.
+pub fn detect_from_header(header: &[u8], extension: &str) -> DetectedType {
+    let by_extension = from_extension(extension);
+
+    if let Some(signature) = match_signature(header) {
+        let specific_extension = !matches!(
+            by_extension.category,
+            FileCategory::Text | FileCategory::Other
+        );
+        if signature.container && specific_extension {
+            return by_extension;
+        }
+        return DetectedType {
+            mime_type: signature.mime_type.to_string(),
+            category: category_for(signature.mime_type),
+            description: signature.description.to_string(),
+        };
+    }
+
+    if let Some(detected) = match_riff_or_ftyp(header) {
+        return detected;
+    }
+
+    if header.is_empty() || !looks_like_text(header) {
+        return by_extension;
+    }
+
+    // Text could be anything, a known text extension tells the language.
+    if by_extension.category == FileCategory::Text {
+        return by_extension;
+    }
+
+    let (mime_type, description) = sniff_text(header);
+    DetectedType {
+        mime_type: mime_type.to_string(),
+        category: category_for(mime_type),
+        description: description.to_string(),
+    }
+}
+
+/// Works out the type of `path` from its name alone, for listings where reading every
+/// file would be too slow. `detect_file_type` refines it for the entries on screen.
+pub fn detect_file_type_by_name(path: &Path, is_dir: bool) -> DetectedType {
+    if is_dir {
+        return directory_type();
+    }
+    from_extension(&lowercase_extension(path))
+}
+
+fn directory_type() -> DetectedType {
+    DetectedType {
+        mime_type: "inode/directory".to_string(),
+        category: FileCategory::Directory,
+        description: "Folder".to_string(),
+    }
+}
+
+fn lowercase_extension(path: &Path) -> String {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn from_extension(extension: &str) -> DetectedType {
+    let mime_type = mime_guess::from_ext(extension)
+        .first_or_octet_stream()
+        .essence_str()
+        .to_string();
+
+    let category = if TEXT_EXTENSIONS.contains(&extension) {
+        FileCategory::Text
+    } else if IMAGE_EXTENSIONS.contains(&extension) {
+        FileCategory::Image
+    } else if AUDIO_EXTENSIONS.contains(&extension) {
+        FileCategory::Audio
+    } else {
+        category_for(&mime_type)
+    };
+
+    DetectedType {
+        description: get_file_description(extension).to_string(),
+        mime_type,
+        category,
+    }
+}
+
+fn match_signature(header: &[u8]) -> Option<&'static Signature> {
+    // Short signatures like `BM` also start ordinary text, while the real formats have
+    // binary data right after them.
+    let is_text = looks_like_text(header);
+
+    SIGNATURES.iter().find(|signature| {
+        header
+            .get(signature.offset..signature.offset + signature.magic.len())
+            .is_some_and(|bytes| bytes == signature.magic)
+            && !(is_text && signature.magic.len() <= 3)
+    })
+}
+
+/// RIFF and ISO media files carry their actual format a few bytes in.
+fn match_riff_or_ftyp(header: &[u8]) -> Option<DetectedType> {
+    let (mime_type, description) = match (header.get(0..4)?, header.get(4..8)?, header.get(8..12)?)
+    {
+        (b"RIFF", _, b"WAVE") => ("audio/wav", "WAV audio"),
+        (b"RIFF", _, b"WEBP") => ("image/webp", "WebP image"),
+        (b"RIFF", _, b"AVI ") => ("video/x-msvideo", "AVI video"),
+        (_, b"ftyp", b"M4A " | b"M4B " | b"M4P ") => ("audio/mp4", "M4A audio"),
+        (_, b"ftyp", b"heic" | b"heix" | b"mif1" | b"msf1") => ("image/heic", "HEIC image"),
+        (_, b"ftyp", b"avif") => ("image/avif", "AVIF image"),
+        (_, b"ftyp", b"qt  ") => ("video/quicktime", "QuickTime video"),
+        (_, b"ftyp", b"3gp4" | b"3gp5" | b"3gp6") => ("video/3gpp", "3GP video"),
+        (_, b"ftyp", _) => ("video/mp4", "MP4 video"),
+        (b"\x1a\x45\xdf\xa3", _, _) => {
+            if header.windows(4).any(|window| window == b"webm") {
+                ("video/webm", "WebM video")
+            } else {
+                ("video/x-matroska", "Matroska video")
+            }
+        }
+        _ => return None,
+    };
+
+    Some(DetectedType {
+        mime_type: mime_type.to_string(),
+        category: category_for(mime_type),
+        description: description.to_string(),
+    })
+}
+
+/// Text starts with a UTF-16 byte order mark, or has no NUL bytes and is valid UTF-8,
+/// apart from a character cut off at the end of the header.
+fn looks_like_text(header: &[u8]) -> bool {
+    if header.starts_with(b"\xff\xfe") || header.starts_with(b"\xfe\xff") {
+        return true;
+    }
+
+    if header.contains(&0) {
+        return false;
+    }
+
+    match std::str::from_utf8(header) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none() && header.len() - e.valid_up_to() < 4,
+    }
+}
+
+fn sniff_text(header: &[u8]) -> (&'static str, &'static str) {
+    let text = String::from_utf8_lossy(header);
+    let start = text.trim_start_matches('\u{feff}').trim_start();
+    let lowercase = start.to_lowercase();
+
+    if let Some(shebang) = start.strip_prefix("#!") {
+        let interpreter = shebang.lines().next().unwrap_or_default();
+        if interpreter.contains("python") {
+            ("text/x-python", "Python script")
+        } else if interpreter.contains("node") {
+            ("text/javascript", "JavaScript file")
+        } else if interpreter.contains("perl") {
+            ("text/x-perl", "Perl script")
+        } else if interpreter.contains("ruby") {
+            ("text/x-ruby", "Ruby script")
+        } else {
+            ("text/x-shellscript", "Shell script")
+        }
+    } else if lowercase.starts_with("<!doctype html") || lowercase.starts_with("<html") {
+        ("text/html", "HTML document")
+    } else if lowercase.starts_with("<svg")
+        || (lowercase.starts_with("<?xml") && lowercase.contains("<svg"))
+    {
+        ("image/svg+xml", "SVG image")
+    } else if lowercase.starts_with("<?xml") {
+        ("application/xml", "XML file")
+    } else if start.starts_with('{') || start.starts_with('[') {
+        if serde_json::from_slice::<serde_json::Value>(header).is_ok() {
+            ("application/json", "JSON file")
+        } else {
+            ("text/plain", "Text file")
+        }
+    } else {
+        ("text/plain", "Text file")
+    }
+}
+
+fn category_for(mime_type: &str) -> FileCategory {
+    let (top_level, subtype) = mime_type.split_once('/').unwrap_or((mime_type, ""));
+
+    match top_level {
+        "inode" if subtype == "directory" => return FileCategory::Directory,
+        "image" => return FileCategory::Image,
+        "audio" => return FileCategory::Audio,
+        "video" => return FileCategory::Video,
+        "text" => return FileCategory::Text,
+        "font" => return FileCategory::Font,
+        _ => {}
+    }
+
+    match subtype {
+        "json" | "xml" | "javascript" | "toml" | "yaml" | "x-yaml" | "x-sh" | "sql" => {
+            FileCategory::Text
+        }
+        "pdf" | "rtf" | "msword" | "epub+zip" | "x-ole-storage" => FileCategory::Document,
+        _ if subtype.starts_with("vnd.openxmlformats")
+            || subtype.starts_with("vnd.oasis.opendocument")
+            || subtype.starts_with("vnd.ms-") =>
+        {
+            FileCategory::Document
+        }
+        "zip" | "gzip" | "x-tar" | "x-bzip2" | "x-xz" | "zstd" | "x-7z-compressed" | "vnd.rar"
+        | "x-rar-compressed" | "java-archive" | "x-iso9660-image" => FileCategory::Archive,
+        "x-executable"
+        | "x-mach-binary"
+        | "x-msdownload"
+        | "x-sharedlib"
+        | "wasm"
+        | "vnd.microsoft.portable-executable" => FileCategory::Executable,
+        "font-woff" | "x-font-ttf" | "x-font-otf" => FileCategory::Font,
+        _ => FileCategory::Other,
+    }
+}
@@ -1,9 +1,31 @@
 pub mod audio;
+pub mod bookmarks;
+pub mod bulk_rename;
 pub mod cache;
+pub mod checksum;
+pub mod compare;
+pub mod disk_usage;
+pub mod duplicates;
 pub mod explorer;
+pub mod formatted_preview;
 pub mod git_utils;
+pub mod hex_preview;
+pub mod highlight;
+pub mod history;
+pub mod listing;
+pub mod mime;
+pub mod open_with;
+pub mod operations;
+pub mod permissions;
+pub mod protocol;
+pub mod similar_images;
+pub mod tags;
+pub mod text_preview;
+pub mod thumbnails;
+pub mod transfer;
 pub mod utils;
 pub mod volume;
+pub mod watcher;
 
 pub const DIRECTORY: &str = "directory";
 pub const FILE: &str = "file";
@@ -0,0 +1,515 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use walkdir::WalkDir;
+
+use super::history::{record_access, AccessSource};
+use super::mime::detect_file_type;
+use super::utils::file_uri;
+use crate::error::Error;
+
+const DEFAULT_APPLICATIONS: &str = "Default Applications";
+const ADDED_ASSOCIATIONS: &str = "Added Associations";
+const REMOVED_ASSOCIATIONS: &str = "Removed Associations";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Application {
+    /// Desktop file id, e.g. `org.gnome.gedit.desktop`.
+    id: String,
+    name: String,
+    icon: Option<String>,
+    is_default: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OpenWithApplications {
+    mime_type: String,
+    /// The default application first.
+    applications: Vec<Application>,
+}
+
+/// The parts of a `.desktop` file needed to list and launch an application.
+#[derive(Debug, Clone)]
+struct DesktopEntry {
+    id: String,
+    path: PathBuf,
+    name: String,
+    icon: Option<String>,
+    exec: String,
+    mime_types: Vec<String>,
+    terminal: bool,
+}
+
+/// Associations from all `mimeapps.list` files, most important file first.
+#[derive(Debug, Default)]
+struct MimeApps {
+    defaults: Vec<HashMap<String, Vec<String>>>,
+    added: HashMap<String, Vec<String>>,
+    removed: HashMap<String, HashSet<String>>,
+}
+
+/// Lists the applications that can open `path`, according to the installed desktop
+/// entries and the associations in `mimeapps.list`.
+#[tauri::command]
+pub async fn get_applications_for_path(path: String) -> Result<OpenWithApplications, Error> {
+    ensure_supported()?;
+
+    let mime_type = detect_file_type(Path::new(&path)).mime_type;
+    let entries = desktop_entries();
+    let mime_apps = MimeApps::load();
+
+    let default = mime_apps.default_for(&mime_type, &entries);
+    let applications = mime_apps
+        .applications_for(&mime_type, &entries)
+        .into_iter()
+        .map(|entry| Application {
+            is_default: default.as_deref() == Some(entry.id.as_str()),
+            id: entry.id.clone(),
+            name: entry.name.clone(),
+            icon: entry.icon.clone(),
+        })
+        .collect();
+
+    Ok(OpenWithApplications {
+        mime_type,
+        applications,
+    })
+}
+
+/// Launches the application with the desktop file id `application` on `path`.
+#[tauri::command]
+pub async fn open_file_with(path: String, application: String) -> Result<(), Error> {
+    ensure_supported()?;
+
+    let entries = desktop_entries();
+    let entry = entries
+        .get(&application)
+        .ok_or_else(|| Error::Custom(format!("Unknown application {}", application)))?;
+
+    let mut args = exec_args(entry, &path);
+    if entry.terminal {
+        args.splice(0..0, ["x-terminal-emulator".to_string(), "-e".to_string()]);
+    }
+    let (program, args) = args
+        .split_first()
+        .ok_or_else(|| Error::Custom(format!("{} has no command to run", entry.name)))?;
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| Error::Custom(format!("Failed to start {}: {}", entry.name, e)))?;
+
+    // Reaps the application once it exits, it outlives the command otherwise.
+    std::thread::spawn(move || child.wait());
+
+    record_access(&path, AccessSource::Open);
+    Ok(())
+}
+
+/// Makes `application` the default for `mime_type` in the user's `mimeapps.list`.
+#[tauri::command]
+pub async fn set_default_application(mime_type: String, application: String) -> Result<(), Error> {
+    ensure_supported()?;
+
+    if !desktop_entries().contains_key(&application) {
+        return Err(Error::Custom(format!(
+            "Unknown application {}",
+            application
+        )));
+    }
+
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| Error::Custom("Failed to get base config path".to_string()))?;
+    let mimeapps_path = config_dir.join("mimeapps.list");
+    let contents = fs::read_to_string(&mimeapps_path).unwrap_or_default();
+
+    let contents = set_ini_value(
+        &contents,
+        DEFAULT_APPLICATIONS,
+        &mime_type,
+        &format!("{};", application),
+    );
+
+    fs::create_dir_all(&config_dir)?;
+    fs::write(mimeapps_path, contents)?;
+    Ok(())
+}
+
+fn ensure_supported() -> Result<(), Error> {
+    if cfg!(target_os = "linux") {
+        Ok(())
+    } else {
+        Err(Error::Custom(
+            "Choosing the application is only supported on Linux".to_string(),
+        ))
+    }
+}
+
+impl MimeApps {
+    fn load() -> Self {
+        let mut mime_apps = MimeApps::default();
+
+        for path in mimeapps_lists() {
+            let Ok(contents) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let sections = parse_ini(&contents);
+
+            if let Some(defaults) = sections.get(DEFAULT_APPLICATIONS) {
+                mime_apps.defaults.push(defaults.clone());
+            }
+            for (mime_type, ids) in sections.get(ADDED_ASSOCIATIONS).into_iter().flatten() {
+                let added = mime_apps.added.entry(mime_type.clone()).or_default();
+                // A more important file can't remove what it added itself.
+                let removed = mime_apps.removed.get(mime_type);
+                for id in ids {
+                    if !added.contains(id) && !removed.is_some_and(|r| r.contains(id)) {
+                        added.push(id.clone());
+                    }
+                }
+            }
+            for (mime_type, ids) in sections.get(REMOVED_ASSOCIATIONS).into_iter().flatten() {
+                let removed = mime_apps.removed.entry(mime_type.clone()).or_default();
+                removed.extend(ids.iter().cloned());
+            }
+        }
+
+        mime_apps
+    }
+
+    /// The first installed default from the most important file that has one.
+    fn default_for(
+        &self,
+        mime_type: &str,
+        entries: &HashMap<String, DesktopEntry>,
+    ) -> Option<String> {
+        self.defaults
+            .iter()
+            .filter_map(|defaults| defaults.get(mime_type))
+            .flatten()
+            .find(|id| entries.contains_key(*id))
+            .cloned()
+    }
+
+    fn applications_for<'a>(
+        &self,
+        mime_type: &str,
+        entries: &'a HashMap<String, DesktopEntry>,
+    ) -> Vec<&'a DesktopEntry> {
+        let mut ids: Vec<String> = self.default_for(mime_type, entries).into_iter().collect();
+
+        // Every text format can be opened as plain text.
+        let mut mime_types = vec![mime_type];
+        if mime_type.starts_with("text/") && mime_type != "text/plain" {
+            mime_types.push("text/plain");
+        }
+
+        for mime_type in mime_types {
+            let removed = self.removed.get(mime_type);
+            let is_removed = |id: &String| removed.is_some_and(|removed| removed.contains(id));
+
+            ids.extend(self.added.get(mime_type).into_iter().flatten().cloned());
+
+            let mut declared: Vec<&DesktopEntry> = entries
+                .values()
+                .filter(|entry| entry.mime_types.iter().any(|m| m == mime_type))
+                .filter(|entry| !is_removed(&entry.id))
+                .collect();
+            declared.sort_by_key(|entry| entry.name.to_lowercase());
+            ids.extend(declared.into_iter().map(|entry| entry.id.clone()));
+        }
+
+        let mut seen = HashSet::new();
+        ids.into_iter()
+            .filter(|id| seen.insert(id.clone()))
+            .filter_map(|id| entries.get(&id))
+            .collect()
+    }
+}
+
+/// Installed applications by desktop file id. Entries in the user's data directory
+/// hide system ones with the same id.
+fn desktop_entries() -> HashMap<String, DesktopEntry> {
+    let mut entries = HashMap::new();
+
+    for data_dir in data_dirs() {
+        let applications = data_dir.join("applications");
+
+        for file in WalkDir::new(&applications)
+            .follow_links(true)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|file| file.path().extension().is_some_and(|ext| ext == "desktop"))
+        {
+            let Ok(relative) = file.path().strip_prefix(&applications) else {
+                continue;
+            };
+            // Entries in subdirectories get the directories as a prefix, e.g. `kde-kate.desktop`.
+            let id = relative.to_string_lossy().replace('/', "-");
+            if entries.contains_key(&id) {
+                continue;
+            }
+
+            if let Some(entry) = parse_desktop_entry(id.clone(), file.path()) {
+                entries.insert(id, entry);
+            }
+        }
+    }
+
+    entries
+}
+
+fn parse_desktop_entry(id: String, path: &Path) -> Option<DesktopEntry> {
+    let contents = fs::read_to_string(path).ok()?;
+    let sections = parse_ini(&contents);
+    let values = sections.get("Desktop Entry")?;
+    let value = |key: &str| values.get(key).and_then(|values| values.first()).cloned();
+
+    let hidden = value("Hidden").is_some_and(|hidden| hidden == "true");
+    if hidden || value("Type").as_deref() != Some("Application") {
+        return None;
+    }
+
+    Some(DesktopEntry {
+        name: value("Name").unwrap_or_else(|| id.trim_end_matches(".desktop").to_string()),
+        id,
+        path: path.to_path_buf(),
+        icon: value("Icon"),
+        exec: value("Exec")?,
+        mime_types: values.get("MimeType").cloned().unwrap_or_default(),
+        terminal: value("Terminal").is_some_and(|terminal| terminal == "true"),
+    })
+}
+
+/// Groups of an ini style file with their keys. Values are split at `;`, and keys with
+/// a locale like `Name[de]` are left out.
+fn parse_ini(contents: &str) -> HashMap<String, HashMap<String, Vec<String>>> {
+    let mut sections: HashMap<String, HashMap<String, Vec<String>>> = HashMap::new();
+    let mut current = None;
+
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = Some(name.to_string());
+            continue;
+        }
+
+        let (Some(section), Some((key, value))) = (&current, line.split_once('=')) else {
+            continue;
+        };
+        let key = key.trim();
+        if key.contains('[') {
+            continue;
+        }
+
+        let value = value.trim();
+        let values = if key == "Exec" || key == "Name" || key == "Icon" {
+            vec![value.to_string()]
+        } else {
+            value
+                .split(';')
+                .filter(|part| !part.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+
+        sections
+            .entry(section.clone())
+            .or_default()
+            .entry(key.to_string())
+            .or_insert(values);
+    }
+
+    sections
+}
+
+/// Replaces or adds `key` in `section`, keeping everything else in the file as it was.
+fn set_ini_value(contents: &str, section: &str, key: &str, value: &str) -> String {
+    let header = format!("[{}]", section);
+    let entry = format!("{}={}", key, value);
+    let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+
+    let Some(start) = lines.iter().position(|line| line.trim() == header) else {
+        if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push(header);
+        lines.push(entry);
+        return lines.join("\n") + "\n";
+    };
+
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| line.trim_start().starts_with('['))
+        .map_or(lines.len(), |offset| start + 1 + offset);
+
+    let existing = lines[start + 1..end]
+        .iter()
+        .position(|line| line.split_once('=').is_some_and(|(k, _)| k.trim() == key));
+
+    match existing {
+        Some(offset) => lines[start + 1 + offset] = entry,
+        None => {
+            // After the last entry of the section, not after the blank lines that follow it.
+            let insert_at = lines[start + 1..end]
+                .iter()
+                .rposition(|line| !line.trim().is_empty())
+                .map_or(start + 1, |offset| start + 2 + offset);
+            lines.insert(insert_at, entry);
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// The command line of `entry` with its field codes filled in for `path`.
+fn exec_args(entry: &DesktopEntry, path: &str) -> Vec<String> {
+    let uri = file_uri(path);
+    let mut args = Vec::new();
+    let mut has_file = false;
+
+    for arg in split_exec(&entry.exec) {
+        if arg == "%i" {
+            if let Some(icon) = &entry.icon {
+                args.push("--icon".to_string());
+                args.push(icon.clone());
+            }
+            continue;
+        }
+
+        let mut expanded = String::new();
+        let mut chars = arg.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                expanded.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                Some('f' | 'F') => {
+                    expanded.push_str(path);
+                    has_file = true;
+                }
+                Some('u' | 'U') => {
+                    expanded.push_str(&uri);
+                    has_file = true;
+                }
+                Some('c') => expanded.push_str(&entry.name),
+                Some('k') => expanded.push_str(&entry.path.to_string_lossy()),
+                Some('%') => expanded.push('%'),
+                // Deprecated codes, they expand to nothing.
+                _ => {}
+            }
+        }
+
+        if !expanded.is_empty() || arg.is_empty() {
+            args.push(expanded);
+        }
+    }
+
+    if !has_file {
+        args.push(path.to_string());
+    }
+
+    args
+}
+
+/// Splits an `Exec` value into arguments, honouring double quotes and backslash escapes.
+fn split_exec(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quoted = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_arg = true;
+            }
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+                in_arg = true;
+            }
+            ' ' | '\t' if !quoted => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+
+    if in_arg {
+        args.push(current);
+    }
+
+    args
+}
+
+/// `$XDG_DATA_HOME` followed by `$XDG_DATA_DIRS`.
+fn data_dirs() -> Vec<PathBuf> {
+    let system = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    dirs::data_dir()
+        .into_iter()
+        .chain(env::split_paths(&system))
+        .collect()
+}
+
+/// `$XDG_CONFIG_HOME` followed by `$XDG_CONFIG_DIRS`.
+fn config_dirs() -> Vec<PathBuf> {
+    let system = env::var("XDG_CONFIG_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/etc/xdg".to_string());
+
+    dirs::config_dir()
+        .into_iter()
+        .chain(env::split_paths(&system))
+        .collect()
+}
+
+/// Every `mimeapps.list` in the order the spec gives them precedence, desktop specific
+/// ones before the generic one in each directory.
+fn mimeapps_lists() -> Vec<PathBuf> {
+    let desktops: Vec<String> = env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|desktop| !desktop.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    let directories = config_dirs()
+        .into_iter()
+        .chain(data_dirs().into_iter().map(|dir| dir.join("applications")));
+
+    directories
+        .flat_map(|dir| {
+            desktops
+                .iter()
+                .map(|desktop| dir.join(format!("{}-mimeapps.list", desktop)))
+                .chain(std::iter::once(dir.join("mimeapps.list")))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
@@ -0,0 +1,371 @@
+use lazy_static::lazy_static;
+use notify::event::CreateKind;
+use serde::Serialize;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use sysinfo::{DiskExt, System, SystemExt};
+use tauri::State;
+
+use super::cache::FsEventHandler;
+use crate::error::Error;
+use crate::StateSafe;
+
+lazy_static! {
+    pub static ref TEMPLATES_DIR_PATH: String = {
+        let mut templates_path = dirs::data_dir().expect("Failed to get base data path");
+        templates_path.push(format!("{}-templates", env!("CARGO_PKG_NAME")));
+        templates_path.to_string_lossy().to_string()
+    };
+
+    /// Mount points with the naming rules of their filesystem, and when they were listed.
+    static ref MOUNT_RULES: Mutex<Option<(Instant, MountRules)>> = Mutex::new(None);
+}
+
+type MountRules = Vec<(PathBuf, NamingRules)>;
+
+/// How long the list of mounted volumes is reused before it is read again.
+const MOUNT_RULES_TTL: Duration = Duration::from_secs(10);
+
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Characters that Windows filesystems refuse in a name, on top of control characters.
+const WINDOWS_RESERVED_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Device names that Windows filesystems refuse, with or without an extension.
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Templates that are always available, next to the ones in the user's template directory.
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("Empty file", ""),
+    ("Markdown note", "# Untitled\n\n"),
+    (
+        "README",
+        "# Project name\n\nA short description of the project.\n\n## Installation\n\n## Usage\n\n## License\n",
+    ),
+];
+
+#[derive(Serialize)]
+pub struct FileTemplate {
+    name: String,
+    builtin: bool,
+}
+
+/// The naming rules of the filesystem a path lives on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NamingRules {
+    /// NTFS, FAT and exFAT, which inherit the DOS restrictions on any platform.
+    Windows,
+    /// HFS+ and APFS, where Finder shows `:` as `/`.
+    Apple,
+    Unix,
+}
+
+impl NamingRules {
+    /// Looks up the filesystem of the volume holding `path`.
+    /// The mount table is cached briefly, bulk renames validate every name.
+    fn for_path(path: &Path) -> Self {
+        let mut cached = MOUNT_RULES.lock().unwrap();
+
+        if cached
+            .as_ref()
+            .is_none_or(|(listed, _)| listed.elapsed() > MOUNT_RULES_TTL)
+        {
+            *cached = Some((Instant::now(), Self::list_mounts()));
+        }
+
+        let mounts = cached.as_ref().map(|(_, mounts)| mounts.as_slice());
+
+        match mounts
+            .unwrap_or_default()
+            .iter()
+            .filter(|(mount_point, _)| path.starts_with(mount_point))
+            .max_by_key(|(mount_point, _)| mount_point.as_os_str().len())
+        {
+            Some((_, rules)) => *rules,
+            None if cfg!(windows) => NamingRules::Windows,
+            None => NamingRules::Unix,
+        }
+    }
+
+    fn list_mounts() -> MountRules {
+        let mut sys = System::new();
+        sys.refresh_disks_list();
+
+        sys.disks()
+            .iter()
+            .map(|disk| {
+                let file_system = String::from_utf8_lossy(disk.file_system()).to_lowercase();
+                let rules = match file_system.as_str() {
+                    "ntfs" | "fat" | "fat16" | "fat32" | "vfat" | "msdos" | "exfat" | "refs" => {
+                        NamingRules::Windows
+                    }
+                    "apfs" | "hfs" | "hfs+" => NamingRules::Apple,
+                    _ => NamingRules::Unix,
+                };
+                (disk.mount_point().to_path_buf(), rules)
+            })
+            .collect()
+    }
+}
+
+/// Checks that `name` is a single, valid entry name on the filesystem holding `parent`.
+pub fn validate_name(parent: &Path, name: &str) -> Result<(), Error> {
+    if name.is_empty() || name.trim().is_empty() {
+        return Err(Error::Custom("Name cannot be empty".to_string()));
+    }
+
+    if name == "." || name == ".." {
+        return Err(Error::Custom(format!("\"{}\" is not a valid name", name)));
+    }
+
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(Error::Custom(format!(
+            "Name is longer than {} bytes",
+            MAX_NAME_LENGTH
+        )));
+    }
+
+    if name.contains('/') || name.contains('\0') {
+        return Err(Error::Custom(
+            "Name cannot contain \"/\" or null characters".to_string(),
+        ));
+    }
+
+    match NamingRules::for_path(parent) {
+        NamingRules::Windows => {
+            if let Some(c) = name
+                .chars()
+                .find(|c| WINDOWS_RESERVED_CHARS.contains(c) || c.is_control())
+            {
+                return Err(Error::Custom(format!(
+                    "Name cannot contain \"{}\" on this volume",
+                    c.escape_default()
+                )));
+            }
+
+            if name.ends_with('.') || name.ends_with(' ') {
+                return Err(Error::Custom(
+                    "Name cannot end with a dot or a space on this volume".to_string(),
+                ));
+            }
+
+            let stem = name.split('.').next().unwrap_or_default().trim_end();
+            if WINDOWS_RESERVED_NAMES
+                .iter()
+                .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+            {
+                return Err(Error::Custom(format!(
+                    "\"{}\" is a reserved name on this volume",
+                    name
+                )));
+            }
+        }
+        NamingRules::Apple => {
+            if name.contains(':') {
+                return Err(Error::Custom(
+                    "Name cannot contain \":\" on this volume".to_string(),
+                ));
+            }
+        }
+        NamingRules::Unix => {}
+    }
+
+    Ok(())
+}
+
+/// Validates `name` and joins it onto `parent`, failing if something already exists there.
+fn new_entry_path(parent: &str, name: &str) -> Result<PathBuf, Error> {
+    let parent = Path::new(parent);
+    if !parent.is_dir() {
+        return Err(Error::Custom("Parent is not a directory".to_string()));
+    }
+
+    validate_name(parent, name)?;
+
+    let path = parent.join(name);
+    if fs::symlink_metadata(&path).is_ok() {
+        return Err(Error::Custom(format!(
+            "An item named \"{}\" already exists",
+            name
+        )));
+    }
+
+    Ok(path)
+}
+
+#[tauri::command]
+pub async fn create_directory(
+    state_mux: State<'_, StateSafe>,
+    parent: String,
+    name: String,
+    mount_point: String,
+) -> Result<String, Error> {
+    let path = new_entry_path(&parent, &name)?;
+
+    fs::create_dir(&path)?;
+
+    let fs_event_manager = FsEventHandler::new(state_mux.deref().clone(), mount_point.into());
+    let future_path = path.clone();
+
+    tokio::spawn(async move {
+        fs_event_manager.handle_create(CreateKind::Folder, &future_path);
+    });
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Creates a new file, optionally filled from a built-in or user template.
+#[tauri::command]
+pub async fn create_file(
+    state_mux: State<'_, StateSafe>,
+    parent: String,
+    name: String,
+    template: Option<String>,
+    mount_point: String,
+) -> Result<String, Error> {
+    let path = new_entry_path(&parent, &name)?;
+
+    match template {
+        Some(template) => write_template(&template, &path)?,
+        None => {
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)?;
+        }
+    }
+
+    let fs_event_manager = FsEventHandler::new(state_mux.deref().clone(), mount_point.into());
+    let future_path = path.clone();
+
+    tokio::spawn(async move {
+        fs_event_manager.handle_create(CreateKind::File, &future_path);
+    });
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Renames a file or directory in place and returns its new path.
+#[tauri::command]
+pub async fn rename_path(
+    state_mux: State<'_, StateSafe>,
+    path: String,
+    new_name: String,
+    mount_point: String,
+) -> Result<String, Error> {
+    let old_path = PathBuf::from(&path);
+    let parent = old_path
+        .parent()
+        .ok_or_else(|| Error::Custom("Cannot rename a volume root".to_string()))?;
+
+    validate_name(parent, &new_name)?;
+
+    let new_path = parent.join(&new_name);
+    if new_path == old_path {
+        return Ok(path);
+    }
+
+    // A case-only rename on a case-insensitive volume finds the item itself at the new path.
+    if fs::symlink_metadata(&new_path).is_ok() && !is_same_entry(&old_path, &new_path) {
+        return Err(Error::Custom(format!(
+            "An item named \"{}\" already exists",
+            new_name
+        )));
+    }
+
+    fs::rename(&old_path, &new_path)?;
+
+    let mut fs_event_manager = FsEventHandler::new(state_mux.deref().clone(), mount_point.into());
+    let future_path = new_path.clone();
+
+    tokio::spawn(async move {
+        fs_event_manager.handle_rename_from(&old_path);
+        fs_event_manager.handle_rename_to(&future_path);
+    });
+
+    Ok(new_path.to_string_lossy().to_string())
+}
+
+/// Lists the built-in templates followed by the files in the user's template directory.
+#[tauri::command]
+pub async fn get_file_templates() -> Result<Vec<FileTemplate>, Error> {
+    let mut templates: Vec<FileTemplate> = BUILTIN_TEMPLATES
+        .iter()
+        .map(|(name, _)| FileTemplate {
+            name: name.to_string(),
+            builtin: true,
+        })
+        .collect();
+
+    let templates_dir = Path::new(&*TEMPLATES_DIR_PATH);
+    if !templates_dir.is_dir() {
+        return Ok(templates);
+    }
+
+    let mut user_templates: Vec<FileTemplate> = fs::read_dir(templates_dir)?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| FileTemplate {
+            name: entry.file_name().to_string_lossy().to_string(),
+            builtin: false,
+        })
+        .collect();
+
+    user_templates.sort_by_key(|template| template.name.to_lowercase());
+    templates.extend(user_templates);
+
+    Ok(templates)
+}
+
+/// Writes the template called `name` to `path`. User templates take precedence over
+/// built-in ones with the same name.
+fn write_template(name: &str, path: &Path) -> Result<(), Error> {
+    let user_template = Path::new(&*TEMPLATES_DIR_PATH).join(name);
+
+    // The name comes from the frontend, make sure it can't point outside the directory.
+    let is_plain_name = Path::new(name)
+        .file_name()
+        .map(|n| n == name)
+        .unwrap_or(false);
+
+    if is_plain_name && user_template.is_file() {
+        fs::copy(&user_template, path)?;
+        return Ok(());
+    }
+
+    match BUILTIN_TEMPLATES
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+    {
+        Some((_, contents)) => {
+            fs::write(path, contents)?;
+            Ok(())
+        }
+        None => Err(Error::Custom(format!("Template \"{}\" not found", name))),
+    }
+}
+
+/// Whether both paths lead to the same file, e.g. when they only differ in case.
+#[cfg(unix)]
+pub fn is_same_entry(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (fs::symlink_metadata(a), fs::symlink_metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn is_same_entry(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
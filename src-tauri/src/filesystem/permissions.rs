@@ -0,0 +1,436 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::volume::{is_hidden, mode, owner_and_group};
+use crate::error::Error;
+
+#[derive(Debug, Serialize)]
+pub struct PathPermissions {
+    path: String,
+    is_dir: bool,
+    /// Mode bits including setuid, setgid and sticky, `None` on Windows.
+    mode: Option<u32>,
+    /// The mode the way `ls -l` shows it, e.g. `rwxr-xr-x`.
+    symbolic: Option<String>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    owner: Option<String>,
+    group: Option<String>,
+    readonly: bool,
+    hidden: bool,
+}
+
+/// What to change, everything left out stays as it is.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct PermissionChanges {
+    /// Mode for files and directories alike, `file_mode` and `directory_mode` take precedence.
+    mode: Option<u32>,
+    file_mode: Option<u32>,
+    directory_mode: Option<u32>,
+    /// User name or id.
+    owner: Option<String>,
+    /// Group name or id.
+    group: Option<String>,
+    /// Also applies the changes to everything inside directories.
+    recursive: bool,
+    /// Windows only.
+    readonly: Option<bool>,
+    /// Windows only.
+    hidden: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionErrorKind {
+    /// EPERM or EACCES, usually changing the owner without being root or changing
+    /// someone else's file.
+    PermissionDenied,
+    NotFound,
+    ReadOnlyFilesystem,
+    Other,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PermissionError {
+    kind: PermissionErrorKind,
+    /// The raw OS error code, like errno on Unix.
+    code: Option<i32>,
+    message: String,
+}
+
+impl From<io::Error> for PermissionError {
+    fn from(error: io::Error) -> Self {
+        let kind = match error.kind() {
+            io::ErrorKind::PermissionDenied => PermissionErrorKind::PermissionDenied,
+            io::ErrorKind::NotFound => PermissionErrorKind::NotFound,
+            io::ErrorKind::ReadOnlyFilesystem => PermissionErrorKind::ReadOnlyFilesystem,
+            _ => PermissionErrorKind::Other,
+        };
+
+        Self {
+            kind,
+            code: error.raw_os_error(),
+            message: error.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PermissionResult {
+    path: String,
+    error: Option<PermissionError>,
+}
+
+/// `PermissionChanges` with user and group names looked up and the modes worked out.
+#[derive(Debug)]
+struct ResolvedChanges {
+    #[cfg(unix)]
+    file_mode: Option<u32>,
+    #[cfg(unix)]
+    directory_mode: Option<u32>,
+    #[cfg(unix)]
+    uid: Option<u32>,
+    #[cfg(unix)]
+    gid: Option<u32>,
+    #[cfg(windows)]
+    readonly: Option<bool>,
+    #[cfg(windows)]
+    hidden: Option<bool>,
+}
+
+#[tauri::command]
+pub async fn get_permissions(path: String) -> Result<PathPermissions, Error> {
+    let metadata = fs::metadata(&path)?;
+    let name = Path::new(&path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let (uid, gid) = owner_ids(&metadata);
+    let (owner, group) = owner_and_group(&metadata);
+    let mode = mode(&metadata);
+
+    Ok(PathPermissions {
+        is_dir: metadata.is_dir(),
+        mode,
+        symbolic: mode.map(symbolic_mode),
+        uid,
+        gid,
+        owner,
+        group,
+        readonly: metadata.permissions().readonly(),
+        hidden: is_hidden(&name, &metadata),
+        path,
+    })
+}
+
+/// Applies `changes` to every path, and to everything inside them when recursive.
+/// Every path touched gets a result, failing ones don't stop the others.
+#[tauri::command]
+pub async fn set_permissions(
+    paths: Vec<String>,
+    changes: PermissionChanges,
+) -> Result<Vec<PermissionResult>, Error> {
+    let resolved = resolve_changes(&changes)?;
+    let mut results = Vec::new();
+
+    for path in paths {
+        let root = PathBuf::from(&path);
+
+        if !changes.recursive {
+            results.push(result_for(&root, apply_changes(&root, true, &resolved)));
+            continue;
+        }
+
+        apply_recursively(&root, true, &resolved, &mut results);
+    }
+
+    Ok(results)
+}
+
+/// Applies `changes` to `path` and everything inside it.
+/// A directory gets its own changes before its children when they leave it open to its
+/// owner, so a locked directory can be opened up, and after them otherwise, so taking
+/// access away doesn't lock us out halfway through.
+fn apply_recursively(
+    path: &Path,
+    follow: bool,
+    changes: &ResolvedChanges,
+    results: &mut Vec<PermissionResult>,
+) {
+    let is_dir = if follow {
+        path.is_dir()
+    } else {
+        fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir())
+    };
+
+    if !is_dir {
+        results.push(result_for(path, apply_changes(path, follow, changes)));
+        return;
+    }
+
+    let opens_first = changes.keeps_directories_open();
+    if opens_first {
+        results.push(result_for(path, apply_changes(path, follow, changes)));
+    }
+
+    match fs::read_dir(path) {
+        Ok(entries) => {
+            for entry in entries {
+                match entry {
+                    // Symlinks are only followed when they are what was selected.
+                    Ok(entry) => apply_recursively(&entry.path(), false, changes, results),
+                    Err(e) => results.push(result_for(path, Err(e))),
+                }
+            }
+        }
+        Err(e) => results.push(result_for(path, Err(e))),
+    }
+
+    if !opens_first {
+        results.push(result_for(path, apply_changes(path, follow, changes)));
+    }
+}
+
+fn result_for(path: &Path, result: io::Result<()>) -> PermissionResult {
+    PermissionResult {
+        path: path.to_string_lossy().to_string(),
+        error: result.err().map(PermissionError::from),
+    }
+}
+
+/// Formats mode bits like `ls -l` does, including setuid, setgid and sticky.
+fn symbolic_mode(mode: u32) -> String {
+    let mut symbolic = String::with_capacity(9);
+
+    for (shift, special, set, unset) in [(6, 0o4000, 's', 'S'), (3, 0o2000, 's', 'S')] {
+        let bits = mode >> shift;
+        symbolic.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        symbolic.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        symbolic.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => set,
+            (false, true) => unset,
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+
+    symbolic.push(if mode & 0o4 != 0 { 'r' } else { '-' });
+    symbolic.push(if mode & 0o2 != 0 { 'w' } else { '-' });
+    symbolic.push(match (mode & 0o1 != 0, mode & 0o1000 != 0) {
+        (true, true) => 't',
+        (false, true) => 'T',
+        (true, false) => 'x',
+        (false, false) => '-',
+    });
+
+    symbolic
+}
+
+#[cfg(unix)]
+fn resolve_changes(changes: &PermissionChanges) -> Result<ResolvedChanges, Error> {
+    if changes.readonly.is_some() || changes.hidden.is_some() {
+        return Err(Error::Custom(
+            "The read-only and hidden attributes can only be changed on Windows".to_string(),
+        ));
+    }
+
+    let modes = [changes.mode, changes.file_mode, changes.directory_mode];
+    if modes.iter().flatten().any(|mode| *mode > 0o7777) {
+        return Err(Error::Custom("Invalid mode".to_string()));
+    }
+
+    Ok(ResolvedChanges {
+        file_mode: changes.file_mode.or(changes.mode),
+        directory_mode: changes.directory_mode.or(changes.mode),
+        uid: changes.owner.as_deref().map(user_id).transpose()?,
+        gid: changes.group.as_deref().map(group_id).transpose()?,
+    })
+}
+
+#[cfg(windows)]
+fn resolve_changes(changes: &PermissionChanges) -> Result<ResolvedChanges, Error> {
+    let unix_only = [changes.mode, changes.file_mode, changes.directory_mode]
+        .iter()
+        .any(Option::is_some)
+        || changes.owner.is_some()
+        || changes.group.is_some();
+
+    if unix_only {
+        return Err(Error::Custom(
+            "Modes and ownership can't be changed on Windows".to_string(),
+        ));
+    }
+
+    Ok(ResolvedChanges {
+        readonly: changes.readonly,
+        hidden: changes.hidden,
+    })
+}
+
+#[cfg(unix)]
+impl ResolvedChanges {
+    /// Whether directories stay readable and searchable by their owner after the changes.
+    fn keeps_directories_open(&self) -> bool {
+        self.directory_mode.is_none_or(|mode| mode & 0o500 == 0o500)
+    }
+}
+
+#[cfg(windows)]
+impl ResolvedChanges {
+    /// The read-only and hidden attributes never keep a directory from being listed.
+    fn keeps_directories_open(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(unix)]
+fn apply_changes(path: &Path, follow: bool, changes: &ResolvedChanges) -> io::Result<()> {
+    use std::os::unix::fs::{chown, lchown, PermissionsExt};
+
+    let metadata = if follow {
+        fs::metadata(path)?
+    } else {
+        fs::symlink_metadata(path)?
+    };
+
+    // Ownership first, as changing it can clear the setuid and setgid bits.
+    if changes.uid.is_some() || changes.gid.is_some() {
+        if follow {
+            chown(path, changes.uid, changes.gid)?;
+        } else {
+            lchown(path, changes.uid, changes.gid)?;
+        }
+    }
+
+    // A symlink has no mode of its own, changing it would change its target.
+    if metadata.file_type().is_symlink() {
+        return Ok(());
+    }
+
+    let mode = if metadata.is_dir() {
+        changes.directory_mode
+    } else {
+        changes.file_mode
+    };
+    if let Some(mode) = mode {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn apply_changes(path: &Path, _follow: bool, changes: &ResolvedChanges) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::fs::MetadataExt;
+    use winapi::um::fileapi::SetFileAttributesW;
+
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    const FILE_ATTRIBUTE_NORMAL: u32 = 0x80;
+
+    if let Some(readonly) = changes.readonly {
+        let mut permissions = fs::metadata(path)?.permissions();
+        permissions.set_readonly(readonly);
+        fs::set_permissions(path, permissions)?;
+    }
+
+    if let Some(hidden) = changes.hidden {
+        let attributes = fs::metadata(path)?.file_attributes();
+        let attributes = if hidden {
+            attributes | FILE_ATTRIBUTE_HIDDEN
+        } else {
+            attributes & !FILE_ATTRIBUTE_HIDDEN
+        };
+        // No attributes at all has to be spelled out as "normal".
+        let attributes = if attributes == 0 {
+            FILE_ATTRIBUTE_NORMAL
+        } else {
+            attributes
+        };
+
+        let wide: Vec<u16> = path
+            .as_os_str()
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+        if unsafe { SetFileAttributesW(wide.as_ptr(), attributes) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn owner_ids(metadata: &fs::Metadata) -> (Option<u32>, Option<u32>) {
+    use std::os::unix::fs::MetadataExt;
+
+    (Some(metadata.uid()), Some(metadata.gid()))
+}
+
+#[cfg(windows)]
+fn owner_ids(_metadata: &fs::Metadata) -> (Option<u32>, Option<u32>) {
+    (None, None)
+}
+
+/// Looks up a user by name, numeric names are taken as ids.
+#[cfg(unix)]
+fn user_id(name: &str) -> Result<u32, Error> {
+    if let Ok(uid) = name.parse() {
+        return Ok(uid);
+    }
+
+    let c_name = std::ffi::CString::new(name)
+        .map_err(|_| Error::Custom(format!("Invalid user name {}", name)))?;
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+    let status = unsafe {
+        libc::getpwnam_r(
+            c_name.as_ptr(),
+            &mut passwd,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+
+    if status == 0 && !result.is_null() {
+        Ok(passwd.pw_uid)
+    } else {
+        Err(Error::Custom(format!("Unknown user {}", name)))
+    }
+}
+
+/// Looks up a group by name, numeric names are taken as ids.
+#[cfg(unix)]
+fn group_id(name: &str) -> Result<u32, Error> {
+    if let Ok(gid) = name.parse() {
+        return Ok(gid);
+    }
+
+    let c_name = std::ffi::CString::new(name)
+        .map_err(|_| Error::Custom(format!("Invalid group name {}", name)))?;
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut group: libc::group = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::group = std::ptr::null_mut();
+
+    let status = unsafe {
+        libc::getgrnam_r(
+            c_name.as_ptr(),
+            &mut group,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+
+    if status == 0 && !result.is_null() {
+        Ok(group.gr_gid)
+    } else {
+        Err(Error::Custom(format!("Unknown group {}", name)))
+    }
+}
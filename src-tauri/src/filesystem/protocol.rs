@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use tauri::http::{Request, Response, ResponseBuilder};
+use tauri::AppHandle;
+
+use super::audio::generate_waveform;
+use super::mime::detect_file_type;
+use super::thumbnails::{thumbnail_for, ThumbnailSize};
+use super::utils::percent_decode;
+use crate::error::Error;
+
+/// Scheme the frontend loads files and previews from, e.g.
+/// `bytes://localhost/%2Fhome%2Fme%2Fsong.mp3?kind=waveform`.
+pub const BYTES_SCHEME: &str = "bytes";
+
+/// Largest body sent for a range, players ask for the rest as they go.
+const MAX_RANGE: u64 = 8 * 1024 * 1024;
+
+/// Largest body sent for a request without a `Range` header. Anything bigger is answered
+/// with its first `MAX_RANGE` bytes as a partial response, so it is never read into
+/// memory whole and players go on with ranges.
+const MAX_FULL_RESPONSE: u64 = 64 * 1024 * 1024;
+
+/// Serves raw file bytes, thumbnails (`kind=thumbnail&size=large`) and audio waveforms
+/// (`kind=waveform`) of the percent encoded path in the URI, honouring `Range` headers.
+pub fn handle_bytes_request(
+    _app: &AppHandle,
+    request: &Request,
+) -> Result<Response, Box<dyn StdError>> {
+    let Some((path, query)) = parse_uri(request.uri()) else {
+        return error_response(400, "Malformed URI");
+    };
+
+    let result = match query.get("kind").map(String::as_str) {
+        None | Some("file") => {
+            let mime_type = detect_file_type(Path::new(&path)).mime_type;
+            serve_file(request, Path::new(&path), &mime_type)
+        }
+        Some("thumbnail") => {
+            let size = match query.get("size") {
+                Some(name) => match ThumbnailSize::from_directory(name) {
+                    Some(size) => size,
+                    None => return error_response(400, "Unknown thumbnail size"),
+                },
+                None => ThumbnailSize::default(),
+            };
+            match thumbnail_for(Path::new(&path), size) {
+                Ok(thumbnail) => serve_file(request, &thumbnail, "image/png"),
+                Err(Error::Io(e)) => Err(e),
+                Err(e) => return error_response(415, &e.to_string()),
+            }
+        }
+        Some("waveform") => match waveform_png(Path::new(&path)) {
+            Ok(png) => respond(request, png.len() as u64, "image/png", |start, len| {
+                Ok(png[start as usize..(start + len) as usize].to_vec())
+            }),
+            Err(e) => return error_response(415, &e),
+        },
+        Some(_) => return error_response(400, "Unknown kind"),
+    };
+
+    result.or_else(|e| {
+        let status = match e.kind() {
+            io::ErrorKind::NotFound => 404,
+            io::ErrorKind::PermissionDenied => 403,
+            _ => 500,
+        };
+        error_response(status, &e.to_string())
+    })
+}
+
+/// The decoded path and query of `bytes://localhost/<path>?<query>`, or of
+/// `https://bytes.localhost/<path>?<query>` which Windows uses for custom schemes.
+fn parse_uri(uri: &str) -> Option<(String, HashMap<String, String>)> {
+    let (_, rest) = uri.split_once("://")?;
+    let (_, path_and_query) = rest.split_once('/')?;
+    let (path, query) = path_and_query
+        .split_once('?')
+        .unwrap_or((path_and_query, ""));
+
+    let query = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .filter_map(|(key, value)| Some((percent_decode(key)?, percent_decode(value)?)))
+        .collect();
+
+    Some((percent_decode(path)?, query))
+}
+
+fn serve_file(request: &Request, path: &Path, mime_type: &str) -> io::Result<Response> {
+    let mut file = File::open(path)?;
+    let total = file.metadata()?.len();
+
+    respond(request, total, mime_type, |start, len| {
+        file.seek(SeekFrom::Start(start))?;
+        let mut buffer = Vec::with_capacity(len as usize);
+        (&mut file).take(len).read_to_end(&mut buffer)?;
+        Ok(buffer)
+    })
+}
+
+/// Answers with the whole body, or with the part asked for by a `Range` header.
+/// `read` gets the offset and length of the bytes to send.
+/// Bodies above `MAX_FULL_RESPONSE` are only sent in parts.
+fn respond<F>(request: &Request, total: u64, mime_type: &str, read: F) -> io::Result<Response>
+where
+    F: FnOnce(u64, u64) -> io::Result<Vec<u8>>,
+{
+    let builder = ResponseBuilder::new()
+        .header("Accept-Ranges", "bytes")
+        .mimetype(mime_type);
+
+    let range = request
+        .headers()
+        .get("range")
+        .and_then(|value| value.to_str().ok());
+
+    let range = match range {
+        Some(range) => Some(parse_range(range, total)),
+        None if total > MAX_FULL_RESPONSE => Some(Some((0, MAX_RANGE - 1))),
+        None => None,
+    };
+
+    let response = match range {
+        None => builder.status(200).body(read(0, total)?),
+        Some(Some((start, end))) => builder
+            .status(206)
+            .header(
+                "Content-Range",
+                format!("bytes {}-{}/{}", start, end, total),
+            )
+            .body(read(start, end - start + 1)?),
+        Some(None) => builder
+            .status(416)
+            .header("Content-Range", format!("bytes */{}", total))
+            .body(Vec::new()),
+    };
+
+    response.map_err(|e| io::Error::other(e.to_string()))
+}
+
+/// The first range of a `Range` header as inclusive offsets, `None` when it can't be
+/// satisfied. Ranges are cut at `MAX_RANGE` bytes, the `Content-Range` of the response
+/// tells the client where the part it got ends.
+fn parse_range(header: &str, total: u64) -> Option<(u64, u64)> {
+    let ranges = header.trim().strip_prefix("bytes=")?;
+    let (start, end) = ranges.split(',').next()?.trim().split_once('-')?;
+    let last = total.checked_sub(1)?;
+
+    let (start, end) = if start.is_empty() {
+        let suffix: u64 = end.parse().ok()?;
+        (total.checked_sub(suffix.min(total))?, last)
+    } else {
+        let start: u64 = start.parse().ok()?;
+        let end = match end.is_empty() {
+            true => last,
+            false => end.parse::<u64>().ok()?.min(last),
+        };
+        (start, end)
+    };
+
+    let end = end.min(start.saturating_add(MAX_RANGE - 1));
+    (start <= end && start < total).then_some((start, end))
+}
+
+fn waveform_png(path: &Path) -> Result<Vec<u8>, String> {
+    let waveform = generate_waveform(path)?;
+    let mut buffer = Cursor::new(Vec::new());
+    waveform
+        .write_to(&mut buffer, image::ImageOutputFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(buffer.into_inner())
+}
+
+fn error_response(status: u16, message: &str) -> Result<Response, Box<dyn StdError>> {
+    ResponseBuilder::new()
+        .status(status)
+        .mimetype("text/plain")
+        .body(message.as_bytes().to_vec())
+}
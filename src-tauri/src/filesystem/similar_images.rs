@@ -0,0 +1,365 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage};
+use lazy_static::lazy_static;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+use super::IMAGE_EXTENSIONS;
+use crate::error::Error;
+
+/// Similarity used when the frontend doesn't pass one, about 6 of 64 bits apart.
+const DEFAULT_THRESHOLD: f64 = 0.9;
+
+lazy_static! {
+    pub static ref IMAGE_HASHES_FILE_PATH: String = {
+        let mut hashes_path = dirs::cache_dir().expect("Failed to get base cache path");
+        hashes_path.push(format!("{}.image-hashes.bin", env!("CARGO_PKG_NAME")));
+        hashes_path.to_string_lossy().to_string()
+    };
+
+    /// Hashes of every image scanned so far, so only new and changed images are decoded again.
+    static ref IMAGE_HASHES: Mutex<HashMap<String, ImageHashes>> = Mutex::new(load_hashes());
+
+    /// Modification times of images that failed to decode, so they are only tried again
+    /// once they change.
+    static ref UNREADABLE_IMAGES: Mutex<HashMap<String, u64>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashMethod {
+    /// Compares the brightness of neighbouring pixels. Fast, but sensitive to edits.
+    DHash,
+    /// Compares the lowest frequencies of the image, which survive resizing and re-encoding.
+    #[default]
+    PHash,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+struct ImageHashes {
+    /// Modification time of the file the hashes were computed from, in seconds since the epoch.
+    modified: u64,
+    width: u32,
+    height: u32,
+    dhash: u64,
+    phash: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SimilarImage {
+    path: String,
+    width: u32,
+    height: u32,
+    size: u64,
+    /// How close this image is to the first one of its cluster, from 0 to 1.
+    /// Every image in a cluster is within the threshold of that first one.
+    similarity: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SimilarImageCluster {
+    /// Largest image first, which is usually the one worth keeping.
+    images: Vec<SimilarImage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimilarImagesReport {
+    clusters: Vec<SimilarImageCluster>,
+    scanned: usize,
+    /// Images that couldn't be decoded.
+    unreadable: Vec<String>,
+}
+
+struct ScannedImage {
+    path: String,
+    size: u64,
+    hashes: ImageHashes,
+}
+
+/// Finds images below `path` that look alike, even when they were resized or re-encoded.
+/// `threshold` is the similarity from 0 to 1 above which two images count as the same.
+#[tauri::command]
+pub async fn find_similar_images(
+    path: String,
+    method: Option<HashMethod>,
+    threshold: Option<f64>,
+) -> Result<SimilarImagesReport, Error> {
+    let root = PathBuf::from(&path);
+    if !root.is_dir() {
+        return Err(Error::Custom(format!("{} is not a directory", path)));
+    }
+
+    let method = method.unwrap_or_default();
+    let threshold = threshold.unwrap_or(DEFAULT_THRESHOLD).clamp(0.0, 1.0);
+    let max_distance = ((1.0 - threshold) * 64.0).floor() as u32;
+
+    // Decoding every image takes a while, keep it off the async command runtime.
+    tauri::async_runtime::spawn_blocking(move || find_similar(&root, method, max_distance))
+        .await
+        .map_err(|e| Error::Custom(e.to_string()))
+}
+
+fn find_similar(root: &Path, method: HashMethod, max_distance: u32) -> SimilarImagesReport {
+    let files: Vec<(String, u64, u64)> = WalkDir::new(root)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && is_decodable_image(entry.path()))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0);
+            let path = entry.path().to_string_lossy().to_string();
+            Some((path, metadata.len(), modified))
+        })
+        .collect();
+
+    let cached: HashMap<String, ImageHashes> = {
+        let hashes = IMAGE_HASHES.lock().unwrap();
+        files
+            .iter()
+            .filter_map(|(path, _, modified)| {
+                let cached = hashes.get(path)?;
+                (cached.modified == *modified).then(|| (path.clone(), *cached))
+            })
+            .collect()
+    };
+
+    let mut unreadable: Vec<String> = {
+        let failed = UNREADABLE_IMAGES.lock().unwrap();
+        files
+            .iter()
+            .filter(|(path, _, modified)| failed.get(path) == Some(modified))
+            .map(|(path, _, _)| path.clone())
+            .collect()
+    };
+    let known_unreadable: HashSet<&String> = unreadable.iter().collect();
+
+    let computed: Vec<(String, u64, Option<ImageHashes>)> = files
+        .par_iter()
+        .filter(|(path, _, _)| !cached.contains_key(path) && !known_unreadable.contains(path))
+        .map(|(path, _, modified)| {
+            let hashes = compute_hashes(Path::new(path), *modified);
+            (path.clone(), *modified, hashes)
+        })
+        .collect();
+
+    {
+        let mut hashes = IMAGE_HASHES.lock().unwrap();
+        // Forget images that were deleted from the scanned directory.
+        let present: HashSet<&String> = files.iter().map(|(path, _, _)| path).collect();
+        hashes.retain(|path, _| !Path::new(path).starts_with(root) || present.contains(path));
+
+        let mut failed = UNREADABLE_IMAGES.lock().unwrap();
+        failed.retain(|path, _| !Path::new(path).starts_with(root) || present.contains(path));
+
+        for (path, modified, computed) in &computed {
+            match computed {
+                Some(computed) => {
+                    hashes.insert(path.clone(), *computed);
+                    failed.remove(path);
+                }
+                None => {
+                    failed.insert(path.clone(), *modified);
+                }
+            }
+        }
+
+        save_hashes(&hashes);
+    }
+
+    unreadable.extend(
+        computed
+            .iter()
+            .filter(|(_, _, hashes)| hashes.is_none())
+            .map(|(path, _, _)| path.clone()),
+    );
+
+    let computed: HashMap<String, ImageHashes> = computed
+        .into_iter()
+        .filter_map(|(path, _, hashes)| Some((path, hashes?)))
+        .collect();
+
+    let images: Vec<ScannedImage> = files
+        .into_iter()
+        .filter_map(|(path, size, _)| {
+            let hashes = cached.get(&path).or_else(|| computed.get(&path)).copied()?;
+            Some(ScannedImage { path, size, hashes })
+        })
+        .collect();
+
+    SimilarImagesReport {
+        scanned: images.len(),
+        clusters: cluster(&images, method, max_distance),
+        unreadable,
+    }
+}
+
+/// SVGs are listed as images but can't be decoded by the `image` crate.
+fn is_decodable_image(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .is_some_and(|extension| {
+            extension != "svg" && IMAGE_EXTENSIONS.contains(&extension.as_str())
+        })
+}
+
+fn compute_hashes(path: &Path, modified: u64) -> Option<ImageHashes> {
+    let image = image::open(path).ok()?;
+    // Shrinking with the fast filter first keeps large photos from taking ages.
+    let small = image.thumbnail_exact(64, 64).grayscale();
+
+    Some(ImageHashes {
+        modified,
+        width: image.width(),
+        height: image.height(),
+        dhash: dhash(&small),
+        phash: phash(&small),
+    })
+}
+
+fn dhash(image: &DynamicImage) -> u64 {
+    let pixels = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            if pixels.get_pixel(x, y)[0] > pixels.get_pixel(x + 1, y)[0] {
+                hash |= 1 << (y * 8 + x);
+            }
+        }
+    }
+    hash
+}
+
+fn phash(image: &DynamicImage) -> u64 {
+    let pixels: GrayImage = image.resize_exact(32, 32, FilterType::Triangle).to_luma8();
+
+    // Only the lowest 8x8 frequencies of the DCT are needed.
+    let mut cosines = [[0.0; 32]; 8];
+    for (frequency, row) in cosines.iter_mut().enumerate() {
+        for (position, cosine) in row.iter_mut().enumerate() {
+            *cosine = ((2 * position + 1) as f64 * frequency as f64 * PI / 64.0).cos();
+        }
+    }
+
+    let mut coefficients = [0.0; 64];
+    for v in 0..8 {
+        for u in 0..8 {
+            let mut sum = 0.0;
+            for (x, y, pixel) in pixels.enumerate_pixels() {
+                sum += pixel[0] as f64 * cosines[u][x as usize] * cosines[v][y as usize];
+            }
+            coefficients[v * 8 + u] = sum;
+        }
+    }
+
+    // The first coefficient is the average brightness, which says nothing about the content.
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+
+    let mut hash = 0;
+    for (index, coefficient) in coefficients.iter().enumerate() {
+        if *coefficient > median {
+            hash |= 1 << index;
+        }
+    }
+    hash
+}
+
+/// Groups images around centres, each the largest image not in a group yet, together
+/// with every remaining image at most `max_distance` bits away from that centre.
+/// Unlike chaining near matches, this keeps dissimilar images out of the same group.
+fn cluster(
+    images: &[ScannedImage],
+    method: HashMethod,
+    max_distance: u32,
+) -> Vec<SimilarImageCluster> {
+    let hash = |image: &ScannedImage| match method {
+        HashMethod::DHash => image.hashes.dhash,
+        HashMethod::PHash => image.hashes.phash,
+    };
+
+    let mut images: Vec<&ScannedImage> = images.iter().collect();
+    images.sort_by_key(|image| {
+        let pixels = image.hashes.width as u64 * image.hashes.height as u64;
+        Reverse((pixels, image.size))
+    });
+
+    let mut grouped = vec![false; images.len()];
+    let mut clusters = Vec::new();
+
+    for centre in 0..images.len() {
+        if grouped[centre] {
+            continue;
+        }
+        grouped[centre] = true;
+
+        let centre_hash = hash(images[centre]);
+        let members: Vec<usize> = (centre + 1..images.len())
+            .into_par_iter()
+            .filter(|&i| {
+                !grouped[i] && (centre_hash ^ hash(images[i])).count_ones() <= max_distance
+            })
+            .collect();
+
+        if members.is_empty() {
+            continue;
+        }
+
+        let similar = std::iter::once(centre)
+            .chain(members)
+            .map(|i| {
+                grouped[i] = true;
+                let image = images[i];
+                SimilarImage {
+                    path: image.path.clone(),
+                    width: image.hashes.width,
+                    height: image.hashes.height,
+                    size: image.size,
+                    similarity: 1.0 - (centre_hash ^ hash(image)).count_ones() as f64 / 64.0,
+                }
+            })
+            .collect();
+
+        clusters.push(SimilarImageCluster { images: similar });
+    }
+
+    clusters.sort_by_key(|cluster| Reverse(cluster.images.len()));
+    clusters
+}
+
+fn load_hashes() -> HashMap<String, ImageHashes> {
+    let Ok(file) = File::open(&IMAGE_HASHES_FILE_PATH[..]) else {
+        return HashMap::new();
+    };
+
+    bincode::deserialize_from(BufReader::new(file)).unwrap_or_else(|e| {
+        eprintln!("Failed to read the image hash cache: {}", e);
+        HashMap::new()
+    })
+}
+
+fn save_hashes(hashes: &HashMap<String, ImageHashes>) {
+    let result = File::create(&IMAGE_HASHES_FILE_PATH[..])
+        .map_err(|e| e.to_string())
+        .and_then(|file| {
+            bincode::serialize_into(BufWriter::new(file), hashes).map_err(|e| e.to_string())
+        });
+
+    if let Err(e) = result {
+        eprintln!("Failed to save the image hash cache: {}", e);
+    }
+}
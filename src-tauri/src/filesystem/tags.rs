@@ -0,0 +1,435 @@
+use lazy_static::lazy_static;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::utils::{file_id, FileId};
+use crate::error::Error;
+
+/// The attribute other file managers read tags from, holding a comma separated list.
+#[cfg(unix)]
+const TAGS_XATTR: &str = "user.xdg.tags";
+
+lazy_static! {
+    /// Tags of every tagged path. Tags that can't be stored in an extended attribute,
+    /// on FAT drives or Windows for example, are only kept here.
+    pub static ref TAGS_DB_PATH: String = {
+        let mut tags_path = dirs::data_dir().expect("Failed to get base data path");
+        tags_path.push(format!("{}-tags.json", env!("CARGO_PKG_NAME")));
+        tags_path.to_string_lossy().to_string()
+    };
+
+    static ref TAGS: Mutex<TagStore> = Mutex::new(TagStore::load());
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct TaggedFile {
+    tags: BTreeSet<String>,
+    id: Option<FileId>,
+    /// Whether the tags are in the file's extended attribute too, or only in the database.
+    in_xattr: bool,
+}
+
+#[derive(Default)]
+struct TagStore {
+    files: HashMap<String, TaggedFile>,
+    /// Tagged paths by tag.
+    index: BTreeMap<String, BTreeSet<String>>,
+    /// First half of a rename, waiting for the event with the same tracker id.
+    renamed_from: Option<(Option<usize>, PathBuf)>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TaggedPath {
+    path: String,
+    tags: Vec<String>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TagSummary {
+    name: String,
+    count: usize,
+}
+
+impl TagStore {
+    fn load() -> Self {
+        let files: HashMap<String, TaggedFile> = fs::read(&TAGS_DB_PATH[..])
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default();
+
+        let mut store = TagStore::default();
+        for (path, file) in files {
+            store.insert(path, file);
+        }
+        store
+    }
+
+    fn save(&self) {
+        let result = serde_json::to_vec(&self.files)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| fs::write(&TAGS_DB_PATH[..], contents).map_err(|e| e.to_string()));
+
+        if let Err(e) = result {
+            eprintln!("Failed to save tags: {}", e);
+        }
+    }
+
+    fn insert(&mut self, path: String, file: TaggedFile) {
+        self.remove(&path);
+        if file.tags.is_empty() {
+            return;
+        }
+
+        for tag in &file.tags {
+            self.index
+                .entry(tag.clone())
+                .or_default()
+                .insert(path.clone());
+        }
+        self.files.insert(path, file);
+    }
+
+    fn remove(&mut self, path: &str) -> Option<TaggedFile> {
+        let file = self.files.remove(path)?;
+        for tag in &file.tags {
+            if let Some(paths) = self.index.get_mut(tag) {
+                paths.remove(path);
+                if paths.is_empty() {
+                    self.index.remove(tag);
+                }
+            }
+        }
+        Some(file)
+    }
+
+    /// Paths of `path` and everything tagged inside it.
+    fn paths_under(&self, path: &Path) -> Vec<String> {
+        self.files
+            .keys()
+            .filter(|tagged| Path::new(tagged).starts_with(path))
+            .cloned()
+            .collect()
+    }
+
+    /// Moves the tags of `from` and everything inside it over to `to`.
+    fn move_paths(&mut self, from: &Path, to: &Path) -> bool {
+        let moved = self.paths_under(from);
+
+        for old_path in &moved {
+            let Some(file) = self.remove(old_path) else {
+                continue;
+            };
+            let relative = Path::new(old_path)
+                .strip_prefix(from)
+                .unwrap_or(Path::new(""));
+            let new_path = to.join(relative).to_string_lossy().to_string();
+            self.insert(new_path, file);
+        }
+
+        !moved.is_empty()
+    }
+
+    fn remove_paths(&mut self, path: &Path) -> bool {
+        let removed = self.paths_under(path);
+        for tagged in &removed {
+            self.remove(tagged);
+        }
+        !removed.is_empty()
+    }
+
+    /// Picks up the tags of a file that arrived without its old path being reported,
+    /// by looking for a tagged file with the same identity that is gone from its path.
+    fn reattach(&mut self, path: &Path) -> bool {
+        let Some(id) = fs::symlink_metadata(path).ok().and_then(|m| file_id(&m)) else {
+            return false;
+        };
+
+        let old_path = self
+            .files
+            .iter()
+            .find(|(tagged, file)| file.id == Some(id) && !Path::new(tagged).exists())
+            .map(|(tagged, _)| PathBuf::from(tagged));
+
+        match old_path {
+            Some(old_path) => self.move_paths(&old_path, path),
+            None => false,
+        }
+    }
+}
+
+/// Tags of `path`. Tags added by other applications through the extended attribute
+/// are picked up into the index.
+#[tauri::command]
+pub async fn get_tags(path: String) -> Result<Vec<String>, Error> {
+    let mut store = TAGS.lock().unwrap();
+    let tags = current_tags(&mut store, Path::new(&path))?;
+    Ok(tags.into_iter().collect())
+}
+
+/// Adds and removes tags on every path. Each path gets a result with its new tags,
+/// failing ones don't stop the others.
+#[tauri::command]
+pub async fn update_tags(
+    paths: Vec<String>,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Vec<TaggedPath>, Error> {
+    let add = add
+        .iter()
+        .map(|tag| validate_tag(tag))
+        .collect::<Result<Vec<_>, _>>()?;
+    let remove: Vec<&str> = remove.iter().map(|tag| tag.trim()).collect();
+
+    let mut store = TAGS.lock().unwrap();
+    let mut results = Vec::with_capacity(paths.len());
+
+    for path in paths {
+        let result = current_tags(&mut store, Path::new(&path)).and_then(|mut tags| {
+            tags.extend(add.iter().map(|tag| tag.to_string()));
+            tags.retain(|tag| !remove.contains(&tag.as_str()));
+            write_tags(&mut store, Path::new(&path), tags)
+        });
+
+        results.push(match result {
+            Ok(tags) => TaggedPath {
+                path,
+                tags: tags.into_iter().collect(),
+                error: None,
+            },
+            Err(e) => TaggedPath {
+                path,
+                tags: Vec::new(),
+                error: Some(e.to_string()),
+            },
+        });
+    }
+
+    store.save();
+    Ok(results)
+}
+
+/// Every tag in use with the number of paths it is on.
+#[tauri::command]
+pub async fn list_tags() -> Result<Vec<TagSummary>, Error> {
+    let store = TAGS.lock().unwrap();
+
+    Ok(store
+        .index
+        .iter()
+        .map(|(name, paths)| TagSummary {
+            name: name.clone(),
+            count: paths.len(),
+        })
+        .collect())
+}
+
+/// Paths carrying `tag` that still exist.
+#[tauri::command]
+pub async fn get_tagged_paths(tag: String) -> Result<Vec<String>, Error> {
+    let store = TAGS.lock().unwrap();
+
+    Ok(store
+        .index
+        .get(tag.trim())
+        .map(|paths| {
+            paths
+                .iter()
+                .filter(|path| Path::new(path).exists())
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Takes `tag` off every path that has it.
+#[tauri::command]
+pub async fn delete_tag(tag: String) -> Result<Vec<TaggedPath>, Error> {
+    let paths: Vec<String> = {
+        let store = TAGS.lock().unwrap();
+        store
+            .index
+            .get(tag.trim())
+            .map(|paths| paths.iter().cloned().collect())
+            .unwrap_or_default()
+    };
+
+    update_tags(paths, Vec::new(), vec![tag]).await
+}
+
+/// Paths that have all of `tags`, for filtering search results.
+pub fn paths_with_tags(tags: &[String]) -> HashSet<String> {
+    let Ok(store) = TAGS.lock() else {
+        return HashSet::new();
+    };
+
+    let mut sets = tags.iter().map(|tag| store.index.get(tag.trim()));
+    let Some(Some(first)) = sets.next() else {
+        return HashSet::new();
+    };
+
+    let mut paths: HashSet<String> = first.iter().cloned().collect();
+    for set in sets {
+        match set {
+            Some(set) => paths.retain(|path| set.contains(path)),
+            None => return HashSet::new(),
+        }
+    }
+    paths
+}
+
+/// Keeps the index pointing at the right paths when tagged files are renamed or deleted.
+pub fn handle_fs_event(event: &Event) {
+    let paths = &event.paths;
+    if paths.is_empty() {
+        return;
+    }
+
+    let Ok(mut store) = TAGS.lock() else {
+        return;
+    };
+    if store.files.is_empty() {
+        return;
+    }
+
+    let changed = match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if paths.len() == 2 => {
+            store.move_paths(&paths[0], &paths[1])
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            store.renamed_from = Some((event.attrs.tracker(), paths[0].clone()));
+            false
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => match store.renamed_from.take() {
+            Some((tracker, from)) if tracker == event.attrs.tracker() => {
+                store.move_paths(&from, &paths[0])
+            }
+            _ => store.reattach(&paths[0]),
+        },
+        EventKind::Remove(_) => store.remove_paths(&paths[0]),
+        _ => false,
+    };
+
+    if changed {
+        store.save();
+    }
+}
+
+/// Reads the tags of `path` from its extended attribute, falling back to the database.
+fn current_tags(store: &mut TagStore, path: &Path) -> Result<BTreeSet<String>, Error> {
+    let metadata = fs::symlink_metadata(path)?;
+    let key = path.to_string_lossy().to_string();
+
+    match read_xattr(path) {
+        Ok(Some(tags)) => {
+            let known = store.files.get(&key).map(|file| &file.tags);
+            if known != Some(&tags) {
+                let file = TaggedFile {
+                    tags: tags.clone(),
+                    id: file_id(&metadata),
+                    in_xattr: true,
+                };
+                store.insert(key, file);
+                store.save();
+            }
+            Ok(tags)
+        }
+        // Tags that were in the attribute, but it got lost, like when an editor saves by
+        // replacing the file, are written back.
+        Ok(None) => match store.files.get(&key).cloned() {
+            Some(file) if file.in_xattr => {
+                let _ = write_xattr(path, &file.tags);
+                Ok(file.tags)
+            }
+            Some(file) => Ok(file.tags),
+            None => Ok(BTreeSet::new()),
+        },
+        Err(_) => Ok(store
+            .files
+            .get(&key)
+            .map(|file| file.tags.clone())
+            .unwrap_or_default()),
+    }
+}
+
+fn write_tags(
+    store: &mut TagStore,
+    path: &Path,
+    tags: BTreeSet<String>,
+) -> Result<BTreeSet<String>, Error> {
+    let metadata = fs::symlink_metadata(path)?;
+
+    let in_xattr = match write_xattr(path, &tags) {
+        Ok(()) => true,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(e.into()),
+        Err(_) => false,
+    };
+
+    let file = TaggedFile {
+        tags: tags.clone(),
+        id: file_id(&metadata),
+        in_xattr,
+    };
+    store.insert(path.to_string_lossy().to_string(), file);
+
+    Ok(tags)
+}
+
+fn validate_tag(tag: &str) -> Result<&str, Error> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err(Error::Custom("Tags can't be empty".to_string()));
+    }
+    if tag.contains(',') {
+        return Err(Error::Custom(format!(
+            "\"{}\" can't contain a comma, it separates tags",
+            tag
+        )));
+    }
+    Ok(tag)
+}
+
+#[cfg(unix)]
+fn read_xattr(path: &Path) -> io::Result<Option<BTreeSet<String>>> {
+    let Some(value) = xattr::get(path, TAGS_XATTR)? else {
+        return Ok(None);
+    };
+
+    Ok(Some(
+        String::from_utf8_lossy(&value)
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect(),
+    ))
+}
+
+#[cfg(windows)]
+fn read_xattr(_path: &Path) -> io::Result<Option<BTreeSet<String>>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(unix)]
+fn write_xattr(path: &Path, tags: &BTreeSet<String>) -> io::Result<()> {
+    if !tags.is_empty() {
+        let value = tags.iter().cloned().collect::<Vec<_>>().join(",");
+        return xattr::set(path, TAGS_XATTR, value.as_bytes());
+    }
+
+    if xattr::get(path, TAGS_XATTR)?.is_none() {
+        return Ok(());
+    }
+    xattr::remove(path, TAGS_XATTR)
+}
+
+#[cfg(windows)]
+fn write_xattr(_path: &Path, _tags: &BTreeSet<String>) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
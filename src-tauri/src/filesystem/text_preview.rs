@@ -0,0 +1,379 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use lazy_static::lazy_static;
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::error::Error;
+
+/// Bytes looked at to tell the encoding, line endings and whether a file is binary.
+const SAMPLE_SIZE: usize = 64 * 1024;
+
+/// Lines returned when the frontend doesn't ask for a range.
+const DEFAULT_LINES: usize = 200;
+
+/// Most bytes returned for a single page, however long its lines are.
+const MAX_PAGE_SIZE: u64 = 1024 * 1024;
+
+/// Every this many lines the byte offset is remembered, so later pages don't have to
+/// scan the file from its start.
+const CHECKPOINT_INTERVAL: usize = 1000;
+
+lazy_static! {
+    static ref LINE_INDEXES: Mutex<LruCache<String, LineIndex>> =
+        Mutex::new(LruCache::new(NonZeroUsize::new(16).unwrap()));
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(tag = "type")]
+pub enum TextRange {
+    /// Lines are counted from 0.
+    Lines {
+        start: usize,
+        count: usize,
+    },
+    Bytes {
+        start: u64,
+        length: u64,
+    },
+}
+
+impl Default for TextRange {
+    fn default() -> Self {
+        TextRange::Lines {
+            start: 0,
+            count: DEFAULT_LINES,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Cr,
+    Mixed,
+    /// A single line, or a binary file.
+    None,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TextPreview {
+    /// Name of the detected encoding, e.g. `UTF-8`, `UTF-16LE` or `Shift_JIS`.
+    encoding: String,
+    line_ending: LineEnding,
+    /// Binary files get no text.
+    pub binary: bool,
+    pub text: String,
+    /// First line of the page, unknown when paging by bytes.
+    start_line: Option<usize>,
+    /// Where the page starts and ends in the file, the end is where the next page starts.
+    start_byte: u64,
+    end_byte: u64,
+    total_bytes: u64,
+    /// Set when the page reaches the end of the file.
+    pub eof: bool,
+}
+
+/// What the start of a file says about the rest of it.
+struct Sniffed {
+    encoding: &'static Encoding,
+    /// Length of the byte order mark, where the text starts.
+    bom_length: u64,
+    binary: bool,
+    line_ending: LineEnding,
+}
+
+/// Byte offsets of every `CHECKPOINT_INTERVAL`th line of a file as it was when indexed.
+struct LineIndex {
+    modified: Option<SystemTime>,
+    size: u64,
+    checkpoints: Vec<u64>,
+}
+
+/// A page of `path` decoded to text, by lines or by bytes. Works for any file, binary
+/// ones are reported as such instead of being decoded.
+#[tauri::command]
+pub async fn get_text_preview(
+    path: String,
+    range: Option<TextRange>,
+) -> Result<TextPreview, Error> {
+    read_text_preview(Path::new(&path), range.unwrap_or_default())
+}
+
+pub fn read_text_preview(path: &Path, range: TextRange) -> Result<TextPreview, Error> {
+    let mut file = File::open(path)?;
+    let metadata = file.metadata()?;
+    let total_bytes = metadata.len();
+
+    let mut sample = Vec::with_capacity(SAMPLE_SIZE);
+    (&mut file)
+        .take(SAMPLE_SIZE as u64)
+        .read_to_end(&mut sample)?;
+    let sniffed = sniff(&sample, total_bytes <= SAMPLE_SIZE as u64);
+
+    let mut preview = TextPreview {
+        encoding: sniffed.encoding.name().to_string(),
+        line_ending: sniffed.line_ending,
+        binary: sniffed.binary,
+        text: String::new(),
+        start_line: None,
+        start_byte: 0,
+        end_byte: 0,
+        total_bytes,
+        eof: true,
+    };
+    if sniffed.binary {
+        return Ok(preview);
+    }
+
+    let mut reader = BufReader::new(file);
+    let bytes = match range {
+        TextRange::Lines { start, count } => {
+            let key = path.to_string_lossy().to_string();
+            let offset = seek_to_line(&mut reader, &key, &metadata, &sniffed, start)?;
+
+            let mut bytes = Vec::new();
+            let mut lines = 0;
+            while lines < count && (bytes.len() as u64) < MAX_PAGE_SIZE {
+                let limit = MAX_PAGE_SIZE - bytes.len() as u64;
+                if read_line(&mut reader, sniffed.encoding, Some(&mut bytes), limit)? == 0 {
+                    break;
+                }
+                lines += 1;
+            }
+
+            preview.start_line = Some(start);
+            preview.start_byte = offset;
+            bytes
+        }
+        TextRange::Bytes { start, length } => {
+            let start = align(start.max(sniffed.bom_length), &sniffed, &mut reader)?;
+            let mut bytes = Vec::new();
+            (&mut reader)
+                .take(length.min(MAX_PAGE_SIZE))
+                .read_to_end(&mut bytes)?;
+
+            preview.start_byte = start;
+            bytes
+        }
+    };
+
+    preview.end_byte = preview.start_byte + bytes.len() as u64;
+    preview.eof = preview.end_byte >= total_bytes;
+    preview.text = sniffed
+        .encoding
+        .decode_without_bom_handling(&bytes)
+        .0
+        .into_owned();
+
+    Ok(preview)
+}
+
+fn sniff(sample: &[u8], complete: bool) -> Sniffed {
+    let bom = Encoding::for_bom(sample);
+    let bom_length = bom.map_or(0, |(_, length)| length as u64);
+    let content = &sample[bom_length as usize..];
+
+    let encoding = match bom {
+        Some((encoding, _)) => encoding,
+        None if is_binary(content) => {
+            return Sniffed {
+                encoding: UTF_8,
+                bom_length: 0,
+                binary: true,
+                line_ending: LineEnding::None,
+            };
+        }
+        None if is_utf8(content, complete) => UTF_8,
+        None => {
+            let mut detector = EncodingDetector::new();
+            detector.feed(content, complete);
+            detector.guess(None, true)
+        }
+    };
+
+    let text = encoding.decode_without_bom_handling(content).0;
+    Sniffed {
+        encoding,
+        bom_length,
+        binary: false,
+        line_ending: line_ending(&text),
+    }
+}
+
+/// NUL bytes never show up in text without a UTF-16 byte order mark, and neither do
+/// many control characters.
+fn is_binary(content: &[u8]) -> bool {
+    if content.contains(&0) {
+        return true;
+    }
+
+    let control = content
+        .iter()
+        .filter(|&&byte| byte < 0x20 && !b"\t\n\r\x0c\x1b".contains(&byte))
+        .count();
+    control * 10 > content.len()
+}
+
+/// Valid UTF-8, apart from a character cut off at the end of an incomplete sample.
+fn is_utf8(content: &[u8], complete: bool) -> bool {
+    match std::str::from_utf8(content) {
+        Ok(_) => true,
+        Err(e) => !complete && e.error_len().is_none(),
+    }
+}
+
+fn line_ending(text: &str) -> LineEnding {
+    let crlf = text.matches("\r\n").count();
+    let cr = text.matches('\r').count() - crlf;
+    let lf = text.matches('\n').count() - crlf;
+
+    match (lf > 0, crlf > 0, cr > 0) {
+        (false, false, false) => LineEnding::None,
+        (true, false, false) => LineEnding::Lf,
+        (false, true, false) => LineEnding::CrLf,
+        (false, false, true) => LineEnding::Cr,
+        _ => LineEnding::Mixed,
+    }
+}
+
+/// Reads the next line including its line feed, but no more than `limit` bytes, and
+/// appends it to `line` when given. Returns the number of bytes read, 0 at the end.
+fn read_line<R: BufRead>(
+    reader: &mut R,
+    encoding: &'static Encoding,
+    mut line: Option<&mut Vec<u8>>,
+    limit: u64,
+) -> io::Result<u64> {
+    // Every other encoding we detect keeps ASCII as it is, even in multibyte characters.
+    let newline: &[u8] = if encoding == UTF_16LE {
+        b"\n\0"
+    } else if encoding == UTF_16BE {
+        b"\0\n"
+    } else {
+        b"\n"
+    };
+
+    let mut read = 0;
+    while read < limit {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+
+        let available = buffer
+            .len()
+            .min((limit - read).min(usize::MAX as u64) as usize);
+        let end = buffer[..available]
+            .chunks(newline.len())
+            .position(|unit| unit == newline)
+            .map(|index| (index + 1) * newline.len());
+        let length = end.unwrap_or(available);
+
+        if let Some(line) = line.as_deref_mut() {
+            line.extend_from_slice(&buffer[..length]);
+        }
+        reader.consume(length);
+        read += length as u64;
+
+        if end.is_some() {
+            break;
+        }
+    }
+
+    Ok(read)
+}
+
+/// Positions `reader` at the start of line `target` and returns its byte offset, using
+/// and extending the checkpoints of the file.
+fn seek_to_line<R: BufRead + Seek>(
+    reader: &mut R,
+    key: &str,
+    metadata: &std::fs::Metadata,
+    sniffed: &Sniffed,
+    target: usize,
+) -> io::Result<u64> {
+    let modified = metadata.modified().ok();
+    let size = metadata.len();
+    let is_current = |index: &&mut LineIndex| index.modified == modified && index.size == size;
+
+    // The lock is only held to look up and store checkpoints, not while reading the file.
+    let (checkpoint, mut offset) = {
+        let mut indexes = LINE_INDEXES.lock().unwrap();
+        match indexes.get_mut(key).filter(is_current) {
+            Some(index) => {
+                let checkpoint = (target / CHECKPOINT_INTERVAL).min(index.checkpoints.len() - 1);
+                (checkpoint, index.checkpoints[checkpoint])
+            }
+            None => {
+                let index = LineIndex {
+                    modified,
+                    size,
+                    checkpoints: vec![sniffed.bom_length],
+                };
+                indexes.put(key.to_string(), index);
+                (0, sniffed.bom_length)
+            }
+        }
+    };
+
+    let mut line = checkpoint * CHECKPOINT_INTERVAL;
+    let mut found = Vec::new();
+    reader.seek(SeekFrom::Start(offset))?;
+
+    while line < target {
+        let read = read_line(reader, sniffed.encoding, None, u64::MAX)?;
+        if read == 0 {
+            break;
+        }
+        offset += read;
+        line += 1;
+
+        if line.is_multiple_of(CHECKPOINT_INTERVAL) {
+            found.push(offset);
+        }
+    }
+
+    if !found.is_empty() {
+        let mut indexes = LINE_INDEXES.lock().unwrap();
+        if let Some(index) = indexes.get_mut(key).filter(is_current) {
+            // `found` starts right after `checkpoint`, another read may have added some of it.
+            if let Some(known) = index.checkpoints.len().checked_sub(checkpoint + 1) {
+                index.checkpoints.extend(found.into_iter().skip(known));
+            }
+        }
+    }
+
+    Ok(offset)
+}
+
+/// Positions `reader` at `start`, moved forward to the start of a character so the page
+/// doesn't begin with half of one. Returns the new offset.
+fn align<R: Read + Seek>(start: u64, sniffed: &Sniffed, reader: &mut R) -> io::Result<u64> {
+    if sniffed.encoding == UTF_16LE || sniffed.encoding == UTF_16BE {
+        let start = start + (start - sniffed.bom_length) % 2;
+        reader.seek(SeekFrom::Start(start))?;
+        return Ok(start);
+    }
+
+    if sniffed.encoding != UTF_8 {
+        reader.seek(SeekFrom::Start(start))?;
+        return Ok(start);
+    }
+
+    // Continuation bytes of UTF-8 all look like 0b10xxxxxx.
+    reader.seek(SeekFrom::Start(start))?;
+    let mut head = Vec::with_capacity(4);
+    (&mut *reader).take(4).read_to_end(&mut head)?;
+    let skip = head.iter().take_while(|&&byte| byte & 0xc0 == 0x80).count() as u64;
+
+    reader.seek(SeekFrom::Start(start + skip))?;
+    Ok(start + skip)
+}
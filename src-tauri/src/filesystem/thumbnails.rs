@@ -0,0 +1,331 @@
+use image::imageops::FilterType;
+use image::io::Reader as ImageReader;
+use lazy_static::lazy_static;
+use md5::{Digest, Md5};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+use super::mime::{detect_file_type, FileCategory};
+use super::utils::file_uri;
+use crate::error::Error;
+
+/// Space the thumbnail cache may take before the least recently used thumbnails go.
+const DEFAULT_CACHE_LIMIT: u64 = 512 * 1024 * 1024;
+
+/// Limit set by the frontend, in bytes.
+static CACHE_LIMIT: AtomicU64 = AtomicU64::new(DEFAULT_CACHE_LIMIT);
+
+/// Bumped for every folder opened, so pre-generation for the previous one stops.
+static PREGENERATION: AtomicU64 = AtomicU64::new(0);
+
+lazy_static! {
+    /// Our own cache, laid out like the freedesktop one in `~/.cache/thumbnails`.
+    pub static ref THUMBNAILS_PATH: PathBuf = {
+        let mut thumbnails_path = dirs::cache_dir().expect("Failed to get base cache path");
+        thumbnails_path.push(env!("CARGO_PKG_NAME"));
+        thumbnails_path.push("thumbnails");
+        thumbnails_path
+    };
+
+    /// Bytes taken by our cache, measured the first time a thumbnail is written.
+    static ref CACHE_SIZE: Mutex<Option<u64>> = Mutex::new(None);
+}
+
+/// The sizes of the freedesktop thumbnail spec.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThumbnailSize {
+    #[default]
+    Normal,
+    Large,
+    XLarge,
+    XXLarge,
+}
+
+impl ThumbnailSize {
+    const ALL: [ThumbnailSize; 4] = [
+        ThumbnailSize::Normal,
+        ThumbnailSize::Large,
+        ThumbnailSize::XLarge,
+        ThumbnailSize::XXLarge,
+    ];
+
+    fn pixels(self) -> u32 {
+        match self {
+            ThumbnailSize::Normal => 128,
+            ThumbnailSize::Large => 256,
+            ThumbnailSize::XLarge => 512,
+            ThumbnailSize::XXLarge => 1024,
+        }
+    }
+
+    /// Looks a size up by its directory name, e.g. `x-large`.
+    pub fn from_directory(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|size| size.directory() == name)
+    }
+
+    fn directory(self) -> &'static str {
+        match self {
+            ThumbnailSize::Normal => "normal",
+            ThumbnailSize::Large => "large",
+            ThumbnailSize::XLarge => "x-large",
+            ThumbnailSize::XXLarge => "xx-large",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct ThumbnailEvent {
+    path: String,
+    thumbnail: String,
+}
+
+/// Path of a PNG thumbnail of the image at `path`, generated if there isn't an
+/// up to date one yet.
+#[tauri::command]
+pub async fn get_thumbnail(path: String, size: Option<ThumbnailSize>) -> Result<String, Error> {
+    let thumbnail = thumbnail_for(Path::new(&path), size.unwrap_or_default())?;
+    Ok(thumbnail.to_string_lossy().to_string())
+}
+
+/// Generates the missing thumbnails of the images in `path` on a background thread,
+/// emitting a `thumbnail_ready` event for each. Stops when another folder is opened.
+#[tauri::command]
+pub async fn pregenerate_thumbnails(
+    path: String,
+    size: Option<ThumbnailSize>,
+    window: tauri::Window,
+) -> Result<(), Error> {
+    let generation = PREGENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let size = size.unwrap_or_default();
+    let entries = fs::read_dir(&path)?;
+
+    thread::spawn(move || {
+        let images: Vec<PathBuf> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .filter(|path| detect_file_type(path).category == FileCategory::Image)
+            .collect();
+
+        images.into_par_iter().for_each(|image| {
+            if PREGENERATION.load(Ordering::SeqCst) != generation {
+                return;
+            }
+
+            match thumbnail_for(&image, size) {
+                Ok(thumbnail) => {
+                    let event = ThumbnailEvent {
+                        path: image.to_string_lossy().to_string(),
+                        thumbnail: thumbnail.to_string_lossy().to_string(),
+                    };
+                    if let Err(e) = window.emit("thumbnail_ready", event) {
+                        eprintln!("Failed to emit thumbnail: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("Failed to thumbnail {}: {}", image.display(), e),
+            }
+        });
+    });
+
+    Ok(())
+}
+
+/// Sets how many bytes the cache may take, evicting thumbnails right away if it's over.
+#[tauri::command]
+pub async fn set_thumbnail_cache_limit(limit: u64) -> Result<(), Error> {
+    CACHE_LIMIT.store(limit, Ordering::SeqCst);
+    evict_if_needed(0);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn clear_thumbnail_cache() -> Result<(), Error> {
+    let mut cache_size = CACHE_SIZE.lock().unwrap();
+    match fs::remove_dir_all(&*THUMBNAILS_PATH) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    *cache_size = Some(0);
+    Ok(())
+}
+
+/// Finds or generates the thumbnail of `path`. Thumbnails are named after the MD5 of
+/// the file's URI and carry its modification time and size, like the freedesktop spec
+/// asks, so a changed file gets a new thumbnail.
+pub fn thumbnail_for(path: &Path, size: ThumbnailSize) -> Result<PathBuf, Error> {
+    let path = path.canonicalize()?;
+    let metadata = fs::metadata(&path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    let uri = file_uri(&path.to_string_lossy());
+    let name = format!("{:x}.png", Md5::digest(uri.as_bytes()));
+
+    // Thumbnails other applications made are used as they are, but never written to.
+    if let Some(shared) = dirs::cache_dir() {
+        let shared = shared.join("thumbnails").join(size.directory()).join(&name);
+        if is_up_to_date(&shared, modified, metadata.len()) {
+            return Ok(shared);
+        }
+    }
+
+    let thumbnail = THUMBNAILS_PATH.join(size.directory()).join(&name);
+    if is_up_to_date(&thumbnail, modified, metadata.len()) {
+        // The modification time of a thumbnail is when it was last used, for eviction.
+        let _ = filetime::set_file_mtime(&thumbnail, filetime::FileTime::now());
+        return Ok(thumbnail);
+    }
+
+    let written = write_thumbnail(&path, &thumbnail, size, &uri, modified, metadata.len())?;
+    evict_if_needed(written);
+
+    Ok(thumbnail)
+}
+
+/// Whether the thumbnail exists and was made from the file as it is now.
+fn is_up_to_date(thumbnail: &Path, modified: u64, size: u64) -> bool {
+    let Ok(file) = File::open(thumbnail) else {
+        return false;
+    };
+    let Ok(reader) = png::Decoder::new(file).read_info() else {
+        return false;
+    };
+
+    let text = |keyword: &str| {
+        reader
+            .info()
+            .uncompressed_latin1_text
+            .iter()
+            .find(|chunk| chunk.keyword == keyword)
+            .map(|chunk| chunk.text.clone())
+    };
+
+    // The size is optional in the spec, the modification time isn't.
+    text("Thumb::MTime") == Some(modified.to_string())
+        && text("Thumb::Size").is_none_or(|text| text == size.to_string())
+}
+
+/// Returns the size of the written thumbnail.
+fn write_thumbnail(
+    path: &Path,
+    thumbnail: &Path,
+    size: ThumbnailSize,
+    uri: &str,
+    modified: u64,
+    file_size: u64,
+) -> Result<u64, Error> {
+    let image = ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()
+        .map_err(|e| Error::Custom(e.to_string()))?;
+
+    // Small images aren't scaled up, the spec leaves that to whoever displays them.
+    let pixels = size.pixels();
+    let image = if image.width() > pixels || image.height() > pixels {
+        image.resize(pixels, pixels, FilterType::Triangle)
+    } else {
+        image
+    };
+    let image = image.to_rgba8();
+
+    let directory = thumbnail.parent().unwrap_or(&THUMBNAILS_PATH);
+    fs::create_dir_all(directory)?;
+
+    // Written next to the thumbnail and renamed over it, so nobody reads half a PNG.
+    let temporary = directory.join(format!(".{:016x}.png", rand::random::<u64>()));
+    let result = encode_png(&temporary, &image, uri, modified, file_size)
+        .and_then(|_| fs::rename(&temporary, thumbnail).map_err(Error::from));
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    result?;
+
+    Ok(fs::metadata(thumbnail)?.len())
+}
+
+fn encode_png(
+    destination: &Path,
+    image: &image::RgbaImage,
+    uri: &str,
+    modified: u64,
+    file_size: u64,
+) -> Result<(), Error> {
+    let png_error = |e: png::EncodingError| Error::Custom(e.to_string());
+
+    let writer = BufWriter::new(File::create(destination)?);
+    let mut encoder = png::Encoder::new(writer, image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .add_text_chunk("Thumb::URI".to_string(), uri.to_string())
+        .map_err(png_error)?;
+    encoder
+        .add_text_chunk("Thumb::MTime".to_string(), modified.to_string())
+        .map_err(png_error)?;
+    encoder
+        .add_text_chunk("Thumb::Size".to_string(), file_size.to_string())
+        .map_err(png_error)?;
+
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    writer.write_image_data(image).map_err(png_error)?;
+    writer.finish().map_err(png_error)
+}
+
+/// Adds `written` bytes to the cache size and drops the least recently used thumbnails
+/// until the cache is back to 90% of its limit when it went over.
+fn evict_if_needed(written: u64) {
+    let mut cache_size = CACHE_SIZE.lock().unwrap();
+    let total = match *cache_size {
+        Some(total) => total + written,
+        None => cached_thumbnails().iter().map(|(_, size, _)| size).sum(),
+    };
+
+    let limit = CACHE_LIMIT.load(Ordering::SeqCst);
+    if total <= limit {
+        *cache_size = Some(total);
+        return;
+    }
+
+    let mut thumbnails = cached_thumbnails();
+    thumbnails.sort_by_key(|(used, _, _)| *used);
+
+    let mut total: u64 = thumbnails.iter().map(|(_, size, _)| size).sum();
+    let target = limit / 10 * 9;
+    for (_, size, path) in thumbnails {
+        if total <= target {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total -= size;
+        }
+    }
+
+    *cache_size = Some(total);
+}
+
+/// Every thumbnail in our cache with when it was last used and its size.
+fn cached_thumbnails() -> Vec<(SystemTime, u64, PathBuf)> {
+    ThumbnailSize::ALL
+        .iter()
+        .flat_map(|size| WalkDir::new(THUMBNAILS_PATH.join(size.directory())).max_depth(1))
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let used = metadata.modified().unwrap_or(UNIX_EPOCH);
+            Some((used, metadata.len(), entry.into_path()))
+        })
+        .collect()
+}
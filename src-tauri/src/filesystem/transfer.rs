@@ -0,0 +1,1110 @@
+use filetime::FileTime;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::ffi::OsString;
+use std::fs::{self, File, Metadata, Permissions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tokio::sync::oneshot;
+use walkdir::WalkDir;
+
+use crate::error::Error;
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+const PARTIAL_SUFFIX: &str = ".bytes-partial";
+
+lazy_static! {
+    /// Conflicts waiting on an answer from the frontend, keyed by conflict id.
+    static ref PENDING_CONFLICTS: Mutex<HashMap<u64, PendingConflict>> =
+        Mutex::new(HashMap::new());
+
+    /// Cancellation flags of the transfers currently running, keyed by transfer id.
+    static ref ACTIVE_TRANSFERS: Mutex<HashMap<u64, Arc<AtomicBool>>> =
+        Mutex::new(HashMap::new());
+}
+
+static NEXT_CONFLICT_ID: AtomicU64 = AtomicU64::new(1);
+
+static NEXT_TRANSFER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// How a copy or move behaves when something already exists at the destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ConflictPolicy {
+    Skip,
+    Overwrite,
+    #[default]
+    KeepBoth,
+    OverwriteIfNewer,
+    Ask,
+}
+
+/// The decision taken for a single conflict, either by the policy or by the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictResolution {
+    Skip,
+    Overwrite,
+    KeepBoth,
+    Cancel,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ConflictAnswer {
+    resolution: ConflictResolution,
+    apply_to_all: bool,
+}
+
+/// A blocked transfer's way to receive the answer, with who it was asked for.
+struct PendingConflict {
+    transfer_id: u64,
+    window: String,
+    sender: oneshot::Sender<ConflictAnswer>,
+}
+
+/// Emitted as `transfer_conflict` when the policy is `Ask`.
+/// The frontend replies with `resolve_transfer_conflict` using the same id.
+#[derive(Debug, Serialize, Clone)]
+pub struct TransferConflict {
+    id: u64,
+    transfer_id: u64,
+    source: String,
+    destination: String,
+    is_dir: bool,
+    source_size: u64,
+    destination_size: u64,
+    source_modified: u64,
+    destination_modified: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TransferMode {
+    Copy,
+    Move,
+}
+
+/// Settings shared by every item of a transfer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TransferOptions {
+    pub conflict_policy: ConflictPolicy,
+    /// Copy what symbolic links point to instead of recreating the links. Moves always keep links.
+    pub dereference_symlinks: bool,
+}
+
+/// Emitted as `transfer_progress` while a transfer is running.
+#[derive(Debug, Serialize, Clone)]
+pub struct TransferProgress {
+    id: u64,
+    bytes_done: u64,
+    bytes_total: u64,
+    files_done: u64,
+    files_total: u64,
+    current_file: Option<String>,
+    bytes_per_second: u64,
+    eta_seconds: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TransferFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// Returned by transfer commands and emitted as `transfer_finished`.
+#[derive(Debug, Serialize, Clone)]
+pub struct TransferReport {
+    pub id: u64,
+    pub transferred: Vec<String>,
+    pub failures: Vec<TransferFailure>,
+    pub cancelled: bool,
+}
+
+/// Where a source ends up once its conflict, if any, has been resolved.
+enum Target {
+    /// Nothing exists at the path, write to it directly.
+    Fresh(PathBuf),
+    /// Something exists at the path and should be overwritten (files) or merged into (directories).
+    Replace(PathBuf),
+    Skip,
+}
+
+impl ConflictPolicy {
+    /// Decides a conflict without asking the user. Returns `None` for `Ask`.
+    fn decide(self, source: &Path, destination: &Path) -> Option<ConflictResolution> {
+        match self {
+            ConflictPolicy::Skip => Some(ConflictResolution::Skip),
+            ConflictPolicy::Overwrite => Some(ConflictResolution::Overwrite),
+            ConflictPolicy::KeepBoth => Some(ConflictResolution::KeepBoth),
+            ConflictPolicy::OverwriteIfNewer => {
+                if is_newer(source, destination) {
+                    Some(ConflictResolution::Overwrite)
+                } else {
+                    Some(ConflictResolution::Skip)
+                }
+            }
+            ConflictPolicy::Ask => None,
+        }
+    }
+
+    /// The policy used for the children of a directory that is being merged into an existing one.
+    fn for_children(self) -> ConflictPolicy {
+        match self {
+            ConflictPolicy::OverwriteIfNewer => ConflictPolicy::OverwriteIfNewer,
+            _ => ConflictPolicy::Overwrite,
+        }
+    }
+}
+
+/// A running copy or move of one or more items.
+/// Tracks progress, conflict answers and everything it created so that a cancelled
+/// copy can be rolled back. A single transfer is shared across a batch so that an
+/// "apply to all" answer covers every remaining item.
+pub struct Transfer {
+    id: u64,
+    mode: TransferMode,
+    policy: ConflictPolicy,
+    dereference_symlinks: bool,
+    remembered: Option<ConflictResolution>,
+    window: tauri::Window,
+    cancelled: Arc<AtomicBool>,
+    bytes_done: u64,
+    bytes_total: u64,
+    files_done: u64,
+    files_total: u64,
+    current_file: Option<String>,
+    started: Instant,
+    last_emit: Option<Instant>,
+    created: Vec<PathBuf>,
+    transferred: Vec<String>,
+    failures: Vec<TransferFailure>,
+}
+
+impl Transfer {
+    /// Registers a new transfer. Its sources are measured separately by `measure`, which
+    /// walks them and so belongs on a blocking thread.
+    pub fn start(mode: TransferMode, options: TransferOptions, window: tauri::Window) -> Self {
+        let id = NEXT_TRANSFER_ID.fetch_add(1, Ordering::Relaxed);
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        ACTIVE_TRANSFERS
+            .lock()
+            .unwrap()
+            .insert(id, Arc::clone(&cancelled));
+
+        let dereference_symlinks = options.dereference_symlinks && mode == TransferMode::Copy;
+
+        Self {
+            id,
+            mode,
+            policy: options.conflict_policy,
+            dereference_symlinks,
+            remembered: None,
+            window,
+            cancelled,
+            bytes_done: 0,
+            bytes_total: 0,
+            files_done: 0,
+            files_total: 0,
+            current_file: None,
+            started: Instant::now(),
+            last_emit: None,
+            created: Vec::new(),
+            transferred: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+
+    /// Adds up the size of `sources` so progress can be reported.
+    pub fn measure(&mut self, sources: &[&Path]) {
+        let (bytes_total, files_total) = sources
+            .iter()
+            .map(|source| tree_size(source, self.dereference_symlinks))
+            .fold((0, 0), |(bytes, files), (b, f)| (bytes + b, files + f));
+
+        self.bytes_total = bytes_total;
+        self.files_total = files_total;
+        self.emit_progress(true);
+    }
+
+    /// Copies or moves `source` to `destination`, recording the outcome in the report.
+    /// Failures are recorded rather than returned, only a cancellation stops the caller.
+    /// Blocks while the data is written and while a conflict waits on an answer.
+    pub fn run(&mut self, source: &Path, destination: &Path) {
+        if self.is_cancelled() {
+            return;
+        }
+
+        let result = match self.mode {
+            TransferMode::Copy => self.copy_path(source, destination),
+            TransferMode::Move => self.move_path(source, destination),
+        };
+
+        match result {
+            Ok(Some(final_path)) => self
+                .transferred
+                .push(final_path.to_string_lossy().to_string()),
+            Ok(None) => {}
+            Err(_) if self.is_cancelled() => {}
+            Err(e) => self.fail(source, e),
+        }
+    }
+
+    /// Unregisters the transfer, rolls back a cancelled copy and emits the final report.
+    pub fn finish(mut self) -> TransferReport {
+        ACTIVE_TRANSFERS.lock().unwrap().remove(&self.id);
+
+        let cancelled = self.is_cancelled();
+        if cancelled {
+            // Newest first so files go before the directories that hold them.
+            for path in self.created.iter().rev() {
+                remove_path(path);
+            }
+        }
+
+        self.current_file = None;
+        self.emit_progress(true);
+
+        let report = TransferReport {
+            id: self.id,
+            transferred: self.transferred,
+            failures: self.failures,
+            cancelled,
+        };
+
+        if let Err(e) = self.window.emit("transfer_finished", report.clone()) {
+            eprintln!("Failed to emit transfer_finished: {}", e);
+        }
+
+        report
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn check_cancelled(&self) -> Result<(), Error> {
+        if self.is_cancelled() {
+            return Err(Error::Custom("Transfer cancelled".to_string()));
+        }
+        Ok(())
+    }
+
+    fn fail(&mut self, path: &Path, error: Error) {
+        eprintln!("Failed to transfer {}: {}", path.to_string_lossy(), error);
+        self.failures.push(TransferFailure {
+            path: path.to_string_lossy().to_string(),
+            error: error.to_string(),
+        });
+    }
+
+    fn emit_progress(&mut self, force: bool) {
+        let now = Instant::now();
+        if !force {
+            if let Some(last_emit) = self.last_emit {
+                if now - last_emit < PROGRESS_INTERVAL {
+                    return;
+                }
+            }
+        }
+        self.last_emit = Some(now);
+
+        let elapsed = (now - self.started).as_secs_f64();
+        let bytes_per_second = if elapsed > 0.0 {
+            (self.bytes_done as f64 / elapsed) as u64
+        } else {
+            0
+        };
+        let eta_seconds = self
+            .bytes_total
+            .saturating_sub(self.bytes_done)
+            .checked_div(bytes_per_second);
+
+        let progress = TransferProgress {
+            id: self.id,
+            bytes_done: self.bytes_done,
+            bytes_total: self.bytes_total,
+            files_done: self.files_done,
+            files_total: self.files_total,
+            current_file: self.current_file.clone(),
+            bytes_per_second,
+            eta_seconds,
+        };
+
+        if let Err(e) = self.window.emit("transfer_progress", progress) {
+            eprintln!("Failed to emit transfer_progress: {}", e);
+        }
+    }
+
+    fn resolve(&mut self, source: &Path, destination: &Path) -> Result<Target, Error> {
+        if fs::symlink_metadata(destination).is_err() {
+            return Ok(Target::Fresh(destination.to_path_buf()));
+        }
+
+        let resolution = match self.remembered {
+            Some(resolution) => resolution,
+            None => match self.policy.decide(source, destination) {
+                Some(resolution) => resolution,
+                None => self.ask(source, destination)?,
+            },
+        };
+
+        // Overwriting an item with itself would only destroy it, so that is a skip.
+        // Keeping both still duplicates it.
+        let same_path = source == destination;
+
+        match resolution {
+            ConflictResolution::Skip => Ok(Target::Skip),
+            ConflictResolution::Overwrite if same_path => Ok(Target::Skip),
+            ConflictResolution::Overwrite => {
+                if self.is_dir(source) != destination.is_dir() {
+                    return Err(Error::Custom(format!(
+                        "Cannot overwrite {} with an item of a different type",
+                        destination.to_string_lossy()
+                    )));
+                }
+                Ok(Target::Replace(destination.to_path_buf()))
+            }
+            ConflictResolution::KeepBoth => Ok(Target::Fresh(unique_destination(destination))),
+            ConflictResolution::Cancel => {
+                self.cancelled.store(true, Ordering::Relaxed);
+                Err(Error::Custom("Transfer cancelled".to_string()))
+            }
+        }
+    }
+
+    /// Asks the frontend how to resolve a conflict and blocks until it answers.
+    fn ask(&mut self, source: &Path, destination: &Path) -> Result<ConflictResolution, Error> {
+        let source_meta = fs::metadata(source)?;
+        let destination_meta = fs::metadata(destination)?;
+
+        let id = NEXT_CONFLICT_ID.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+
+        PENDING_CONFLICTS.lock().unwrap().insert(
+            id,
+            PendingConflict {
+                transfer_id: self.id,
+                window: self.window.label().to_string(),
+                sender,
+            },
+        );
+
+        // Cancelled before the conflict was registered, nobody would answer it.
+        if self.is_cancelled() {
+            PENDING_CONFLICTS.lock().unwrap().remove(&id);
+            return Err(Error::Custom("Transfer cancelled".to_string()));
+        }
+
+        let conflict = TransferConflict {
+            id,
+            transfer_id: self.id,
+            source: source.to_string_lossy().to_string(),
+            destination: destination.to_string_lossy().to_string(),
+            is_dir: source_meta.is_dir(),
+            source_size: source_meta.len(),
+            destination_size: destination_meta.len(),
+            source_modified: modified_secs(&source_meta),
+            destination_modified: modified_secs(&destination_meta),
+        };
+
+        if let Err(e) = self.window.emit("transfer_conflict", conflict) {
+            PENDING_CONFLICTS.lock().unwrap().remove(&id);
+            return Err(Error::Custom(format!("Failed to emit conflict: {}", e)));
+        }
+
+        let answer = receiver
+            .blocking_recv()
+            .map_err(|_| Error::Custom("Conflict was never answered".to_string()))?;
+
+        if answer.apply_to_all {
+            self.remembered = Some(answer.resolution);
+        }
+
+        Ok(answer.resolution)
+    }
+
+    /// Copies a file or directory to `destination`.
+    /// Returns the path that was written, or `None` when the conflict resolved to a skip.
+    fn copy_path(&mut self, source: &Path, destination: &Path) -> Result<Option<PathBuf>, Error> {
+        check_not_into_itself(source, destination)?;
+
+        let target = match self.resolve(source, destination)? {
+            Target::Skip => {
+                self.mark_done(source);
+                return Ok(None);
+            }
+            Target::Fresh(target) => {
+                if self.is_dir(source) {
+                    let metadata = PreservedMetadata::read(source, self.dereference_symlinks)?;
+                    fs::create_dir_all(&target)?;
+                    self.created.push(target.clone());
+                    self.transfer_tree(source, &target, ConflictPolicy::Overwrite)?;
+                    metadata.apply(&target);
+                } else {
+                    self.copy_file(source, &target)?;
+                    self.created.push(target.clone());
+                }
+                target
+            }
+            Target::Replace(target) => {
+                if self.is_dir(source) {
+                    self.transfer_tree(source, &target, self.policy.for_children())?;
+                } else {
+                    self.copy_file(source, &target)?;
+                }
+                target
+            }
+        };
+
+        Ok(Some(target))
+    }
+
+    /// Moves a file or directory to `destination`, falling back to copy and delete across volumes.
+    /// Returns the path that was written, or `None` when the conflict resolved to a skip.
+    fn move_path(&mut self, source: &Path, destination: &Path) -> Result<Option<PathBuf>, Error> {
+        if source == destination {
+            return Ok(Some(destination.to_path_buf()));
+        }
+
+        check_not_into_itself(source, destination)?;
+
+        let target = match self.resolve(source, destination)? {
+            Target::Skip => {
+                self.mark_done(source);
+                return Ok(None);
+            }
+            Target::Fresh(target) => {
+                if self.is_dir(source) {
+                    self.move_dir(source, &target)?;
+                } else {
+                    self.move_file(source, &target)?;
+                }
+                target
+            }
+            Target::Replace(target) => {
+                if self.is_dir(source) {
+                    self.transfer_tree(source, &target, self.policy.for_children())?;
+                } else {
+                    self.move_file(source, &target)?;
+                }
+                target
+            }
+        };
+
+        Ok(Some(target))
+    }
+
+    /// Transfers the contents of `from` into the existing directory `to`.
+    /// Conflicts inside are decided by `policy` without asking, failures are recorded per
+    /// entry, and when moving, anything skipped or failed stays behind in the source.
+    fn transfer_tree(
+        &mut self,
+        from: &Path,
+        to: &Path,
+        policy: ConflictPolicy,
+    ) -> Result<(), Error> {
+        for entry_result in fs::read_dir(from)? {
+            self.check_cancelled()?;
+
+            let entry = match entry_result {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.fail(from, e.into());
+                    continue;
+                }
+            };
+            let from_path = entry.path();
+            let to_path = to.join(entry.file_name());
+
+            match self.transfer_entry(&from_path, &to_path, policy) {
+                Ok(()) => {}
+                Err(e) if self.is_cancelled() => return Err(e),
+                Err(e) => self.fail(&from_path, e),
+            }
+        }
+
+        if self.mode == TransferMode::Move {
+            // Only succeeds once everything has been moved out, which is what we want.
+            let _ = fs::remove_dir(from);
+        }
+
+        Ok(())
+    }
+
+    fn transfer_entry(
+        &mut self,
+        from: &Path,
+        to: &Path,
+        policy: ConflictPolicy,
+    ) -> Result<(), Error> {
+        let is_dir = self.is_dir(from);
+
+        if fs::symlink_metadata(to).is_err() {
+            return match (self.mode, is_dir) {
+                (TransferMode::Copy, true) => {
+                    let metadata = PreservedMetadata::read(from, self.dereference_symlinks)?;
+                    fs::create_dir(to)?;
+                    self.created.push(to.to_path_buf());
+                    self.transfer_tree(from, to, policy)?;
+                    metadata.apply(to);
+                    Ok(())
+                }
+                (TransferMode::Copy, false) => {
+                    self.copy_file(from, to)?;
+                    self.created.push(to.to_path_buf());
+                    Ok(())
+                }
+                (TransferMode::Move, true) => self.move_dir(from, to),
+                (TransferMode::Move, false) => self.move_file(from, to),
+            };
+        }
+
+        if is_dir != to.is_dir() {
+            return Err(Error::Custom(
+                "An item of a different type exists at the destination".to_string(),
+            ));
+        }
+
+        if is_dir {
+            return self.transfer_tree(from, to, policy);
+        }
+
+        if policy.decide(from, to) != Some(ConflictResolution::Overwrite) {
+            self.mark_done(from);
+            return Ok(());
+        }
+
+        match self.mode {
+            TransferMode::Copy => self.copy_file(from, to),
+            TransferMode::Move => self.move_file(from, to),
+        }
+    }
+
+    /// Counts everything below `path` as done, for items that were skipped, failed or
+    /// renamed in one go, so the progress still reaches its total.
+    fn mark_done(&mut self, path: &Path) {
+        let (bytes, files) = tree_size(path, self.dereference_symlinks);
+        self.bytes_done += bytes;
+        self.files_done += files;
+        self.emit_progress(false);
+    }
+
+    fn move_dir(&mut self, from: &Path, to: &Path) -> Result<(), Error> {
+        if fs::rename(from, to).is_ok() {
+            self.mark_done(to);
+            return Ok(());
+        }
+
+        // Read up front, the source directory is gone once everything has moved out.
+        let metadata = PreservedMetadata::read(from, false)?;
+        fs::create_dir_all(to)?;
+        self.transfer_tree(from, to, ConflictPolicy::Overwrite)?;
+        metadata.apply(to);
+        Ok(())
+    }
+
+    fn move_file(&mut self, from: &Path, to: &Path) -> Result<(), Error> {
+        if fs::rename(from, to).is_ok() {
+            self.mark_done(to);
+            return Ok(());
+        }
+
+        self.copy_file(from, to)?;
+        fs::remove_file(from)?;
+        Ok(())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        if self.dereference_symlinks {
+            path.is_dir()
+        } else {
+            fs::symlink_metadata(path)
+                .map(|metadata| metadata.is_dir())
+                .unwrap_or(false)
+        }
+    }
+
+    /// Copies a single file or symbolic link, keeping its permissions, timestamps and
+    /// extended attributes. The data is written to a partial file next to the target and
+    /// renamed into place once complete, so an overwrite never leaves a half-written file.
+    fn copy_file(&mut self, from: &Path, to: &Path) -> Result<(), Error> {
+        let partial = partial_path(to);
+        self.current_file = Some(from.to_string_lossy().to_string());
+
+        let is_symlink = fs::symlink_metadata(from)?.file_type().is_symlink();
+        // A dangling link has nothing to dereference, so it is copied as a link.
+        let copy_as_link = is_symlink && (!self.dereference_symlinks || !from.exists());
+
+        let bytes_before = self.bytes_done;
+        let result = if copy_as_link {
+            copy_symlink(from, &partial)
+        } else {
+            self.copy_file_contents(from, &partial)
+        };
+
+        if let Err(e) = result {
+            let _ = fs::remove_file(&partial);
+            self.bytes_done = bytes_before;
+            self.mark_done(from);
+            return Err(e);
+        }
+
+        match PreservedMetadata::read(from, !copy_as_link) {
+            Ok(metadata) => metadata.apply(&partial),
+            Err(e) => eprintln!(
+                "Failed to read metadata of {}: {}",
+                from.to_string_lossy(),
+                e
+            ),
+        }
+
+        if let Err(e) = fs::rename(&partial, to) {
+            let _ = fs::remove_file(&partial);
+            return Err(e.into());
+        }
+
+        self.files_done += 1;
+        self.emit_progress(false);
+        Ok(())
+    }
+
+    /// Copies the contents of a file in chunks, reporting progress as it goes.
+    /// A reflink is tried first so copies within a copy-on-write volume are instant.
+    fn copy_file_contents(&mut self, from: &Path, to: &Path) -> Result<(), Error> {
+        if reflink_copy::reflink(from, to).is_ok() {
+            self.bytes_done += fs::metadata(to)?.len();
+            self.emit_progress(false);
+            return Ok(());
+        }
+
+        let mut reader = File::open(from)?;
+        let mut writer = File::create(to)?;
+        let mut buffer = Vec::new();
+        let mut kernel_copy = true;
+
+        loop {
+            self.check_cancelled()?;
+
+            let copied = copy_chunk(&mut reader, &mut writer, &mut buffer, &mut kernel_copy)?;
+            if copied == 0 {
+                break;
+            }
+
+            self.bytes_done += copied as u64;
+            self.emit_progress(false);
+        }
+
+        Ok(())
+    }
+}
+
+/// The parts of a file's metadata that a copy carries over.
+struct PreservedMetadata {
+    permissions: Permissions,
+    accessed: FileTime,
+    modified: FileTime,
+    is_symlink: bool,
+    #[cfg(unix)]
+    xattrs: Vec<(OsString, Vec<u8>)>,
+}
+
+impl PreservedMetadata {
+    fn read(path: &Path, follow_symlinks: bool) -> io::Result<Self> {
+        let metadata = if follow_symlinks {
+            fs::metadata(path)?
+        } else {
+            fs::symlink_metadata(path)?
+        };
+
+        Ok(Self {
+            permissions: metadata.permissions(),
+            accessed: FileTime::from_last_access_time(&metadata),
+            modified: FileTime::from_last_modification_time(&metadata),
+            is_symlink: metadata.file_type().is_symlink(),
+            #[cfg(unix)]
+            xattrs: read_xattrs(path, follow_symlinks),
+        })
+    }
+
+    /// Applies the metadata to `path`. Failures are only logged, destinations such as FAT
+    /// formatted USB sticks can't hold most of it and that should not fail the copy.
+    fn apply(&self, path: &Path) {
+        #[cfg(unix)]
+        for (name, value) in &self.xattrs {
+            if let Err(e) = xattr::set(path, name, value) {
+                eprintln!(
+                    "Failed to copy attribute {:?} to {}: {}",
+                    name,
+                    path.to_string_lossy(),
+                    e
+                );
+            }
+        }
+
+        // Permissions can't be set on a link itself, only on what it points to.
+        if !self.is_symlink {
+            if let Err(e) = fs::set_permissions(path, self.permissions.clone()) {
+                eprintln!(
+                    "Failed to copy permissions to {}: {}",
+                    path.to_string_lossy(),
+                    e
+                );
+            }
+        }
+
+        let result = if self.is_symlink {
+            filetime::set_symlink_file_times(path, self.accessed, self.modified)
+        } else {
+            filetime::set_file_times(path, self.accessed, self.modified)
+        };
+
+        if let Err(e) = result {
+            eprintln!(
+                "Failed to copy timestamps to {}: {}",
+                path.to_string_lossy(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(unix)]
+fn read_xattrs(path: &Path, follow_symlinks: bool) -> Vec<(OsString, Vec<u8>)> {
+    let names = if follow_symlinks {
+        xattr::list_deref(path)
+    } else {
+        xattr::list(path)
+    };
+
+    let names = match names {
+        Ok(names) => names,
+        Err(_) => return Vec::new(),
+    };
+
+    names
+        .filter_map(|name| {
+            let value = if follow_symlinks {
+                xattr::get_deref(path, &name)
+            } else {
+                xattr::get(path, &name)
+            };
+            Some((name, value.ok()??))
+        })
+        .collect()
+}
+
+/// Recreates the symbolic link `from` at `to`, pointing at the same target.
+fn copy_symlink(from: &Path, to: &Path) -> Result<(), Error> {
+    let target = fs::read_link(from)?;
+
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&target, to)?;
+
+    #[cfg(windows)]
+    {
+        if from.is_dir() {
+            std::os::windows::fs::symlink_dir(&target, to)?;
+        } else {
+            std::os::windows::fs::symlink_file(&target, to)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the next chunk from `reader` to `writer` and returns its size, 0 at the end.
+/// On Linux `copy_file_range` is used while the filesystems allow it, which keeps the data
+/// in the kernel and lets NFS and friends copy on the server side.
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+fn copy_chunk(
+    reader: &mut File,
+    writer: &mut File,
+    buffer: &mut Vec<u8>,
+    kernel_copy: &mut bool,
+) -> io::Result<usize> {
+    #[cfg(target_os = "linux")]
+    if *kernel_copy {
+        use std::os::unix::io::AsRawFd;
+
+        // Null offsets make the call advance both files, so a read/write fallback can
+        // simply carry on from where it stopped.
+        let copied = unsafe {
+            libc::copy_file_range(
+                reader.as_raw_fd(),
+                std::ptr::null_mut(),
+                writer.as_raw_fd(),
+                std::ptr::null_mut(),
+                CHUNK_SIZE,
+                0,
+            )
+        };
+
+        if copied >= 0 {
+            return Ok(copied as usize);
+        }
+        *kernel_copy = false;
+    }
+
+    if buffer.is_empty() {
+        buffer.resize(CHUNK_SIZE, 0);
+    }
+
+    let read = reader.read(buffer)?;
+    writer.write_all(&buffer[..read])?;
+    Ok(read)
+}
+
+/// Stops a running transfer. Partially copied items are removed once it winds down.
+#[tauri::command]
+pub fn cancel_transfer(id: u64) -> Result<(), Error> {
+    match ACTIVE_TRANSFERS.lock().unwrap().get(&id) {
+        Some(cancelled) => cancelled.store(true, Ordering::Relaxed),
+        None => return Err(Error::Custom(format!("No running transfer with id {}", id))),
+    }
+
+    // A transfer waiting on a conflict only notices once it gets an answer.
+    cancel_conflicts(|pending| pending.transfer_id == id);
+    Ok(())
+}
+
+/// Cancels the transfers waiting on a conflict in a window, called once the window is
+/// destroyed and can no longer answer.
+pub fn cancel_window_conflicts(label: &str) {
+    cancel_conflicts(|pending| pending.window == label);
+}
+
+/// Answers the matching pending conflicts with `Cancel`.
+fn cancel_conflicts(matches: impl Fn(&PendingConflict) -> bool) {
+    let cancelled: Vec<PendingConflict> = {
+        let mut pending = PENDING_CONFLICTS.lock().unwrap();
+        let ids: Vec<u64> = pending
+            .iter()
+            .filter(|(_, conflict)| matches(conflict))
+            .map(|(id, _)| *id)
+            .collect();
+        ids.iter().filter_map(|id| pending.remove(id)).collect()
+    };
+
+    for conflict in cancelled {
+        let _ = conflict.sender.send(ConflictAnswer {
+            resolution: ConflictResolution::Cancel,
+            apply_to_all: false,
+        });
+    }
+}
+
+/// Answers a conflict previously emitted through `transfer_conflict`.
+#[tauri::command]
+pub fn resolve_transfer_conflict(
+    id: u64,
+    resolution: ConflictResolution,
+    apply_to_all: bool,
+) -> Result<(), Error> {
+    let pending = PENDING_CONFLICTS
+        .lock()
+        .unwrap()
+        .remove(&id)
+        .ok_or_else(|| Error::Custom(format!("No pending conflict with id {}", id)))?;
+
+    pending
+        .sender
+        .send(ConflictAnswer {
+            resolution,
+            apply_to_all,
+        })
+        .map_err(|_| Error::Custom("Transfer is no longer waiting".to_string()))
+}
+
+/// Copies or moves every path into the `destination` directory as a single transfer.
+#[tauri::command]
+pub async fn transfer_paths(
+    paths: Vec<String>,
+    destination: String,
+    mode: TransferMode,
+    conflict_policy: Option<ConflictPolicy>,
+    dereference_symlinks: Option<bool>,
+    window: tauri::Window,
+) -> Result<TransferReport, Error> {
+    let destination_dir = Path::new(&destination);
+    if !destination_dir.is_dir() {
+        return Err(Error::Custom("Destination is not a directory".to_string()));
+    }
+
+    let items: Vec<(PathBuf, PathBuf)> = paths
+        .iter()
+        .map(PathBuf::from)
+        .filter_map(|source| {
+            let target = destination_dir.join(source.file_name()?);
+            Some((source, target))
+        })
+        .collect();
+
+    let options = TransferOptions {
+        conflict_policy: conflict_policy.unwrap_or_default(),
+        dereference_symlinks: dereference_symlinks.unwrap_or(false),
+    };
+
+    Ok(run_transfer(&items, mode, options, window).await)
+}
+
+/// Runs a transfer over `(source, destination)` pairs and returns its report.
+/// The walking and copying happen on a blocking thread, off the async command runtime.
+pub async fn run_transfer(
+    items: &[(PathBuf, PathBuf)],
+    mode: TransferMode,
+    options: TransferOptions,
+    window: tauri::Window,
+) -> TransferReport {
+    let mut transfer = Transfer::start(mode, options, window);
+    let id = transfer.id;
+    let owned_items = items.to_vec();
+
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let sources: Vec<&Path> = owned_items
+            .iter()
+            .map(|(source, _)| source.as_path())
+            .collect();
+        transfer.measure(&sources);
+
+        for (source, destination) in &owned_items {
+            transfer.run(source, destination);
+        }
+
+        transfer.finish()
+    })
+    .await;
+
+    result.unwrap_or_else(|e| {
+        ACTIVE_TRANSFERS.lock().unwrap().remove(&id);
+        TransferReport {
+            id,
+            transferred: Vec::new(),
+            failures: items
+                .iter()
+                .map(|(source, _)| TransferFailure {
+                    path: source.to_string_lossy().to_string(),
+                    error: format!("Transfer failed: {}", e),
+                })
+                .collect(),
+            cancelled: false,
+        }
+    })
+}
+
+/// Finds a free path next to `path` by appending a counter, e.g. `report_1.pdf` or `photos_2`.
+pub fn unique_destination(path: &Path) -> PathBuf {
+    // Directories keep their whole name, `file_stem` would cut `v1.2` in half.
+    let (stem, extension) = if path.is_dir() {
+        (path.file_name(), None)
+    } else {
+        (path.file_stem(), path.extension())
+    };
+    let stem = stem.unwrap_or_default().to_string_lossy();
+    let extension = extension.map(|e| e.to_string_lossy());
+
+    let mut counter = 1;
+    loop {
+        let file_name = match &extension {
+            Some(extension) => format!("{}_{}.{}", stem, counter, extension),
+            None => format!("{}_{}", stem, counter),
+        };
+        let candidate = path.with_file_name(file_name);
+
+        if fs::symlink_metadata(&candidate).is_err() {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+/// Total size in bytes and number of files below `path`, or of `path` itself for a file.
+/// Symbolic links count as empty files unless they are followed.
+fn tree_size(path: &Path, follow_symlinks: bool) -> (u64, u64) {
+    if !follow_symlinks && fs::symlink_metadata(path).is_ok_and(|m| m.is_symlink()) {
+        return (0, 1);
+    }
+
+    WalkDir::new(path)
+        .follow_links(follow_symlinks)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| !entry.file_type().is_dir())
+        .fold((0, 0), |(bytes, files), entry| {
+            let size = if entry.file_type().is_file() {
+                entry.metadata().map(|m| m.len()).unwrap_or(0)
+            } else {
+                0
+            };
+            (bytes + size, files + 1)
+        })
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(PARTIAL_SUFFIX);
+    path.with_file_name(file_name)
+}
+
+fn remove_path(path: &Path) {
+    let is_dir = fs::symlink_metadata(path).is_ok_and(|m| m.is_dir());
+    let result = if is_dir {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+
+    if let Err(e) = result {
+        eprintln!("Failed to clean up {}: {}", path.to_string_lossy(), e);
+    }
+}
+
+fn check_not_into_itself(source: &Path, destination: &Path) -> Result<(), Error> {
+    if !source.is_dir() {
+        return Ok(());
+    }
+
+    let source = canonical_location(source)?;
+    let destination = canonical_location(destination)?;
+
+    if destination != source && destination.starts_with(&source) {
+        return Err(Error::Custom(
+            "Cannot transfer a directory into itself".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Resolves `..` and symbolic links in the parent of `path`, which has to exist, while
+/// the last component is kept as is since the destination usually doesn't exist yet.
+fn canonical_location(path: &Path) -> Result<PathBuf, Error> {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if !parent.as_os_str().is_empty() => {
+            Ok(parent.canonicalize()?.join(name))
+        }
+        _ => Ok(path.canonicalize()?),
+    }
+}
+
+fn is_newer(source: &Path, destination: &Path) -> bool {
+    match (fs::metadata(source), fs::metadata(destination)) {
+        (Ok(source_meta), Ok(destination_meta)) => {
+            modified_secs(&source_meta) > modified_secs(&destination_meta)
+        }
+        _ => false,
+    }
+}
+
+fn modified_secs(metadata: &Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
@@ -1,5 +1,9 @@
+use std::fs;
 use std::path::{Path, PathBuf};
 
+/// Device and inode number, which identify a file across renames and hard links.
+pub type FileId = (u64, u64);
+
 pub fn get_mount_point(path: String) -> Option<String> {
     let path = Path::new(&path);
     let root = path.components().next()?;
@@ -10,3 +14,80 @@ pub fn get_mount_point(path: String) -> Option<String> {
     mount_point_path.push("");
     Some(mount_point_path.to_string_lossy().into_owned())
 }
+
+/// A `file://` URI for an absolute path, with everything but unreserved characters
+/// percent encoded.
+pub fn file_uri(path: &str) -> String {
+    let mut uri = String::from("file://");
+
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    uri
+}
+
+/// Undoes percent encoding, `None` when it is malformed or doesn't decode to UTF-8.
+pub fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = value.get(index + 1..index + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+/// Escapes text for use in HTML, inside elements as well as quoted attributes.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(unix)]
+pub fn file_id(metadata: &fs::Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+pub fn file_id(_metadata: &fs::Metadata) -> Option<FileId> {
+    None
+}
+
+/// The device a file lives on, `None` where the platform doesn't expose it.
+#[cfg(unix)]
+pub fn device_id(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+pub fn device_id(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
@@ -2,6 +2,7 @@ use crate::error::Error;
 use crate::filesystem::cache::{
     load_system_cache, run_cache_interval, save_system_cache, FsEventHandler, CACHE_FILE_PATH,
 };
+use crate::filesystem::mime::{detect_file_type, detect_file_type_by_name, FileCategory};
 use crate::filesystem::{DIRECTORY, FILE};
 use crate::{CachedPath, StateSafe, CREATE_NO_WINDOW};
 use lazy_static::lazy_static;
@@ -10,9 +11,12 @@ use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::File;
+use std::io;
 use std::os::windows::process::CommandExt;
-use std::path::PathBuf;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use std::{fs, thread};
 use sysinfo::{Disk, DiskExt, System, SystemExt};
 use tauri::State;
@@ -40,10 +44,212 @@ pub struct Volume {
     recycle_bin_path: String,
 }
 
+/// A single entry of a directory listing or search result.
+/// Timestamps are seconds since the Unix epoch, `None` where the platform doesn't record them.
 #[derive(Serialize, Deserialize, Clone)]
-pub enum DirectoryChild {
-    File(String, String, u64, u64, String), // Name of file, path to file, size of file, last modified seconds, type of file
-    Directory(String, String, u64, u64, String, bool, bool), // Name of directory, path to directory, size of directory, last modified seconds, is git repo, isSupportedProject
+pub struct DirectoryChild {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    /// Size in bytes, `None` for directories as their contents aren't walked when listing.
+    pub size: Option<u64>,
+    pub created: Option<u64>,
+    pub modified: Option<u64>,
+    pub accessed: Option<u64>,
+    /// Human readable type, e.g. "PNG image".
+    pub file_type: String,
+    pub mime_type: String,
+    pub category: FileCategory,
+    /// Unix mode bits, `None` on Windows.
+    pub permissions: Option<u32>,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    pub hidden: bool,
+    pub readonly: bool,
+    pub symlink_target: Option<String>,
+    /// Set when the entry is a symlink whose target doesn't exist.
+    pub broken_link: bool,
+    pub is_git: bool,
+    pub is_project: bool,
+}
+
+impl DirectoryChild {
+    /// Reads the details of `path`, following symlinks for everything but the link
+    /// target itself. The type is guessed from the name, `detect_content_type` reads the
+    /// file to tell for sure. The git and project flags are left for the caller to fill in.
+    pub fn from_path(path: &Path) -> io::Result<Self> {
+        let link_metadata = fs::symlink_metadata(path)?;
+        let is_symlink = link_metadata.file_type().is_symlink();
+
+        let symlink_target = if is_symlink {
+            fs::read_link(path)
+                .ok()
+                .map(|target| target.to_string_lossy().to_string())
+        } else {
+            None
+        };
+
+        // A broken link has nothing to follow, so it is described by the link itself.
+        let (metadata, broken_link) = match fs::metadata(path) {
+            Ok(metadata) => (metadata, false),
+            Err(_) if is_symlink => (link_metadata, true),
+            Err(e) => return Err(e),
+        };
+
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string());
+
+        let is_dir = metadata.is_dir();
+
+        let (file_type, mime_type, category) = if broken_link {
+            (
+                "Broken link".to_string(),
+                "inode/symlink".to_string(),
+                FileCategory::Other,
+            )
+        } else {
+            let detected = detect_file_type_by_name(path, is_dir);
+            (detected.description, detected.mime_type, detected.category)
+        };
+
+        let (owner, group) = owner_and_group(&metadata);
+
+        Ok(Self {
+            hidden: is_hidden(&name, &metadata),
+            readonly: metadata.permissions().readonly(),
+            permissions: mode(&metadata),
+            size: if is_dir { None } else { Some(metadata.len()) },
+            created: metadata.created().ok().and_then(epoch_secs),
+            modified: metadata.modified().ok().and_then(epoch_secs),
+            accessed: metadata.accessed().ok().and_then(epoch_secs),
+            name,
+            path: path.to_string_lossy().to_string(),
+            is_dir,
+            file_type,
+            mime_type,
+            category,
+            owner,
+            group,
+            symlink_target,
+            broken_link,
+            is_git: false,
+            is_project: false,
+        })
+    }
+
+    /// Replaces the type guessed from the name with the one the file's content shows.
+    pub fn detect_content_type(&mut self) {
+        if self.is_dir || self.broken_link {
+            return;
+        }
+
+        let detected = detect_file_type(Path::new(&self.path));
+        self.file_type = detected.description;
+        self.mime_type = detected.mime_type;
+        self.category = detected.category;
+    }
+}
+
+fn epoch_secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|duration| duration.as_secs())
+}
+
+#[cfg(unix)]
+pub fn is_hidden(name: &str, _metadata: &fs::Metadata) -> bool {
+    name.starts_with('.')
+}
+
+#[cfg(windows)]
+pub fn is_hidden(_name: &str, metadata: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0
+}
+
+#[cfg(unix)]
+pub fn mode(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(windows)]
+pub fn mode(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+pub fn owner_and_group(metadata: &fs::Metadata) -> (Option<String>, Option<String>) {
+    use std::os::unix::fs::MetadataExt;
+
+    (user_name(metadata.uid()), group_name(metadata.gid()))
+}
+
+#[cfg(windows)]
+pub fn owner_and_group(_metadata: &fs::Metadata) -> (Option<String>, Option<String>) {
+    (None, None)
+}
+
+/// Looks up the name of a user id, falling back to the id itself.
+/// Names are cached as a listing asks for the same few ids over and over.
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> Option<String> {
+    lazy_static! {
+        static ref USER_NAMES: Mutex<HashMap<u32, String>> = Mutex::new(HashMap::new());
+    }
+
+    let mut names = USER_NAMES.lock().ok()?;
+    let name = names.entry(uid).or_insert_with(|| {
+        let mut buf = vec![0 as libc::c_char; 4096];
+        let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+        let status =
+            unsafe { libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+
+        if status == 0 && !result.is_null() {
+            unsafe { std::ffi::CStr::from_ptr(passwd.pw_name) }
+                .to_string_lossy()
+                .to_string()
+        } else {
+            uid.to_string()
+        }
+    });
+
+    Some(name.clone())
+}
+
+/// Looks up the name of a group id, falling back to the id itself.
+#[cfg(unix)]
+pub fn group_name(gid: u32) -> Option<String> {
+    lazy_static! {
+        static ref GROUP_NAMES: Mutex<HashMap<u32, String>> = Mutex::new(HashMap::new());
+    }
+
+    let mut names = GROUP_NAMES.lock().ok()?;
+    let name = names.entry(gid).or_insert_with(|| {
+        let mut buf = vec![0 as libc::c_char; 4096];
+        let mut group: libc::group = unsafe { std::mem::zeroed() };
+        let mut result: *mut libc::group = std::ptr::null_mut();
+
+        let status =
+            unsafe { libc::getgrgid_r(gid, &mut group, buf.as_mut_ptr(), buf.len(), &mut result) };
+
+        if status == 0 && !result.is_null() {
+            unsafe { std::ffi::CStr::from_ptr(group.gr_name) }
+                .to_string_lossy()
+                .to_string()
+        } else {
+            gid.to_string()
+        }
+    });
+
+    Some(name.clone())
 }
 
 impl Volume {
@@ -0,0 +1,308 @@
+use lazy_static::lazy_static;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::listing::probe_entries;
+use super::volume::DirectoryChild;
+use crate::error::Error;
+
+/// How long to wait for more events before telling the frontend about a batch.
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Longest a change waits while events keep coming, so a file being written to
+/// continuously still shows up.
+const MAX_LATENCY: Duration = Duration::from_secs(1);
+
+lazy_static! {
+    /// Watchers of the directories open in each window, keyed by window label and path.
+    /// Dropping a watcher closes its channel, which ends its debounce thread.
+    static ref WATCHED_DIRECTORIES: Mutex<HashMap<(String, PathBuf), RecommendedWatcher>> =
+        Mutex::new(HashMap::new());
+}
+
+#[derive(Serialize, Clone)]
+pub struct RenamedChild {
+    from: String,
+    to: DirectoryChild,
+}
+
+/// Emitted as `directory_changed` with everything that changed in a watched directory
+/// since the last batch.
+#[derive(Serialize, Clone, Default)]
+pub struct DirectoryChanges {
+    path: String,
+    created: Vec<DirectoryChild>,
+    removed: Vec<String>,
+    renamed: Vec<RenamedChild>,
+    modified: Vec<DirectoryChild>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Change {
+    Created,
+    Removed,
+    Modified,
+    RenamedFrom(PathBuf),
+}
+
+/// Changes per path in the order they happened, so the frontend applies them in that order.
+/// Batches are small, a linear search is fine.
+#[derive(Default)]
+struct PendingChanges(Vec<(PathBuf, Change)>);
+
+impl PendingChanges {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn get(&self, path: &Path) -> Option<&Change> {
+        self.0
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, change)| change)
+    }
+
+    fn insert(&mut self, path: PathBuf, change: Change) {
+        match self.0.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, existing)) => *existing = change,
+            None => self.0.push((path, change)),
+        }
+    }
+
+    fn remove(&mut self, path: &Path) -> Option<Change> {
+        let index = self.0.iter().position(|(p, _)| p == path)?;
+        Some(self.0.remove(index).1)
+    }
+}
+
+/// Starts pushing changes of the direct children of `path` to this window.
+#[tauri::command]
+pub async fn watch_directory(path: String, window: tauri::Window) -> Result<(), Error> {
+    let directory = PathBuf::from(&path);
+    if !directory.is_dir() {
+        return Err(Error::Custom(format!("{} is not a directory", path)));
+    }
+
+    let key = (window.label().to_string(), directory.clone());
+    let mut watched = WATCHED_DIRECTORIES.lock().unwrap();
+    if watched.contains_key(&key) {
+        return Ok(());
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |res| match res {
+        Ok(event) => {
+            let _ = sender.send(event);
+        }
+        Err(e) => eprintln!("Failed to handle event: {e}"),
+    })
+    .map_err(|e| Error::Custom(e.to_string()))?;
+
+    watcher
+        .watch(&directory, RecursiveMode::NonRecursive)
+        .map_err(|e| Error::Custom(e.to_string()))?;
+
+    thread::spawn(move || debounce_events(receiver, directory, window));
+
+    watched.insert(key, watcher);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn unwatch_directory(path: String, window: tauri::Window) -> Result<(), Error> {
+    let key = (window.label().to_string(), PathBuf::from(path));
+    WATCHED_DIRECTORIES.lock().unwrap().remove(&key);
+    Ok(())
+}
+
+/// Stops every watcher of a window, called once the window is destroyed.
+pub fn unwatch_window(label: &str) {
+    WATCHED_DIRECTORIES
+        .lock()
+        .unwrap()
+        .retain(|(window_label, _), _| window_label != label);
+}
+
+/// Collects events until none arrive for `DEBOUNCE_INTERVAL`, or the oldest has waited
+/// `MAX_LATENCY`, then emits them as one batch.
+fn debounce_events(receiver: Receiver<Event>, directory: PathBuf, window: tauri::Window) {
+    let mut pending = PendingChanges::default();
+    let mut batch_started = Instant::now();
+    // Halves of a rename reported as two events, matched through their tracker id.
+    let mut rename_sources: HashMap<usize, PathBuf> = HashMap::new();
+
+    loop {
+        let event = if pending.is_empty() {
+            match receiver.recv() {
+                Ok(event) => {
+                    batch_started = Instant::now();
+                    event
+                }
+                Err(_) => return,
+            }
+        } else {
+            let timeout = MAX_LATENCY
+                .saturating_sub(batch_started.elapsed())
+                .min(DEBOUNCE_INTERVAL);
+
+            // A steady stream of events would otherwise keep the channel from timing out.
+            let received = if timeout.is_zero() {
+                Err(RecvTimeoutError::Timeout)
+            } else {
+                receiver.recv_timeout(timeout)
+            };
+
+            match received {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => {
+                    rename_sources.clear();
+                    emit_changes(&directory, std::mem::take(&mut pending), &window);
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        };
+
+        let tracker = event.attrs.tracker();
+        let paths = event.paths;
+
+        match event.kind {
+            EventKind::Create(_) => record(&mut pending, &paths[0], Change::Created),
+            EventKind::Remove(_) => record(&mut pending, &paths[0], Change::Removed),
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if paths.len() == 2 => {
+                rename(&mut pending, &paths[0], &paths[1], &directory)
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                if let Some(tracker) = tracker {
+                    rename_sources.insert(tracker, paths[0].clone());
+                }
+                record(&mut pending, &paths[0], Change::Removed);
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                match tracker.and_then(|tracker| rename_sources.remove(&tracker)) {
+                    Some(from) => rename(&mut pending, &from, &paths[0], &directory),
+                    None => record(&mut pending, &paths[0], Change::Created),
+                }
+            }
+            // Some backends, like FSEvents, can't tell which side of a rename a path is.
+            EventKind::Modify(ModifyKind::Name(_)) => {
+                for path in &paths {
+                    let change = if path.exists() {
+                        Change::Created
+                    } else {
+                        Change::Removed
+                    };
+                    record(&mut pending, path, change);
+                }
+            }
+            EventKind::Modify(_) => {
+                for path in &paths {
+                    record(&mut pending, path, Change::Modified);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Merges a change into what is already pending for the same path.
+fn record(pending: &mut PendingChanges, path: &Path, change: Change) {
+    // Renamed and then deleted, the frontend only knows about the old name.
+    if let (Some(Change::RenamedFrom(from)), Change::Removed) = (pending.get(path), &change) {
+        let from = from.clone();
+        pending.remove(path);
+        pending.insert(from, Change::Removed);
+        return;
+    }
+
+    let merged = match (pending.get(path), change) {
+        (None, change) => Some(change),
+        // Created and gone again before anyone saw it.
+        (Some(Change::Created), Change::Removed) => None,
+        (Some(Change::Created), _) => Some(Change::Created),
+        (Some(Change::Removed), Change::Created) => Some(Change::Modified),
+        (Some(Change::RenamedFrom(from)), Change::Modified) => {
+            Some(Change::RenamedFrom(from.clone()))
+        }
+        (Some(_), change) => Some(change),
+    };
+
+    match merged {
+        Some(change) => {
+            pending.insert(path.to_path_buf(), change);
+        }
+        None => {
+            pending.remove(path);
+        }
+    }
+}
+
+/// Records a rename, which only counts as one if both sides are in the watched directory.
+fn rename(pending: &mut PendingChanges, from: &Path, to: &Path, directory: &Path) {
+    let from_inside = from.parent() == Some(directory);
+    let to_inside = to.parent() == Some(directory);
+
+    match (from_inside, to_inside) {
+        (true, true) => {
+            // A path that was only just created is reported as created under its new name.
+            let change = match pending.remove(from) {
+                Some(Change::Created) => Change::Created,
+                Some(Change::RenamedFrom(original)) => Change::RenamedFrom(original),
+                _ => Change::RenamedFrom(from.to_path_buf()),
+            };
+            pending.insert(to.to_path_buf(), change);
+        }
+        (true, false) => record(pending, from, Change::Removed),
+        (false, true) => record(pending, to, Change::Created),
+        (false, false) => {}
+    }
+}
+
+fn emit_changes(directory: &Path, pending: PendingChanges, window: &tauri::Window) {
+    let mut changes = DirectoryChanges {
+        path: directory.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+
+    for (path, change) in pending.0 {
+        if change == Change::Removed {
+            changes.removed.push(path.to_string_lossy().to_string());
+            continue;
+        }
+
+        // The path may be gone again by the time the batch is sent.
+        let mut child = match DirectoryChild::from_path(&path) {
+            Ok(child) => vec![child],
+            Err(_) => {
+                if let Change::RenamedFrom(from) = change {
+                    changes.removed.push(from.to_string_lossy().to_string());
+                }
+                continue;
+            }
+        };
+        probe_entries(&mut child);
+        let child = child.remove(0);
+
+        match change {
+            Change::Created => changes.created.push(child),
+            Change::Modified => changes.modified.push(child),
+            Change::RenamedFrom(from) if from == path => changes.modified.push(child),
+            Change::RenamedFrom(from) => changes.renamed.push(RenamedChild {
+                from: from.to_string_lossy().to_string(),
+                to: child,
+            }),
+            Change::Removed => unreachable!("handled above"),
+        }
+    }
+
+    if let Err(e) = window.emit("directory_changed", changes) {
+        eprintln!("Failed to emit directory changes: {}", e);
+    }
+}
@@ -20,16 +20,43 @@ use commands::{
 
 use archiver::{archive_folder, extract_archive};
 use encryption::{decrypt_file, encrypt_file, is_file_encrypted};
+use filesystem::bookmarks::{add_bookmark, get_bookmarks, remove_bookmark, rename_bookmark};
+use filesystem::bulk_rename::bulk_rename;
+use filesystem::checksum::{compute_checksums, verify_checksums};
+use filesystem::compare::{compare_directories, sync_directories};
+use filesystem::disk_usage::{analyze_disk_usage, compute_folder_sizes, get_folder_size};
+use filesystem::duplicates::{find_duplicates, resolve_duplicates};
 use filesystem::explorer::{
     add_all_changes, checkout_branch_for_directory, clear_recycle_bin,
     commit_changes_for_directory, cut_directory_from, cut_file_from, delete_file,
-    fetch_repo_for_directory, get_file_preview, get_files_for_paths, get_folder_size,
-    get_git_meta_for_directory, get_supported_project_metadata, init_git_repo_in_directory,
-    install_dep, open_directory, open_file, open_with_explorer, paste_directory_at, paste_file_at,
-    pull_changes_for_directory, push_changes_for_directory, remove_dep,
-    stash_changes_for_directory,
+    fetch_repo_for_directory, get_file_preview, get_files_for_paths, get_git_meta_for_directory,
+    get_supported_project_metadata, init_git_repo_in_directory, install_dep, open_directory,
+    open_file, open_with_explorer, paste_directory_at, paste_file_at, pull_changes_for_directory,
+    push_changes_for_directory, remove_dep, stash_changes_for_directory,
+};
+use filesystem::formatted_preview::get_formatted_preview;
+use filesystem::hex_preview::{get_binary_header, get_hex_preview};
+use filesystem::highlight::get_highlighted_preview;
+use filesystem::history::{
+    clear_history, get_recent_items, import_system_history, pin_recent_item, record_preview,
+    record_search_pick, remove_recent_item,
+};
+use filesystem::listing::stream_directory;
+use filesystem::open_with::{get_applications_for_path, open_file_with, set_default_application};
+use filesystem::operations::{create_directory, create_file, get_file_templates, rename_path};
+use filesystem::permissions::{get_permissions, set_permissions};
+use filesystem::protocol::{handle_bytes_request, BYTES_SCHEME};
+use filesystem::similar_images::find_similar_images;
+use filesystem::tags::{delete_tag, get_tagged_paths, get_tags, list_tags, update_tags};
+use filesystem::text_preview::get_text_preview;
+use filesystem::thumbnails::{
+    clear_thumbnail_cache, get_thumbnail, pregenerate_thumbnails, set_thumbnail_cache_limit,
+};
+use filesystem::transfer::{
+    cancel_transfer, cancel_window_conflicts, resolve_transfer_conflict, transfer_paths,
 };
 use filesystem::volume::{get_volumes, safely_eject_removable};
+use filesystem::watcher::{unwatch_directory, unwatch_window, watch_directory};
 use search::search_directory;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -79,6 +106,13 @@ async fn main() {
             set_shadow(&window, true).unwrap();
             Ok(())
         })
+        .register_uri_scheme_protocol(BYTES_SCHEME, handle_bytes_request)
+        .on_window_event(|event| {
+            if let tauri::WindowEvent::Destroyed = event.event() {
+                unwatch_window(event.window().label());
+                cancel_window_conflicts(event.window().label());
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             get_volumes,
             safely_eject_removable,
@@ -104,6 +138,56 @@ async fn main() {
             paste_directory_at,
             cut_directory_from,
             cut_file_from,
+            transfer_paths,
+            resolve_transfer_conflict,
+            cancel_transfer,
+            create_directory,
+            create_file,
+            rename_path,
+            get_file_templates,
+            bulk_rename,
+            stream_directory,
+            watch_directory,
+            unwatch_directory,
+            compute_folder_sizes,
+            analyze_disk_usage,
+            compare_directories,
+            sync_directories,
+            compute_checksums,
+            verify_checksums,
+            find_duplicates,
+            resolve_duplicates,
+            find_similar_images,
+            get_permissions,
+            set_permissions,
+            get_tags,
+            update_tags,
+            list_tags,
+            get_tagged_paths,
+            delete_tag,
+            get_bookmarks,
+            add_bookmark,
+            rename_bookmark,
+            remove_bookmark,
+            get_recent_items,
+            record_search_pick,
+            record_preview,
+            pin_recent_item,
+            remove_recent_item,
+            clear_history,
+            import_system_history,
+            get_applications_for_path,
+            open_file_with,
+            set_default_application,
+            get_thumbnail,
+            pregenerate_thumbnails,
+            set_thumbnail_cache_limit,
+            clear_thumbnail_cache,
+            get_text_preview,
+            get_hex_preview,
+            get_binary_header,
+            get_highlighted_preview,
+            get_formatted_preview,
             install_theme,
             get_installed_themes,
             remove_theme,
@@ -1,20 +1,16 @@
 use crate::filesystem::explorer::check_is_supported_project;
-use crate::filesystem::get_file_description;
+use crate::filesystem::mime::FileCategory;
+use crate::filesystem::tags::paths_with_tags;
 use crate::CachedPath;
 use crate::{filesystem::volume::DirectoryChild, StateSafe};
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs;
-// use std::os::windows::prelude::MetadataExt;
 use std::path::Path;
 use std::time::Instant;
 use tauri::State;
 
-#[cfg(target_os = "windows")]
-use std::os::windows::fs::MetadataExt;
-
 const MINIMUM_SCORE: i16 = 20;
 
 const FILTERED_STRINGS: [&str; 3] = ["$$_systemapps_", "shared.index", "com."]; // Replace with the actual strings you want
@@ -109,11 +105,10 @@ fn check_file(
     filename: &String,
     file_path: &String,
     query: String,
-    results: &mut Vec<DirectoryChild>,
-    fuzzy_scores: &mut Vec<i16>,
-) {
+    categories: Option<&[FileCategory]>,
+) -> Option<(DirectoryChild, i16)> {
     if !accept_files {
-        return;
+        return None;
     }
 
     let filename_path = Path::new(filename);
@@ -123,7 +118,7 @@ fn check_file(
 
     for filter in &FILTERED_STRINGS {
         if filename.contains(filter) {
-            return;
+            return None;
         }
     }
 
@@ -134,55 +129,34 @@ fn check_file(
             .to_string_lossy()
             .to_lowercase();
         if file_extension != extension.to_lowercase() {
-            return;
+            return None;
         }
     }
 
     let score = score_filename(matcher, filename.as_str(), query.as_str());
 
     if score < MINIMUM_SCORE {
-        return;
+        return None;
     }
 
-    let metadata = match fs::metadata(file_path) {
-        Ok(meta) => meta,
-        Err(_) => return,
-    };
-
-    #[cfg(target_family = "windows")]
-    let is_hidden = {
-        let attributes = metadata.file_attributes();
-        attributes & 0x2 != 0
-    };
-
-    #[cfg(target_family = "unix")]
-    let is_hidden = filename.starts_with('.');
-
-    if is_hidden {
-        return;
+    let mut child = DirectoryChild::from_path(Path::new(file_path)).ok()?;
+    if child.hidden {
+        return None;
     }
 
-    let size = metadata.len();
-    let last_modified_sys_time = fs::metadata(file_path).unwrap().modified();
-
-    let last_modified = last_modified_sys_time.unwrap().elapsed().unwrap().as_secs();
-
-    let extension = filename_path
-        .extension()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
+    // Only matches get their content read, the name alone can be wrong about the type.
+    child.detect_content_type();
+    if !in_categories(&child, categories) {
+        return None;
+    }
 
-    let file_type = get_file_description(&extension);
+    Some((child, score))
+}
 
-    results.push(DirectoryChild::File(
-        filename.to_string(),
-        file_path.to_string(),
-        size,
-        last_modified,
-        file_type.to_string(),
-    ));
-    fuzzy_scores.push(score);
+/// Whether the detected type of `child` is one of `categories`, anything is when there
+/// is no category filter.
+fn in_categories(child: &DirectoryChild, categories: Option<&[FileCategory]>) -> bool {
+    categories.is_none_or(|categories| categories.contains(&child.category))
 }
 
 #[derive(Serialize, Deserialize)]
@@ -201,8 +175,42 @@ pub async fn search_directory(
     _mount_pnt: String,
     accept_files: bool,
     accept_directories: bool,
+    tags: Option<Vec<String>>,
+    categories: Option<Vec<FileCategory>>,
 ) -> Result<SearchResult, ()> {
     let start_time = Instant::now();
+    let categories = categories.filter(|categories| !categories.is_empty());
+
+    // Only paths carrying all of these tags are kept.
+    let tagged = tags
+        .filter(|tags| !tags.is_empty())
+        .map(|tags| paths_with_tags(&tags));
+
+    // Without a query, a tag filter lists everything with those tags.
+    if let (Some(tagged), true) = (&tagged, query.trim().is_empty()) {
+        let mut results: Vec<DirectoryChild> = tagged
+            .iter()
+            .filter_map(|path| DirectoryChild::from_path(Path::new(path)).ok())
+            .filter(|child| {
+                if child.is_dir {
+                    accept_directories
+                } else {
+                    accept_files
+                }
+            })
+            .map(|mut child| {
+                child.detect_content_type();
+                child
+            })
+            .filter(|child| in_categories(child, categories.as_deref()))
+            .collect();
+        results.sort_by(|a, b| a.name.cmp(&b.name));
+
+        return Ok(SearchResult {
+            results,
+            more: false,
+        });
+    }
 
     let state = state_mux.lock().unwrap();
 
@@ -255,18 +263,25 @@ pub async fn search_directory(
             let file_type = &path.file_type;
             let file_path = &path.file_path;
 
-            let filename_path = Path::new(filename);
+            if tagged
+                .as_ref()
+                .is_some_and(|tagged| !tagged.contains(file_path))
+            {
+                continue;
+            }
 
             if file_type == "file" {
-                check_file(
+                if let Some((child, score)) = check_file(
                     &matcher,
                     accept_files,
                     filename,
                     file_path,
                     query.clone(),
-                    &mut results,
-                    &mut fuzzy_scores,
-                );
+                    categories.as_deref(),
+                ) {
+                    results.push(child);
+                    fuzzy_scores.push(score);
+                }
 
                 if results.len() >= 250 {
                     println!("Over limit");
@@ -286,39 +301,19 @@ pub async fn search_directory(
                 continue;
             }
 
-            let metadata = match fs::metadata(file_path) {
-                Ok(meta) => meta,
+            let mut child = match DirectoryChild::from_path(Path::new(file_path)) {
+                Ok(child) => child,
                 Err(_) => continue,
             };
 
-            let size = metadata.len();
-            let last_modified_sys_time = fs::metadata(file_path).unwrap().modified();
-
-            let last_modified = last_modified_sys_time.unwrap().elapsed().unwrap().as_secs();
-
-            let extension = filename_path
-                .extension()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
-
-            let file_type = get_file_description(&extension);
+            if !in_categories(&child, categories.as_deref()) {
+                continue;
+            }
 
-            let is_project = match check_is_supported_project(file_path.clone()) {
-                Ok(is_project) => is_project,
-                Err(_) => false,
-            };
+            // We don't care about git in the search results
+            child.is_project = check_is_supported_project(file_path.clone()).unwrap_or(false);
 
-            results.push(DirectoryChild::Directory(
-                (*filename).to_string(),
-                file_path.to_string(),
-                size,
-                last_modified,
-                file_type.to_string(),
-                // We don't care in the search results
-                false,
-                is_project,
-            ));
+            results.push(child);
             fuzzy_scores.push(score);
 
             if results.len() >= 250 {
@@ -26,6 +26,7 @@ import {
   ProjectType,
   TagDoc,
 } from '../lib/types';
+import { bytesUrl } from '../lib/utils/bytesUrl';
 import { formatBytes } from '../lib/utils/formatBytes';
 import { generateUUID } from '../lib/utils/generateUUID';
 import { NoInternetFeature } from './NoInternetFeature';
@@ -34,7 +35,7 @@ export const ContextMenu = () => {
   const [cache, setCache] = useRecoilState(stateCacheState);
   const [runtime, setRuntime] = useRecoilState(runtimeState);
   const [currentContext, setCurrentContext] = useRecoilState(currentContextState);
-  const [preview, setPreview] = useState({ value: '', loading: true });
+  const [preview, setPreview] = useState({ value: '', image: false, loading: true });
   const [gitMeta, setGitMeta] = useState<GitMeta | null>(null);
   const [commitMessage, setCommitMessage] = useState('');
   const [show, setShow] = useState(false);
@@ -59,7 +60,7 @@ export const ContextMenu = () => {
       const item = currentContext.currentItem;
 
       const confirmed = await confirm(
-        'Are you sure you want to delete ' + (item['Directory'] ? item['Directory']![0] : item['File']![0]) + '?',
+        'Are you sure you want to delete ' + item.name + '?',
       );
 
       if (!confirmed) {
@@ -69,8 +70,8 @@ export const ContextMenu = () => {
 
       toast.promise(
         invoke('delete_file', {
-          path: item['Directory'] ? item['Directory']![1] : item['File']![1],
-          isDir: item['Directory'] ? true : false,
+          path: item.path,
+          isDir: item.is_dir,
           mountPoint: runtime.currentDrive?.mount_point,
         })
           .then((res) => {
@@ -85,9 +86,9 @@ export const ContextMenu = () => {
             console.error(err);
           }),
         {
-          error: 'Failed to delete ' + (item['Directory'] ? item['Directory']![0] : item['File']![0]),
-          loading: 'Deleting ' + (item['Directory'] ? item['Directory']![0] : item['File']![0]),
-          success: 'Deleted ' + (item['Directory'] ? item['Directory']![0] : item['File']![0]),
+          error: 'Failed to delete ' + item.name,
+          loading: 'Deleting ' + item.name,
+          success: 'Deleted ' + item.name,
         },
         {
           duration: 3000,
@@ -102,7 +103,7 @@ export const ContextMenu = () => {
     setPackageLoading(true);
 
     invoke('remove_dep', {
-      path: projectManagerItemTemp.Directory![1],
+      path: projectManagerItemTemp.path,
       projectType: curProject?.project_type,
       packageName: dep,
     })
@@ -115,8 +116,8 @@ export const ContextMenu = () => {
           </>,
         );
 
-        if (projectManagerItemTemp?.Directory && projectManagerItemTemp.Directory[6]) {
-          invoke<ProjectMetadata>('get_supported_project_metadata', { path: projectManagerItemTemp['Directory']![1] })
+        if (projectManagerItemTemp?.is_dir && projectManagerItemTemp.is_project) {
+          invoke<ProjectMetadata>('get_supported_project_metadata', { path: projectManagerItemTemp.path })
             .then((res) => {
               setPackageLoading(false);
 
@@ -155,7 +156,7 @@ export const ContextMenu = () => {
     setPackageLoading(true);
 
     invoke('install_dep', {
-      path: projectManagerItemTemp.Directory![1],
+      path: projectManagerItemTemp.path,
       projectType: curProject?.project_type,
       packageName: dep,
       asDev: asDev,
@@ -169,8 +170,8 @@ export const ContextMenu = () => {
           </>,
         );
 
-        if (projectManagerItemTemp?.Directory && projectManagerItemTemp.Directory[6]) {
-          invoke<ProjectMetadata>('get_supported_project_metadata', { path: projectManagerItemTemp['Directory']![1] })
+        if (projectManagerItemTemp?.is_dir && projectManagerItemTemp.is_project) {
+          invoke<ProjectMetadata>('get_supported_project_metadata', { path: projectManagerItemTemp.path })
             .then((res) => {
               setInstalling(false);
               setPackageLoading(false);
@@ -232,8 +233,8 @@ export const ContextMenu = () => {
     setPackageLoading(false);
     setSearchVal('');
 
-    if (item?.Directory && item.Directory[6]) {
-      invoke<ProjectMetadata>('get_supported_project_metadata', { path: item['Directory']![1] })
+    if (item?.is_dir && item.is_project) {
+      invoke<ProjectMetadata>('get_supported_project_metadata', { path: item.path })
         .then((res) => {
           setCurProject(res);
           console.log(res);
@@ -248,8 +249,8 @@ export const ContextMenu = () => {
 
     checkHidden();
 
-    if (item['File'] && item['File'][1]) {
-      invoke<boolean>('is_file_encrypted', { filePath: item['File']![1] })
+    if (!item.is_dir && item.path) {
+      invoke<boolean>('is_file_encrypted', { filePath: item.path })
         .then((res) => {
           setIsEncrypted(res);
         })
@@ -258,14 +259,24 @@ export const ContextMenu = () => {
         });
     }
 
-    setPreview({ value: '', loading: true });
+    // Images and audio are loaded straight from the bytes protocol as they are displayed.
+    if (item.category === 'Image' || item.category === 'Audio') {
+      const kind = item.category === 'Image' ? 'thumbnail' : 'waveform';
+      setPreview({ value: bytesUrl(item.path, kind), image: true, loading: false });
+      invoke('record_preview', { path: item.path }).catch((err) => {
+        console.error(err);
+      });
+      return;
+    }
+
+    setPreview({ value: '', image: false, loading: true });
 
-    invoke('get_file_preview', { path: item['Directory'] ? item['Directory']![1] : item['File']![1] })
+    invoke('get_file_preview', { path: item.path })
       .then((res) => {
-        setPreview({ value: String(res), loading: false });
+        setPreview({ value: String(res), image: false, loading: false });
 
-        if (item['Directory'] && item['Directory']![5] === true) {
-          invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] })
+        if (item.is_dir && item.is_git === true) {
+          invoke<GitMeta>('get_git_meta_for_directory', { path: item.path })
             .then((res) => {
               setGitMeta(res);
             })
@@ -277,14 +288,14 @@ export const ContextMenu = () => {
       .catch((err) => {
         console.log(err);
 
-        if (item['Directory']) {
-          invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
+        if (item.is_dir) {
+          invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
             setGitMeta(res);
-            setPreview({ value: '', loading: false });
+            setPreview({ value: '', image: false, loading: false });
           });
         } else {
           setGitMeta(null);
-          setPreview({ value: '', loading: false });
+          setPreview({ value: '', image: false, loading: false });
         }
       });
   }, [currentContext]);
@@ -294,14 +305,14 @@ export const ContextMenu = () => {
 
     if (!item) return;
 
-    if (item['Directory']) {
-      invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
+    if (item.is_dir) {
+      invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
         if (res.can_init) {
-          invoke('init_git_repo_in_directory', { path: item['Directory']![1] })
+          invoke('init_git_repo_in_directory', { path: item.path })
             .then((res) => {
               console.log(res);
 
-              invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
+              invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
                 setGitMeta(res);
               });
             })
@@ -325,9 +336,7 @@ export const ContextMenu = () => {
         let found = hiddenFiles.find(
           (doc) =>
             doc.file_path ===
-            (currentContext.currentItem?.File
-              ? currentContext.currentItem?.File!['0']
-              : currentContext.currentItem?.Directory!['0']),
+            currentContext.currentItem?.name,
         );
 
         setIsHidden(found ? true : false);
@@ -348,7 +357,7 @@ export const ContextMenu = () => {
       mountPoint: runtime.currentDrive?.mount_point!,
     });
 
-    toast.success('Copied ' + (item.File ? item.File[0] : item.Directory![0]) + ' to clipboard.');
+    toast.success('Copied ' + item.name + ' to clipboard.');
   };
 
   const onPaste = () => {
@@ -357,60 +366,60 @@ export const ContextMenu = () => {
     if (!item) return;
 
     if (pasteboard.currentOperation === 'COPY') {
-      if (pasteboard.file && pasteboard.file['File']) {
+      if (pasteboard.file && !pasteboard.file.is_dir) {
         invoke('paste_file_at', {
-          from: pasteboard.file?.File![1],
-          destination: runtime.currentDrive?.mount_point + runtime.currentPath + pasteboard.file?.File![0],
+          from: pasteboard.file?.path,
+          destination: runtime.currentDrive?.mount_point + runtime.currentPath + pasteboard.file?.name,
         })
           .then(() => {
             DirectoryEmitter.emit('delete', {});
 
-            toast.success('Pasted ' + pasteboard.file?.File![0]);
+            toast.success('Pasted ' + pasteboard.file?.name);
           })
           .catch((err) => {
             console.error(err);
-            toast.error('Failed to paste ' + pasteboard.file?.File![0]);
+            toast.error('Failed to paste ' + pasteboard.file?.name);
           });
-      } else if (pasteboard.file && pasteboard.file['Directory']) {
+      } else if (pasteboard.file && pasteboard.file.is_dir) {
         invoke('paste_directory_at', {
-          from: pasteboard.file?.Directory![1],
-          destination: runtime.currentDrive?.mount_point + runtime.currentPath + pasteboard.file?.Directory![0],
+          from: pasteboard.file?.path,
+          destination: runtime.currentDrive?.mount_point + runtime.currentPath + pasteboard.file?.name,
         })
           .then(() => {
-            toast.success('Pasted ' + pasteboard.file?.Directory![0]);
+            toast.success('Pasted ' + pasteboard.file?.name);
             DirectoryEmitter.emit('delete', {});
           })
           .catch((err) => {
             console.error(err);
-            toast.error('Failed to paste ' + pasteboard.file?.Directory![0]);
+            toast.error('Failed to paste ' + pasteboard.file?.name);
           });
       }
     } else if (pasteboard.currentOperation === 'CUT') {
       invoke('cut_file_from', {
-        from: pasteboard.file?.File![1],
-        destination: runtime.currentDrive?.mount_point + runtime.currentPath + pasteboard.file?.File![0],
+        from: pasteboard.file?.path,
+        destination: runtime.currentDrive?.mount_point + runtime.currentPath + pasteboard.file?.name,
       })
         .then(() => {
           DirectoryEmitter.emit('delete', {});
 
-          toast.success('Cut ' + pasteboard.file?.File![0]);
+          toast.success('Cut ' + pasteboard.file?.name);
         })
         .catch((err) => {
           console.error(err);
-          toast.error('Failed to cut ' + pasteboard.file?.File![0]);
+          toast.error('Failed to cut ' + pasteboard.file?.name);
         });
-    } else if (pasteboard.file && pasteboard.file['Directory']) {
+    } else if (pasteboard.file && pasteboard.file.is_dir) {
       invoke('cut_directory_from', {
-        from: pasteboard.file?.Directory![1],
-        destination: runtime.currentDrive?.mount_point + runtime.currentPath + pasteboard.file?.Directory![0],
+        from: pasteboard.file?.path,
+        destination: runtime.currentDrive?.mount_point + runtime.currentPath + pasteboard.file?.name,
       })
         .then(() => {
-          toast.success('Cut ' + pasteboard.file?.Directory![0]);
+          toast.success('Cut ' + pasteboard.file?.name);
           DirectoryEmitter.emit('delete', {});
         })
         .catch((err) => {
           console.error(err);
-          toast.error('Failed to cut ' + pasteboard.file?.Directory![0]);
+          toast.error('Failed to cut ' + pasteboard.file?.name);
         });
     }
   };
@@ -432,32 +441,32 @@ export const ContextMenu = () => {
 
     if (!item) return;
 
-    if (item && item['File']) {
+    if (item && !item.is_dir) {
       invoke('paste_file_at', {
-        from: item.File![1],
-        destination: runtime.currentDrive?.mount_point + runtime.currentPath + item.File![0],
+        from: item.path,
+        destination: runtime.currentDrive?.mount_point + runtime.currentPath + item.name,
       })
         .then(() => {
-          toast.success('Duplicated ' + item.File![0]);
+          toast.success('Duplicated ' + item.name);
 
           DirectoryEmitter.emit('delete', {});
         })
         .catch((err) => {
           console.error(err);
-          toast.error('Failed to duplicate ' + item.File![0]);
+          toast.error('Failed to duplicate ' + item.name);
         });
-    } else if (item && item['Directory']) {
+    } else if (item && item.is_dir) {
       invoke('paste_directory_at', {
-        from: item?.Directory![1],
-        destination: runtime.currentDrive?.mount_point + runtime.currentPath + item?.Directory![0],
+        from: item?.path,
+        destination: runtime.currentDrive?.mount_point + runtime.currentPath + item?.name,
       })
         .then(() => {
-          toast.success('Duplicated ' + item?.Directory![0]);
+          toast.success('Duplicated ' + item?.name);
           DirectoryEmitter.emit('delete', {});
         })
         .catch((err) => {
           console.error(err);
-          toast.error('Failed to duplicate ' + item?.Directory![0]);
+          toast.error('Failed to duplicate ' + item?.name);
         });
     }
   };
@@ -476,9 +485,7 @@ export const ContextMenu = () => {
         hiddenFldrs = hiddenFldrs.filter(
           (doc) =>
             doc.file_path !==
-            (currentContext.currentItem?.File
-              ? currentContext.currentItem?.File!['0']
-              : currentContext.currentItem?.Directory!['0']),
+            currentContext.currentItem?.name,
         );
 
         await runtime.store.set(`profile-store-${runtime.currentUser}`, {
@@ -496,12 +503,12 @@ export const ContextMenu = () => {
 
     if (!item) return;
 
-    if (item['Directory']) {
-      invoke<number>('get_folder_size', { path: item['Directory']![1] })
+    if (item.is_dir) {
+      invoke<number>('get_folder_size', { path: item.path })
         .then((res) => {
           const newSizes = { ...cache.folderSizes };
 
-          newSizes[item['Directory']![1] as any] = { size: res };
+          newSizes[item.path as any] = { size: res };
 
           console.log(newSizes);
 
@@ -528,17 +535,17 @@ export const ContextMenu = () => {
       if (db) {
         let hiddenFldrs = db.hiddenFolders ? db.hiddenFolders : [];
 
-        if (item.File) {
+        if (!item.is_dir) {
           hiddenFldrs.push({
             uuid: generateUUID(),
-            file_path: item.File!['1'].replace(runtime.currentDrive?.mount_point!, ''),
+            file_path: item.path.replace(runtime.currentDrive?.mount_point!, ''),
             identifier: runtime.currentPath,
             mount_point: runtime.currentDrive?.mount_point,
           });
         } else {
           hiddenFldrs.push({
             uuid: generateUUID(),
-            file_path: item.Directory!['1'].replace(runtime.currentDrive?.mount_point!, ''),
+            file_path: item.path.replace(runtime.currentDrive?.mount_point!, ''),
             identifier: runtime.currentPath,
             mount_point: runtime.currentDrive?.mount_point,
           });
@@ -557,9 +564,7 @@ export const ContextMenu = () => {
           hiddenFolders: [
             {
               uuid: generateUUID(),
-              file_path: item.File
-                ? item.File!['1'].replace(runtime.currentDrive?.mount_point!, '')
-                : item.Directory!['1'].replace(runtime.currentDrive?.mount_point!, ''),
+              file_path: item.path.replace(runtime.currentDrive?.mount_point!, ''),
               identifier: runtime.currentPath,
               mount_point: runtime.currentDrive?.mount_point,
             },
@@ -578,10 +583,10 @@ export const ContextMenu = () => {
 
     if (!item) return;
 
-    if (item['Directory']) {
-      invoke('fetch_repo_for_directory', { path: item['Directory']![1] })
+    if (item.is_dir) {
+      invoke('fetch_repo_for_directory', { path: item.path })
         .then(() => {
-          toast.success('Fetched changes for ' + item['Directory']![0] + '.');
+          toast.success('Fetched changes for ' + item.name + '.');
         })
         .catch((err) => {
           console.log(err);
@@ -626,14 +631,14 @@ export const ContextMenu = () => {
 
     if (!item) return;
 
-    if (item['Directory']) {
-      invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
+    if (item.is_dir) {
+      invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
         if (res.can_stash) {
-          invoke('stash_changes_for_directory', { path: item['Directory']![1] })
+          invoke('stash_changes_for_directory', { path: item.path })
             .then(() => {
-              toast.success('Stashed changes for ' + item['Directory']![0] + '.');
+              toast.success('Stashed changes for ' + item.name + '.');
 
-              invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
+              invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
                 setGitMeta(res);
               });
             })
@@ -653,14 +658,14 @@ export const ContextMenu = () => {
 
     if (!item) return;
 
-    if (item['Directory']) {
-      invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
+    if (item.is_dir) {
+      invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
         if (res.can_stash) {
-          invoke('pull_changes_for_directory', { path: item['Directory']![1] })
+          invoke('pull_changes_for_directory', { path: item.path })
             .then(() => {
-              toast.success('Pulled changes for ' + item['Directory']![0] + '.');
+              toast.success('Pulled changes for ' + item.name + '.');
 
-              invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
+              invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
                 setGitMeta(res);
               });
             })
@@ -678,17 +683,17 @@ export const ContextMenu = () => {
 
     if (!item) return;
 
-    if (item['File']) {
-      invoke('extract_archive', { path: item['File']![1] })
+    if (!item.is_dir) {
+      invoke('extract_archive', { path: item.path })
         .then((res) => {
           console.log(res);
-          toast.success('Extracted ' + item['File']![0] + '.');
+          toast.success('Extracted ' + item.name + '.');
 
           DirectoryEmitter.emit('refresh', {});
         })
         .catch((err) => {
           console.log(err);
-          toast.error('Failed to extract ' + item['File']![0] + '.');
+          toast.error('Failed to extract ' + item.name + '.');
         });
     }
   };
@@ -698,17 +703,17 @@ export const ContextMenu = () => {
 
     if (!item) return;
 
-    if (item['Directory']) {
-      invoke('archive_folder', { path: item['Directory']![1] })
+    if (item.is_dir) {
+      invoke('archive_folder', { path: item.path })
         .then((res) => {
           console.log(res);
-          toast.success('Archived ' + item['Directory']![0] + '.');
+          toast.success('Archived ' + item.name + '.');
 
           DirectoryEmitter.emit('refresh', {});
         })
         .catch((err) => {
           console.log(err);
-          toast.error('Failed to archive ' + item['Directory']![0] + '.');
+          toast.error('Failed to archive ' + item.name + '.');
         });
     }
   };
@@ -718,14 +723,14 @@ export const ContextMenu = () => {
 
     if (!item) return;
 
-    if (item['Directory']) {
-      invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
+    if (item.is_dir) {
+      invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
         if (res.can_stash) {
-          invoke('push_changes_for_directory', { path: item['Directory']![1] })
+          invoke('push_changes_for_directory', { path: item.path })
             .then(() => {
-              toast.success('pushed changes for ' + item['Directory']![0] + '.');
+              toast.success('pushed changes for ' + item.name + '.');
 
-              invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
+              invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
                 setGitMeta(res);
               });
             })
@@ -742,14 +747,14 @@ export const ContextMenu = () => {
 
     if (!item) return;
 
-    if (item['Directory']) {
-      invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
+    if (item.is_dir) {
+      invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
         if (res.can_stash) {
-          invoke('checkout_branch_for_directory', { path: item['Directory']![1], branch })
+          invoke('checkout_branch_for_directory', { path: item.path, branch })
             .then((res) => {
-              toast.success(`Checked out branch ${branch} for ` + item['Directory']![0] + '.' + res);
+              toast.success(`Checked out branch ${branch} for ` + item.name + '.' + res);
 
-              invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
+              invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
                 setGitMeta(res);
               });
             })
@@ -766,15 +771,15 @@ export const ContextMenu = () => {
 
     if (!item) return;
 
-    if (item['Directory']) {
-      invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
-        invoke('commit_changes_for_directory', { path: item['Directory']![1], message: commitMessage })
+    if (item.is_dir) {
+      invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
+        invoke('commit_changes_for_directory', { path: item.path, message: commitMessage })
           .then((res) => {
-            toast.success(`Made a commit in ` + item['Directory']![0] + '.' + res);
+            toast.success(`Made a commit in ` + item.name + '.' + res);
 
             setCommitItem(null);
 
-            invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
+            invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
               setGitMeta(res);
             });
           })
@@ -792,13 +797,13 @@ export const ContextMenu = () => {
 
     if (!item) return;
 
-    if (item['Directory']) {
-      invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
-        invoke('add_all_changes', { path: item['Directory']![1] })
+    if (item.is_dir) {
+      invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
+        invoke('add_all_changes', { path: item.path })
           .then((res) => {
-            toast.success(`Added all changes in ` + item['Directory']![0] + '.' + res);
+            toast.success(`Added all changes in ` + item.name + '.' + res);
 
-            invoke<GitMeta>('get_git_meta_for_directory', { path: item['Directory']![1] }).then((res) => {
+            invoke<GitMeta>('get_git_meta_for_directory', { path: item.path }).then((res) => {
               setGitMeta(res);
             });
           })
@@ -814,7 +819,7 @@ export const ContextMenu = () => {
       if (db) {
         const updated_tag = { ...tag };
 
-        updated_tag.file_paths = updated_tag.file_paths.filter((doc) => doc.mount_point + doc.path !== item.File!['1']);
+        updated_tag.file_paths = updated_tag.file_paths.filter((doc) => doc.mount_point + doc.path !== item.path);
 
         const tags = db.tags ? db.tags : [];
 
@@ -833,7 +838,7 @@ export const ContextMenu = () => {
 
         await runtime.store.save();
 
-        toast.success(`Removed ${item['File']![0]} from ${tag.identifier} tag group.`);
+        toast.success(`Removed ${item.name} from ${tag.identifier} tag group.`);
       }
     });
   };
@@ -845,7 +850,7 @@ export const ContextMenu = () => {
 
         updated_tag.file_paths.push({
           mount_point: runtime.currentDrive?.mount_point,
-          path: item.File!['1'].replace(runtime.currentDrive?.mount_point!, ''),
+          path: item.path.replace(runtime.currentDrive?.mount_point!, ''),
         });
 
         const tags = db.tags ? db.tags : [];
@@ -863,7 +868,7 @@ export const ContextMenu = () => {
 
         await runtime.store.save();
 
-        toast.success(`Added ${item['File']![0]} to ${tag.identifier} tag group.`);
+        toast.success(`Added ${item.name} to ${tag.identifier} tag group.`);
       }
     });
   };
@@ -899,28 +904,28 @@ export const ContextMenu = () => {
 
     if (!isEncrypted) {
       invoke('encrypt_file', {
-        sourceFilePath: item['File']![1],
+        sourceFilePath: item.path,
         key: import.meta.env.VITE_ENCRYPTOR_KEY.trim(),
         iv: import.meta.env.VITE_ENCRYPTOR_IV.trim(),
       })
         .then((res) => {
-          toast.success('Encrypted ' + item['File']![0]);
+          toast.success('Encrypted ' + item.name);
         })
         .catch((err) => {
           console.error(err);
-          toast.error('Failed to encrypt ' + item['File']![0]);
+          toast.error('Failed to encrypt ' + item.name);
         });
     } else {
       invoke('decrypt_file', {
-        sourceFilePath: item['File']![1],
+        sourceFilePath: item.path,
         key: import.meta.env.VITE_ENCRYPTOR_KEY.trim(),
         iv: import.meta.env.VITE_ENCRYPTOR_IV.trim(),
       })
         .then((res) => {
-          toast.success('Decrypted ' + item['File']![0]);
+          toast.success('Decrypted ' + item.name);
         })
         .catch((err) => {
-          toast.error('Failed to decrypt ' + item['File']![0]);
+          toast.error('Failed to decrypt ' + item.name);
         });
     }
   };
@@ -930,7 +935,7 @@ export const ContextMenu = () => {
 
     if (!item) return;
 
-    invoke('open_with_explorer', { path: item.File ? item.File[1] : item.Directory![1] })
+    invoke('open_with_explorer', { path: item.path })
       .then((res) => {
         console.log(res);
       })
@@ -944,10 +949,10 @@ export const ContextMenu = () => {
 
     if (!item) return;
 
-    if (!item['File']) return;
+    if (item.is_dir) return;
 
     invoke('open_file', {
-      path: item['File']![1],
+      path: item.path,
     })
       .then((_) => {})
       .catch((err) => {
@@ -977,7 +982,7 @@ export const ContextMenu = () => {
             marginLeft: '5px',
           }}
         >
-          {preview.value.includes('data:') && !preview.loading && (
+          {preview.image && !preview.loading && (
             <>
               <p
                 className="mb-4"
@@ -991,7 +996,7 @@ export const ContextMenu = () => {
               <img src={preview.value} className="w-full rounded-lg shadow-lg mb-4 m-auto" />
             </>
           )}
-          {!preview.value.includes('data:') && !preview.loading && preview.value.length > 0 && (
+          {!preview.image && !preview.loading && preview.value.length > 0 && (
             <>
               <p
                 className="mb-4"
@@ -1017,9 +1022,9 @@ export const ContextMenu = () => {
             }}
           >
             {currentContext.currentItem &&
-              (currentContext.currentItem['File']
-                ? 'File: ' + currentContext.currentItem['File'][0]
-                : 'Folder: ' + currentContext.currentItem['Directory']![0])}
+              (!currentContext.currentItem.is_dir
+                ? 'File: ' + currentContext.currentItem.name
+                : 'Folder: ' + currentContext.currentItem.name)}
           </p>
         </div>
         <Separator />
@@ -1030,7 +1035,7 @@ export const ContextMenu = () => {
             if (tags.length < 1) return true;
 
             if (currentContext.currentItem) {
-              if (currentContext.currentItem['Directory']) {
+              if (currentContext.currentItem.is_dir) {
                 return true;
               }
             }
@@ -1042,7 +1047,7 @@ export const ContextMenu = () => {
             <Item
               onClick={() => {
                 let found = tag.file_paths.find(
-                  (doc) => doc.mount_point + doc.path === currentContext.currentItem?.File!['1'],
+                  (doc) => doc.mount_point + doc.path === currentContext.currentItem?.path,
                 );
 
                 if (!found) {
@@ -1054,7 +1059,7 @@ export const ContextMenu = () => {
               id="tag"
               key={key}
               disabled={() => {
-                if (currentContext.currentItem && currentContext.currentItem['Directory']) {
+                if (currentContext.currentItem && currentContext.currentItem.is_dir) {
                   return true;
                 }
 
@@ -1070,8 +1075,8 @@ export const ContextMenu = () => {
               <span>
                 {`${
                   currentContext.currentItem &&
-                  currentContext.currentItem.File &&
-                  tag.file_paths.find((doc) => doc.mount_point + doc.path === currentContext.currentItem?.File!['1'])
+                  !currentContext.currentItem.is_dir &&
+                  tag.file_paths.find((doc) => doc.mount_point + doc.path === currentContext.currentItem?.path)
                     ? 'Remove '
                     : ''
                 }` + tag.identifier}
@@ -1092,34 +1097,34 @@ export const ContextMenu = () => {
         >
           {isHidden ? 'Unhide' : 'Hide'}
         </Item>
-        {currentContext.currentItem && currentContext.currentItem['Directory'] && (
+        {currentContext.currentItem && currentContext.currentItem.is_dir && (
           <Item
             id="calculate-fldr-size"
             onClick={() => {
               if (currentContext.currentItem) {
-                if (!cache.folderSizes[currentContext.currentItem['Directory']![1] as any]) {
+                if (!cache.folderSizes[currentContext.currentItem.path as any]) {
                   calcFolderSize();
                 }
               }
             }}
           >
             <>
-              {cache.folderSizes[currentContext.currentItem['Directory']![1] as any]
+              {cache.folderSizes[currentContext.currentItem.path as any]
                 ? 'Total Size: ' +
-                  formatBytes(cache.folderSizes[currentContext.currentItem['Directory']![1] as any].size)
+                  formatBytes(cache.folderSizes[currentContext.currentItem.path as any].size)
                 : 'Calculate Folder Size'}
             </>
           </Item>
         )}
         <Item
-          disabled={currentContext.currentItem && currentContext.currentItem['File'] ? true : false}
+          disabled={currentContext.currentItem && !currentContext.currentItem.is_dir ? true : false}
           id="archive"
           onClick={handleArchive}
         >
           Archive
         </Item>
         <Item
-          disabled={currentContext.currentItem && currentContext.currentItem['Directory'] ? true : false}
+          disabled={currentContext.currentItem && currentContext.currentItem.is_dir ? true : false}
           id="unarchive"
           onClick={handleUnzip}
         >
@@ -1127,12 +1132,12 @@ export const ContextMenu = () => {
         </Item>
         <Item
           onClick={() => {
-            if (currentContext.currentItem && currentContext.currentItem['File']) {
+            if (currentContext.currentItem && !currentContext.currentItem.is_dir) {
               handleEncrypt();
             }
           }}
           id="encrypt"
-          disabled={currentContext.currentItem && currentContext.currentItem['File'] ? false : true}
+          disabled={currentContext.currentItem && !currentContext.currentItem.is_dir ? false : true}
         >
           {isEncrypted ? 'Decrypt' : 'Encrypt'}
         </Item>
@@ -1140,7 +1145,7 @@ export const ContextMenu = () => {
           <Item
             onClick={onOpen}
             id="open"
-            disabled={currentContext.currentItem && currentContext.currentItem['File'] ? false : true}
+            disabled={currentContext.currentItem && !currentContext.currentItem.is_dir ? false : true}
           >
             Open With Default App
           </Item>
@@ -1154,11 +1159,11 @@ export const ContextMenu = () => {
         <Item
           id="copy-path"
           onClick={() => {
-            if (currentContext.currentItem && currentContext.currentItem['File']) {
-              navigator.clipboard.writeText(currentContext.currentItem['File']![1]);
+            if (currentContext.currentItem && !currentContext.currentItem.is_dir) {
+              navigator.clipboard.writeText(currentContext.currentItem.path);
               toast.success('Copied path to clipboard.');
-            } else if (currentContext.currentItem && currentContext.currentItem['Directory']) {
-              navigator.clipboard.writeText(currentContext.currentItem['Directory']![1]);
+            } else if (currentContext.currentItem && currentContext.currentItem.is_dir) {
+              navigator.clipboard.writeText(currentContext.currentItem.path);
               toast.success('Copied path to clipboard.');
             }
           }}
@@ -1176,7 +1181,7 @@ export const ContextMenu = () => {
             Copy
           </Item>
           <Item disabled={pasteboard.currentOperation === 'NONE'} id="paste" onClick={onPaste}>
-            Paste {pasteboard.file?.File ? pasteboard.file.File[0] : pasteboard.file?.Directory![0]}
+            Paste {pasteboard.file?.name}
           </Item>
           <Item id="rename">Rename</Item>
           <Item id="delete" onClick={onDelete} color="red">
@@ -1214,7 +1219,7 @@ export const ContextMenu = () => {
               </p>
             </div>
           }
-          disabled={!currentContext.currentItem?.Directory || !hasGit ? true : false}
+          disabled={!currentContext.currentItem?.is_dir || !hasGit ? true : false}
         >
           <Item id="init" disabled={!gitMeta?.can_init} onClick={onInit}>
             Init
@@ -87,22 +87,22 @@ export const SearchModal = ({ show, setShow }: { show: boolean; setShow: (show:
 
         if (sortBy === 'Name') {
           sorted = [...res.results].sort((a, b) => {
-            const nameA = a.Directory ? a.Directory[0] : a.File ? a.File[0] : '';
-            const nameB = b.Directory ? b.Directory[0] : b.File ? b.File[0] : '';
+            const nameA = a.name;
+            const nameB = b.name;
 
             return nameA.localeCompare(nameB);
           });
         } else if (sortBy === 'Date') {
           sorted = [...res.results].sort((a, b) => {
-            const nameA = a.Directory ? a.Directory[3] : a.File ? a.File[3] : '';
-            const nameB = b.Directory ? b.Directory[3] : b.File ? b.File[3] : '';
+            const nameA = a.modified ?? 0;
+            const nameB = b.modified ?? 0;
 
-            return nameA < nameB ? 1 : -1;
+            return nameA > nameB ? 1 : -1;
           });
         } else if (sortBy === 'Size') {
           sorted = [...res.results].sort((a, b) => {
-            const nameA = a.Directory ? a.Directory[2] : a.File ? a.File[2] : '';
-            const nameB = b.Directory ? b.Directory[2] : b.File ? b.File[2] : '';
+            const nameA = a.size ?? 0;
+            const nameB = b.size ?? 0;
 
             return nameA < nameB ? 1 : -1;
           });
@@ -142,13 +142,13 @@ export const SearchModal = ({ show, setShow }: { show: boolean; setShow: (show:
   const clickedSearch = (result: DirectoryContents) => {
     const device = runtime.devices.find((device) => {
       return device.mount_point.includes(
-        result['Directory'] ? result['Directory']![1].slice(0, 1) : result['File']![1].slice(0, 2),
+        result.is_dir ? result.path.slice(0, 1) : result.path.slice(0, 2),
       );
     });
 
     const deviceIndex = runtime.devices.findIndex((device) => {
       return device.mount_point.includes(
-        result['Directory'] ? result['Directory']![1].slice(0, 1) : result['File']![1].slice(0, 2),
+        result.is_dir ? result.path.slice(0, 1) : result.path.slice(0, 2),
       );
     });
 
@@ -157,13 +157,13 @@ export const SearchModal = ({ show, setShow }: { show: boolean; setShow: (show:
 
       navigate(
         `/drive/${deviceIndex}?path=${encodeURIComponent(
-          result['Directory']
-            ? result['Directory']![1] === '/'
+          result.is_dir
+            ? result.path === '/'
               ? ''
-              : removeAllAfterLastSlash(result['Directory']![1].replace(result['Directory']![1].slice(0, 2), ''))
-            : result['File']![1] === '/'
+              : removeAllAfterLastSlash(result.path.replace(result.path.slice(0, 2), ''))
+            : result.path === '/'
             ? ''
-            : removeAllAfterLastSlash(result['File']![1].replace(device?.mount_point, '')),
+            : removeAllAfterLastSlash(result.path.replace(device?.mount_point, '')),
         )}&mount=${encodeURIComponent(device.mount_point)}`,
       );
     }
@@ -479,7 +479,7 @@ export const SearchModal = ({ show, setShow }: { show: boolean; setShow: (show:
                     className="font-light py-1 mb-1 flex items-center justify-between w-full cursor-pointer opacity-50 hover:ml-2 hover:opacity-100 transition-all"
                   >
                     <>
-                      {result['Directory'] ? (
+                      {result.is_dir ? (
                         <svg xmlns="http://www.w3.org/2000/svg" width="24" height="24" viewBox="0 0 24 24">
                           <path
                             fill="white"
@@ -496,7 +496,7 @@ export const SearchModal = ({ show, setShow }: { show: boolean; setShow: (show:
                           opacity: 'var(--light-text-opacity)',
                         }}
                       >
-                        {result['Directory'] ? result['Directory']![0] : result['File']![0]}
+                        {result.name}
                       </p>
                     </>
                     <p
@@ -506,9 +506,7 @@ export const SearchModal = ({ show, setShow }: { show: boolean; setShow: (show:
                         opacity: 'var(--light-text-opacity)',
                       }}
                     >
-                      {result['Directory']
-                        ? removeLastCharOf(formatLongText(result['Directory']![1], 45))
-                        : removeLastCharOf(formatLongText(result['File']![1], 45))}
+                      {removeLastCharOf(formatLongText(result.path, 45))}
                     </p>
                   </div>
                 ))}
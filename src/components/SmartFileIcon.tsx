@@ -122,9 +122,9 @@ export const SmartFileIcon = ({ file }: { file: DirectoryContents }) => {
   const [icon, setIcon] = useState<React.ReactNode>(null);
 
   useEffect(() => {
-    if (!file['File']) return;
+    if (file.is_dir) return;
 
-    const fileExtension = file['File'][0].split('.').pop()?.toLowerCase() || 'default';
+    const fileExtension = file.name.split('.').pop()?.toLowerCase() || 'default';
 
     setIcon(iconMappings[fileExtension] ? iconMappings[fileExtension] : iconMappings['default']);
   }, [file]);
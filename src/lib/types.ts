@@ -93,17 +93,43 @@ export interface Profile {
   lastUsed: string;
 }
 
+// Mirrors `FileCategory` in src-tauri/src/filesystem/mime.rs.
+export type FileCategory =
+  | 'Directory'
+  | 'Image'
+  | 'Audio'
+  | 'Video'
+  | 'Text'
+  | 'Document'
+  | 'Archive'
+  | 'Executable'
+  | 'Font'
+  | 'Other';
+
+// Mirrors `DirectoryChild` in src-tauri/src/filesystem/volume.rs.
+// Timestamps are seconds since the Unix epoch.
 export interface DirectoryContents {
-  Directory?: [string, string, number, number, string, boolean, boolean];
-  File?: [string, string, number, number, string, boolean];
+  name: string;
+  path: string;
+  is_dir: boolean;
+  size: number | null;
+  created: number | null;
+  modified: number | null;
+  accessed: number | null;
+  file_type: string;
+  mime_type: string;
+  category: FileCategory;
+  permissions: number | null;
+  owner: string | null;
+  group: string | null;
+  hidden: boolean;
+  readonly: boolean;
+  symlink_target: string | null;
+  broken_link: boolean;
+  is_git: boolean;
+  is_project: boolean;
 }
 
-// #[derive(Serialize, Deserialize, Clone)]
-// pub enum DirectoryChild {
-//     File(String, String, u64, u64, String), // Name of file, path to file, size of file, last modified seconds, type of file
-//     Directory(String, String, u64, u64, String, bool, bool), // Name of directory, path to directory, size of directory, last modified seconds, is git repo, isSupportedProject
-// }
-
 export interface SearchResult {
   results: DirectoryContents[];
   more: boolean;
@@ -142,6 +168,20 @@ export interface ThemeJSONSchema {
   scrollbarThumbHoverColor?: string;
   scrollbarThumbActiveColor?: string;
   scrollbarTrackColor?: string;
+  // Colours of highlighted code previews, by token kind, e.g. { keyword: '#569cd6' }.
+  syntax?: {
+    plain?: string;
+    keyword?: string;
+    type?: string;
+    constant?: string;
+    function?: string;
+    string?: string;
+    number?: string;
+    comment?: string;
+    operator?: string;
+    tag?: string;
+    attribute?: string;
+  };
 }
 
 export enum ProjectType {
@@ -0,0 +1,16 @@
+import { convertFileSrc } from '@tauri-apps/api/tauri';
+
+export type BytesKind = 'file' | 'thumbnail' | 'waveform';
+
+export type ThumbnailSize = 'normal' | 'large' | 'x-large' | 'xx-large';
+
+// URL of a file, or of a preview of it, served by the `bytes` protocol in
+// src-tauri/src/filesystem/protocol.rs. Works as the `src` of img, audio and video tags.
+export const bytesUrl = (path: string, kind: BytesKind = 'file', size?: ThumbnailSize): string => {
+  const url = convertFileSrc(path, 'bytes');
+  if (kind === 'file') {
+    return url;
+  }
+
+  return size ? `${url}?kind=${kind}&size=${size}` : `${url}?kind=${kind}`;
+};
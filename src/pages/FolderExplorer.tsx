@@ -18,7 +18,6 @@ import { currentContextState } from '../lib/state/currentContext.state';
 import { runtimeState } from '../lib/state/runtime.state';
 import { DirectoryContents, ProfileStore } from '../lib/types';
 import { formatBytes } from '../lib/utils/formatBytes';
-import { secondsAgoToDate } from '../lib/utils/secondsToTimestamp';
 
 export const FolderExplorer = () => {
   const [runtime, setRuntime] = useRecoilState(runtimeState);
@@ -43,10 +42,8 @@ export const FolderExplorer = () => {
     } else if (val === 'ASC') {
       setSortedDirectories(
         [...directories].sort((a, b) => {
-          if (a.File && b.File) {
-            return a.File![0].localeCompare(b.File![0]);
-          } else if (a.Directory && b.Directory) {
-            return a.Directory![0].localeCompare(b.Directory![0]);
+          if (a.is_dir === b.is_dir) {
+            return a.name.localeCompare(b.name);
           } else {
             return 0;
           }
@@ -56,10 +53,8 @@ export const FolderExplorer = () => {
     } else if (val === 'DESC') {
       setSortedDirectories(
         [...directories].sort((a, b) => {
-          if (a.File && b.File) {
-            return b.File![0].localeCompare(a.File![0]);
-          } else if (a.Directory && b.Directory) {
-            return b.Directory![0].localeCompare(a.Directory![0]);
+          if (a.is_dir === b.is_dir) {
+            return b.name.localeCompare(a.name);
           } else {
             return 0;
           }
@@ -69,10 +64,8 @@ export const FolderExplorer = () => {
     } else if (val === 'SIZE') {
       setSortedDirectories(
         [...directories].sort((a, b) => {
-          if (a.File && b.File) {
-            return a.File![2] - b.File![2];
-          } else if (a.Directory && b.Directory) {
-            return a.Directory![2] - b.Directory![2];
+          if (a.is_dir === b.is_dir) {
+            return (a.size ?? 0) - (b.size ?? 0);
           } else {
             return 0;
           }
@@ -82,9 +75,9 @@ export const FolderExplorer = () => {
     } else if (val === 'TYPE') {
       setSortedDirectories(
         [...directories].sort((a, b) => {
-          if (a.File && b.File) {
-            return a.File![1].localeCompare(b.File![1]);
-          } else if (a.Directory && b.Directory) {
+          if (!a.is_dir && !b.is_dir) {
+            return a.path.localeCompare(b.path);
+          } else if (a.is_dir && b.is_dir) {
             return -1;
           } else {
             return 0;
@@ -160,7 +153,7 @@ export const FolderExplorer = () => {
 
           const filtered = dirs.filter((dir) => {
             const isHidden = db.hiddenFolders.find((file) => {
-              return file.file_path === (dir.File ? dir.File!['0'] : dir.Directory!['0']);
+              return file.file_path === dir.name;
             });
 
             return isHidden ? false : true;
@@ -739,7 +732,7 @@ export const FolderExplorer = () => {
             px-4 py-2 cursor-pointer transition-all hover:opacity-50 items-center"
                   key={key}
                 >
-                  {directory['Directory'] ? (
+                  {directory.is_dir ? (
                     <svg xmlns="http://www.w3.org/2000/svg" width="24" height="24" viewBox="0 0 24 24">
                       <path
                         fill="var(--icon-color)"
@@ -752,7 +745,7 @@ export const FolderExplorer = () => {
                   <div
                     className="ml-4 flex items-center justify-between w-full pr-12"
                     onClick={() => {
-                      if (directory['Directory']) {
+                      if (directory.is_dir) {
                         setNavigationRules((prevRules) => ({
                           forward: [],
                           back: [...prevRules.back, runtime.currentPath],
@@ -760,14 +753,14 @@ export const FolderExplorer = () => {
 
                         setRuntime({
                           ...runtime,
-                          currentPath: runtime.currentPath + directory['Directory']![0] + '/',
+                          currentPath: runtime.currentPath + directory.name + '/',
                         });
                       }
                     }}
                     onDoubleClick={() => {
-                      if (directory['File']) {
+                      if (!directory.is_dir) {
                         invoke('open_file', {
-                          path: directory['File']![1],
+                          path: directory.path,
                         })
                           .then((_) => {})
                           .catch((err) => {
@@ -777,26 +770,18 @@ export const FolderExplorer = () => {
                     }}
                   >
                     <p className="min-w-[250px] text-ellipsis truncate w-[250px]">
-                      {directory['Directory'] ? directory['Directory'][0] : directory['File']![0]}
+                      {directory.name}
                     </p>
                     <Moment
                       fromNow
-                      date={secondsAgoToDate(
-                        directory['Directory'] ? directory['Directory']![3] : directory['File']![3],
-                      ).toISOString()}
+                      date={new Date((directory.modified ?? 0) * 1000).toISOString()}
                       className="min-w-[250px] flex justify-left opacity-50 pl-2"
                     />
                     <p className="min-w-[250px] flex justify-left opacity-50 pl-6">
-                      {directory['Directory']
-                        ? directory['Directory']![4] === 'File'
-                          ? 'Folder'
-                          : directory['Directory']![4]
-                        : directory['File']![4]}
+                      {directory.file_type}
                     </p>
                     <p className="opacity-50 min-w-[250px] flex justify-left pl-8">
-                      {directory['Directory']
-                        ? formatBytes(directory['Directory']![2])
-                        : formatBytes(directory['File']![2])}
+                      {directory.size !== null ? formatBytes(directory.size) : '--'}
                     </p>
                   </div>
                 </div>
@@ -811,7 +796,7 @@ export const FolderExplorer = () => {
             px-4 py-2 cursor-pointer transition-all hover:opacity-50 items-center"
                   key={key}
                 >
-                  {directory['Directory'] ? (
+                  {directory.is_dir ? (
                     <svg xmlns="http://www.w3.org/2000/svg" width="24" height="24" viewBox="0 0 24 24">
                       <path
                         fill="var(--icon-color)"
@@ -824,7 +809,7 @@ export const FolderExplorer = () => {
                   <div
                     className="ml-4 flex items-center justify-between w-full pr-12"
                     onClick={() => {
-                      if (directory['Directory']) {
+                      if (directory.is_dir) {
                         setNavigationRules((prevRules) => ({
                           forward: [],
                           back: [...prevRules.back, runtime.currentPath],
@@ -832,14 +817,14 @@ export const FolderExplorer = () => {
 
                         setRuntime({
                           ...runtime,
-                          currentPath: runtime.currentPath + directory['Directory']![0] + '/',
+                          currentPath: runtime.currentPath + directory.name + '/',
                         });
                       }
                     }}
                     onDoubleClick={() => {
-                      if (directory['File']) {
+                      if (!directory.is_dir) {
                         invoke('open_file', {
-                          path: directory['File']![1],
+                          path: directory.path,
                         })
                           .then((_) => {})
                           .catch((err) => {
@@ -849,26 +834,18 @@ export const FolderExplorer = () => {
                     }}
                   >
                     <p className="min-w-[250px] text-ellipsis truncate w-[250px]">
-                      {directory['Directory'] ? directory['Directory'][0] : directory['File']![0]}
+                      {directory.name}
                     </p>
                     <Moment
                       fromNow
-                      date={secondsAgoToDate(
-                        directory['Directory'] ? directory['Directory']![3] : directory['File']![3],
-                      ).toISOString()}
+                      date={new Date((directory.modified ?? 0) * 1000).toISOString()}
                       className="min-w-[250px] flex justify-left opacity-50 pl-2"
                     />
                     <p className="min-w-[250px] flex justify-left opacity-50 pl-6">
-                      {directory['Directory']
-                        ? directory['Directory']![4] === 'File'
-                          ? 'Folder'
-                          : directory['Directory']![4]
-                        : directory['File']![4]}
+                      {directory.file_type}
                     </p>
                     <p className="opacity-50 min-w-[250px] flex justify-left pl-8">
-                      {directory['Directory']
-                        ? formatBytes(directory['Directory']![2])
-                        : formatBytes(directory['File']![2])}
+                      {directory.size !== null ? formatBytes(directory.size) : '--'}
                     </p>
                   </div>
                 </div>
@@ -116,7 +116,7 @@ export const Tags = () => {
           <div className="flex flex-col">
             {results?.map(
               (result, key) =>
-                result.File && (
+                !result.is_dir && (
                   <div className="flex items-center cursor-pointer justify-between opacity-50 hover:ml-2 hover:opacity-100 transition-all">
                     <div className="flex items-center mt-4">
                       <SmartFileIcon file={result} />
@@ -125,9 +125,9 @@ export const Tags = () => {
                         onClick={() => {
                           const device = runtime.devices.find((device) => {
                             return device.mount_point.includes(
-                              result['Directory']
-                                ? result['Directory']![1].slice(0, 1)
-                                : result['File']![1].slice(0, 2),
+                              result.is_dir
+                                ? result.path.slice(0, 1)
+                                : result.path.slice(0, 2),
                             );
                           });
 
@@ -135,27 +135,27 @@ export const Tags = () => {
 
                           const deviceIndex = runtime.devices.findIndex((device) => {
                             return device.mount_point.includes(
-                              result['Directory']
-                                ? result['Directory']![1].slice(0, 1)
-                                : result['File']![1].slice(0, 2),
+                              result.is_dir
+                                ? result.path.slice(0, 1)
+                                : result.path.slice(0, 2),
                             );
                           });
 
                           navigate(
                             `/drive/${deviceIndex}?path=${encodeURIComponent(
-                              result['File']![1] === '/'
+                              result.path === '/'
                                 ? ''
-                                : result['File']![1].replace(result['File']![0], '').replace(device?.mount_point, ''),
+                                : result.path.replace(result.name, '').replace(device?.mount_point, ''),
                             )}&mount=${encodeURIComponent(device.mount_point)}`,
                           );
                         }}
                         key={key}
                       >
-                        {result.File![0]}
+                        {result.name}
                       </p>
                     </div>
                     <p className="w-1/2 flex items-center justify-end mr-2">
-                      {removeLastCharOf(formatLongText(result['File']![1], 45))}
+                      {removeLastCharOf(formatLongText(result.path, 45))}
                     </p>
                   </div>
                 ),